@@ -25,6 +25,7 @@ pub(super) fn plugin(app: &mut App) {
 
     app.add_plugins((
         PopupPlugin,
+        ToastPlugin,
         pause::plugin,
         #[cfg(feature = "dev")]
         dev_tools::plugin,
@@ -1,16 +1,21 @@
 //! ### 游戏事件处理与转发
-//! 注意⚠️：服务器发出的 GameEvent 只能在 [receive_event_from_server()] 这个函数里面接受并转发到本地系统
+//! 注意⚠️：服务器发出的 [`ServerEvent`] 只能在 [receive_event_from_server()] 这个函数里面接受，
+//! 其中的 [`GameEvent`] 转发到本地系统
 //!
-//! 发送到服务器的 GameEvent 没有限制，但因为安全原因部分事件会被服务器拦截
+//! 发送到服务器的是 [`ClientCommand`]，不包含执行的玩家和座位，由服务器按连接确定
 
 use bevy::prelude::*;
-use bevy_renet2::prelude::{RenetClient, ServerEvent, client_connected};
-use shared::event::GameEvent;
+use bevy_renet2::prelude::{RenetClient, client_connected};
+use shared::chat::ChatMessage;
+use shared::event::{ClientCommand, GameEvent, ServerEvent};
+use shared::the_hidden_card::state::PendingTurn;
+use std::time::Duration;
 
 use crate::core::AppSystems;
 use crate::game::bincode::BincodeConfig;
+use crate::menus::{LobbyRooms, MatchQueue};
 use crate::network::MessageEvent;
-use crate::prelude::{ClosePopupEvent, OpenPopupEvent};
+use crate::prelude::{ClosePopupEvent, OpenPopupEvent, ToastEvent};
 use crate::screens::ScreenState;
 use crate::theme::widget::{body_text, button_mid, card_display, text_base};
 use shared::{Player};
@@ -25,37 +30,95 @@ pub(crate) fn plugin(app: &mut App) {
             .run_if(resource_exists::<RenetClient>)
             .run_if(resource_exists::<Player>),
     );
+    app.add_systems(
+        OnExit(ScreenState::Gameplay),
+        (
+            stop_spectating.run_if(resource_exists::<Spectating>),
+            clear_turn_countdown,
+        ),
+    );
     // app.add_systems(PostUpdate, receive_event_from_server.)
 }
 
+/// 当前所在的房间，牌桌上显示房间号方便分享
+#[derive(Resource)]
+pub struct CurrentRoom {
+    pub code: String,
+}
+
+/// 正在观战，牌桌不显示手牌和操作弹窗
+#[derive(Resource)]
+pub struct Spectating;
+
+/// 当前操作的倒计时，按收到时服务器给的剩余时间计时
+#[derive(Resource)]
+pub struct TurnCountdown {
+    pub turn: PendingTurn,
+    pub timer: Timer,
+}
+
 /// 接受来自服务器的事件，并将其转换为游戏事件，然后发送给本地游戏事件系统。
 fn receive_event_from_server(
     mut cmds: Commands,
     mut finished: Local<bool>,
     mut client: ResMut<RenetClient>,
     mut game_event_writer: EventWriter<GameEvent>,
+    mut chat_writer: EventWriter<ChatMessage>,
     mut bincode_config: Res<BincodeConfig>,
     mut next_screen: ResMut<NextState<ScreenState>>,
+    match_queue: Option<Res<MatchQueue>>,
+    mut lobby_rooms: ResMut<LobbyRooms>,
 ) {
-    use GameEvent::*;
+    use ServerEvent::*;
+    let mut matching = match_queue.is_some();
     while let Some(message) = client.receive_message(0) {
-        let (event, _len): (GameEvent, usize) =
+        let (event, _len): (ServerEvent, usize) =
             bincode::serde::decode_from_slice(&message, bincode_config.0).unwrap();
 
         info!("Received event {}", event);
         match event {
-            JoinRoomOk { room_id } => {
+            RoomList(rooms) => lobby_rooms.0 = rooms,
+            Lobby(event) => event.apply(&mut lobby_rooms.0),
+            JoinRoomOk { room_id, code } => {
                 *finished = true;
+                if matching {
+                    close_match_popup(&mut cmds);
+                    matching = false;
+                }
+                cmds.insert_resource(CurrentRoom { code });
+                cmds.remove_resource::<Spectating>();
                 // 收到加入房间成功事件，进入游戏屏
                 next_screen.set(ScreenState::Gameplay);
-                game_event_writer.write(event);
             },
-            ReJoinRoomOk { room_id } => {
+            ReJoinRoomOk { room_id, code } => {
+                cmds.insert_resource(CurrentRoom { code });
+                cmds.remove_resource::<Spectating>();
                 next_screen.set(ScreenState::Gameplay);
-                game_event_writer.write(event);
                 // 关闭询问是否重新加入房间的弹窗
                 cmds.trigger(ClosePopupEvent);
             },
+            SpectateOk { room_id, code } => {
+                cmds.insert_resource(CurrentRoom { code });
+                cmds.insert_resource(Spectating);
+                next_screen.set(ScreenState::Gameplay);
+            },
+            SpectateEnded => {
+                cmds.remove_resource::<Spectating>();
+                cmds.trigger(ToastEvent("观战的房间已关闭".to_string()));
+                next_screen.set(ScreenState::Title);
+            },
+            MatchQueued { position, estimated_wait_secs } => {
+                matching = true;
+                cmds.insert_resource(MatchQueue::new(position, estimated_wait_secs));
+            },
+            MatchCancelled => {
+                if matching {
+                    close_match_popup(&mut cmds);
+                    matching = false;
+                }
+            },
+            RoomError(err) => cmds.trigger(ToastEvent(err.to_string())),
+            ActionRejected(err) => cmds.trigger(ToastEvent(err.to_string())),
             AskForRejoinRoom(room_id) => cmds.trigger(OpenPopupEvent {
                 content_builder: Box::new(|parent| {
                     parent.spawn(card_display(
@@ -68,26 +131,46 @@ fn receive_event_from_server(
                 }),
                 blocking: true
             }),
-            _ => {
-                game_event_writer.write(event);
+            Game(event) => {
+                game_event_writer.write(*event);
             },
+            Chat(message) => {
+                chat_writer.write(message);
+            },
+            TurnDeadline(Some(deadline)) => {
+                let total = Duration::from_millis(deadline.total_ms);
+                let mut timer = Timer::new(total, TimerMode::Once);
+                timer.set_elapsed(total.saturating_sub(Duration::from_millis(deadline.remaining_ms)));
+                cmds.insert_resource(TurnCountdown { turn: deadline.turn, timer });
+            },
+            TurnDeadline(None) => cmds.remove_resource::<TurnCountdown>(),
         }
     }
 }
 
-fn rejoin_button_click(_: Trigger<Pointer<Click>>, mut cmds: Commands, local_player: Res<Player>) {
-    let event = GameEvent::ReJoinRoom {
-        player: local_player.clone(),
-    };
+/// 匹配成功或取消后关闭排队弹窗
+fn close_match_popup(cmds: &mut Commands) {
+    cmds.remove_resource::<MatchQueue>();
+    cmds.trigger(ClosePopupEvent);
+}
+
+fn clear_turn_countdown(mut cmds: Commands) {
+    cmds.remove_resource::<TurnCountdown>();
+}
+
+/// 离开牌桌时通知服务器停止观战
+fn stop_spectating(mut cmds: Commands) {
+    cmds.remove_resource::<Spectating>();
+    cmds.trigger(MessageEvent(ClientCommand::LeaveRoom));
+}
+
+fn rejoin_button_click(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    let event = ClientCommand::ReJoinRoom;
     cmds.trigger(MessageEvent(event));
 }
 
-fn cancel_rejoin_button_click(
-    _: Trigger<Pointer<Click>>,
-    mut cmds: Commands,
-    local_player: Res<Player>,
-) {
-    let event = GameEvent::PlayerLeave(local_player.id);
+fn cancel_rejoin_button_click(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    let event = ClientCommand::LeaveRoom;
     cmds.trigger(MessageEvent(event));
     cmds.trigger(ClosePopupEvent);
 }
@@ -0,0 +1,77 @@
+//! 托管：玩家可以自己开启或取消托管，托管中由服务器代替出牌
+//!
+//! 断线的玩家由服务器自动托管，重新连接后收回控制，托管的座位显示标记，见 [`seat`](super::seat)
+
+use shared::Player;
+use shared::event::ClientCommand;
+use shared::the_hidden_card::state::GameState;
+
+use crate::game::event::Spectating;
+use crate::game::hidden_card::level::spawn_level;
+use crate::network::MessageEvent;
+use crate::prelude::*;
+use crate::screens::ScreenState;
+use crate::theme::widget;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        OnEnter(ScreenState::Gameplay),
+        spawn_auto_play_button
+            .after(spawn_level)
+            .run_if(not(resource_exists::<Spectating>)),
+    );
+    app.add_systems(
+        Update,
+        update_auto_play_button
+            .in_set(AppSystems::Update)
+            .run_if(in_state(ScreenState::Gameplay))
+            .run_if(resource_changed::<GameState>),
+    );
+}
+
+#[derive(Component)]
+struct AutoPlayButton;
+
+fn spawn_auto_play_button(mut cmds: Commands) {
+    cmds.spawn((
+        Name::new("Auto Play"),
+        Node {
+            top: Vw(4.),
+            left: Vw(1.),
+            ..Node::DEFAULT.abs()
+        },
+        Pickable::IGNORE,
+        GlobalZIndex(1),
+        StateScoped(ScreenState::Gameplay),
+        children![(widget::button_compact("托管", toggle_auto_play), AutoPlayButton)],
+    ));
+}
+
+fn toggle_auto_play(
+    _: Trigger<Pointer<Click>>,
+    mut cmds: Commands,
+    state: Res<GameState>,
+    local_player: Res<Player>,
+) {
+    let seat = r!(state.get_seat_by_id(local_player.id));
+    cmds.trigger(MessageEvent(ClientCommand::AutoPlay(!seat.auto_play)));
+}
+
+/// 托管中按钮显示为取消托管
+fn update_auto_play_button(
+    state: Res<GameState>,
+    local_player: Res<Player>,
+    button_query: Query<&Children, With<AutoPlayButton>>,
+    mut text_query: Query<&mut Text>,
+) {
+    let auto_play = state.get_seat_by_id(local_player.id).is_some_and(|seat| seat.auto_play);
+    let label = if auto_play { "取消托管" } else { "托管" };
+    for children in button_query.iter() {
+        for child in children.iter() {
+            let mut text = c!(text_query.get_mut(child));
+            if text.0 != label {
+                text.0 = label.to_string();
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! 房间聊天：发送者座位上的气泡和可以收起的聊天记录
+//!
+//! 聊天记录面板展开时键盘输入写入草稿，回车发送，此时 `P` 键不再暂停游戏
+
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use shared::chat::{ChatContent, ChatMessage, Emote, MAX_CHAT_CHARS, QuickPhrase};
+use shared::event::ClientCommand;
+use shared::the_hidden_card::state::GameState;
+use std::collections::VecDeque;
+
+use crate::game::hidden_card::level::spawn_level;
+use crate::game::hidden_card::seat::ShowSpeechBubble;
+use crate::network::MessageEvent;
+use crate::prelude::*;
+use crate::screens::ScreenState;
+use crate::theme::widget;
+
+/// 保留的聊天记录条数
+const MAX_CHAT_LOG: usize = 50;
+/// 面板中显示的聊天记录条数
+const VISIBLE_CHAT_LINES: usize = 8;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_event::<ChatMessage>();
+    app.init_resource::<ChatLog>();
+    app.init_resource::<ChatPanel>();
+    app.add_systems(
+        OnEnter(ScreenState::Gameplay),
+        spawn_chat_panel.after(spawn_level),
+    );
+    app.add_systems(OnExit(ScreenState::Gameplay), reset_chat);
+    app.add_systems(
+        Update,
+        (
+            handle_chat_message,
+            type_chat_draft.run_if(chat_open),
+            render_chat_panel.run_if(resource_changed::<ChatLog>.or(resource_changed::<ChatPanel>)),
+        )
+            .chain()
+            .in_set(AppSystems::Update)
+            .run_if(in_state(ScreenState::Gameplay)),
+    );
+}
+
+/// 收到的聊天记录，离开牌桌后清空
+#[derive(Resource, Default)]
+struct ChatLog(VecDeque<String>);
+
+/// 聊天面板是否展开和正在输入的内容
+#[derive(Resource, Default)]
+pub struct ChatPanel {
+    open: bool,
+    draft: String,
+}
+
+/// 聊天面板展开时键盘用于输入
+pub fn chat_open(panel: Option<Res<ChatPanel>>) -> bool {
+    panel.is_some_and(|panel| panel.open)
+}
+
+#[derive(Component)]
+struct ChatPanelBody;
+
+#[derive(Component)]
+struct ChatLogList;
+
+#[derive(Component)]
+struct ChatDraftText;
+
+fn spawn_chat_panel(mut cmds: Commands) {
+    cmds.spawn((
+        Name::new("Chat Panel"),
+        Node {
+            top: Vw(1.),
+            right: Vw(1.),
+            width: Vw(28.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::End,
+            row_gap: Vw(0.5),
+            ..Node::DEFAULT.abs()
+        },
+        Pickable::IGNORE,
+        GlobalZIndex(1),
+        StateScoped(ScreenState::Gameplay),
+        children![
+            widget::button_compact("聊天", toggle_chat_panel),
+            (
+                Node {
+                    width: Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Vw(0.5),
+                    padding: UiRect::all(Vw(0.8)),
+                    display: Display::None,
+                    ..default()
+                },
+                BorderRadius::all(Vw(1.)),
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                ChatPanelBody,
+                children![
+                    (
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            ..default()
+                        },
+                        ChatLogList,
+                    ),
+                    (
+                        text_base("", Vw(1.6), ThemeColor::BODY_TEXT_LIGHT),
+                        ChatDraftText,
+                    ),
+                    (
+                        Node {
+                            flex_wrap: FlexWrap::Wrap,
+                            gap: Vw(0.5).into(),
+                            ..Node::ROW
+                        },
+                        Children::spawn(SpawnIter(QuickPhrase::ALL.into_iter().map(|phrase| {
+                            widget::button_compact(phrase.to_string(), send_content(ChatContent::Phrase(phrase)))
+                        }))),
+                    ),
+                    (
+                        Node {
+                            flex_wrap: FlexWrap::Wrap,
+                            gap: Vw(0.5).into(),
+                            ..Node::ROW
+                        },
+                        Children::spawn(SpawnIter(Emote::ALL.into_iter().map(|emote| {
+                            widget::button_compact(emote.to_string(), send_content(ChatContent::Emote(emote)))
+                        }))),
+                    ),
+                ],
+            ),
+        ],
+    ));
+}
+
+fn send_content(content: ChatContent) -> impl Fn(Trigger<Pointer<Click>>, Commands) + Send + Sync + 'static {
+    move |_: Trigger<Pointer<Click>>, mut cmds: Commands| {
+        cmds.trigger(MessageEvent(ClientCommand::Chat(content.clone())));
+    }
+}
+
+fn toggle_chat_panel(_: Trigger<Pointer<Click>>, mut panel: ResMut<ChatPanel>) {
+    panel.open = !panel.open;
+}
+
+/// 记录收到的消息，玩家的消息同时显示在座位上
+fn handle_chat_message(
+    mut cmds: Commands,
+    mut events: EventReader<ChatMessage>,
+    mut log: ResMut<ChatLog>,
+    state: Res<GameState>,
+) {
+    for message in events.read() {
+        let prefix = if message.spectator { "[观战] " } else { "" };
+        log.0.push_back(format!("{}{}: {}", prefix, message.name, message.content));
+        if log.0.len() > MAX_CHAT_LOG {
+            log.0.pop_front();
+        }
+
+        if message.spectator {
+            continue;
+        }
+        if let Some(seat_index) = state.get_player_seat_index_by_id(message.sender) {
+            cmds.trigger(ShowSpeechBubble {
+                seat_index,
+                text: message.content.to_string(),
+            });
+        }
+    }
+}
+
+/// 把键盘输入写入草稿，回车发送
+fn type_chat_draft(mut cmds: Commands, mut events: EventReader<KeyboardInput>, mut panel: ResMut<ChatPanel>) {
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Enter => {
+                let text = std::mem::take(&mut panel.draft);
+                if !text.trim().is_empty() {
+                    cmds.trigger(MessageEvent(ClientCommand::Chat(ChatContent::Text(text))));
+                }
+            },
+            Key::Backspace => {
+                panel.draft.pop();
+            },
+            Key::Space => {
+                if panel.draft.chars().count() < MAX_CHAT_CHARS {
+                    panel.draft.push(' ');
+                }
+            },
+            Key::Character(chars) => {
+                for c in chars.chars().filter(|c| !c.is_control()) {
+                    if panel.draft.chars().count() < MAX_CHAT_CHARS {
+                        panel.draft.push(c);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+fn render_chat_panel(
+    mut cmds: Commands,
+    log: Res<ChatLog>,
+    panel: Res<ChatPanel>,
+    mut body_query: Query<&mut Node, With<ChatPanelBody>>,
+    list_query: Query<Entity, With<ChatLogList>>,
+    mut draft_query: Query<&mut Text, With<ChatDraftText>>,
+    children_query: Query<&Children>,
+) {
+    for mut node in body_query.iter_mut() {
+        node.display = if panel.open { Display::Flex } else { Display::None };
+    }
+    for mut text in draft_query.iter_mut() {
+        text.0 = format!("> {}_", panel.draft);
+    }
+
+    let entity = r!(list_query.single());
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter() {
+            cmds.entity(child).despawn();
+        }
+    }
+    let skip = log.0.len().saturating_sub(VISIBLE_CHAT_LINES);
+    cmds.entity(entity).with_children(|parent| {
+        for line in log.0.iter().skip(skip) {
+            parent.spawn(text_base(line, Vw(1.6), ThemeColor::BODY_TEXT_LIGHT));
+        }
+    });
+}
+
+fn reset_chat(mut log: ResMut<ChatLog>, mut panel: ResMut<ChatPanel>) {
+    log.0.clear();
+    *panel = ChatPanel::default();
+}
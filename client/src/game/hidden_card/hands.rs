@@ -5,11 +5,11 @@ use crate::game::widget::prelude::{card_view, CardData};
 use bevy_renet2::prelude::{ClientId, RenetClient};
 use shared::Player;
 use shared::cards::Card;
-use shared::event::GameEvent;
+use shared::event::{ClientCommand, GameEvent};
 use shared::the_hidden_card::prelude::*;
 
 use crate::prelude::*;
-use crate::screens::ScreenState;
+use crate::screens::{InGame, ScreenState};
 use crate::theme::interaction::InteractionSelected;
 
 pub(super) fn plugin(app: &mut App) {
@@ -17,7 +17,7 @@ pub(super) fn plugin(app: &mut App) {
         Update,
         handle_update_hands_event
             .in_set(AppSystems::Update)
-            .run_if(in_state(ScreenState::Gameplay)),
+            .run_if(in_state(InGame)),
     );
 
     // 渲染手牌
@@ -58,13 +58,13 @@ fn handle_update_hands_event(
                 }
             },
             SyncState(_) => {
-                let index = game_state.get_player_seat_index_by_id(local_player.id);
-                let seat = game_state.get_seat_by_id(local_player.id);
+                // 本地玩家不在座位上时（例如观看回放）显示 0 号座位的手牌
+                let index = game_state.get_player_seat_index_by_id(local_player.id).unwrap_or(0);
+                let seat = &game_state.get_seats()[index];
 
-                if let Some(seat) = seat {
-                    if !seat.hands.is_empty() {
-                        cmds.trigger(RenderLocalHandsImmediately(seat.hands.clone()));
-                    }
+                cmds.trigger(ClearHands);
+                if !seat.hands.is_empty() {
+                    cmds.trigger(RenderLocalHandsImmediately(seat.hands.clone()));
                 }
             },
             _ => {},
@@ -162,7 +162,6 @@ fn card_dealer_system(
     mut cards_data_query: Query<&CardData>,
     mut children_query: Query<&mut Children>,
     mut client: ResMut<RenetClient>,
-    local_player: Res<Player>,
     bincode_config: Res<BincodeConfig>,
 ) {
     if dealer_machine.timer.tick(time.delta()).just_finished() {
@@ -192,7 +191,7 @@ fn card_dealer_system(
                 dealer_machine.cards = None;
                 client.send_message(
                     0,
-                    encode_to_vec(GameEvent::DealCardsDone(local_player.id), bincode_config.0)
+                    encode_to_vec(ClientCommand::DealCardsDone, bincode_config.0)
                         .unwrap(),
                 );
             }
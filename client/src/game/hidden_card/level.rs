@@ -1,9 +1,10 @@
 use shared::cards::{Card, CardValue, Suit};
 
 use crate::prelude::*;
-use crate::screens::ScreenState;
+use crate::screens::InGame;
 
 use crate::game::assets::CardAssets;
+use crate::game::event::Spectating;
 use crate::game::hidden_card::table::table;
 use crate::game::hidden_card::seat::{SeatPosition, seat_click};
 use crate::game::hidden_card::hands::hands_view;
@@ -16,19 +17,24 @@ pub fn spawn_level(
     card_assets: Res<CardAssets>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    spectating: Option<Res<Spectating>>,
 ) {
-    commands.spawn((
-        Name::new("Game UI root 1"),
-        Node::COLUMN_CENTER.full_size(),
-        Pickable::IGNORE,
-        LevelUiRoot,
-        StateScoped(ScreenState::Gameplay),
-        BackgroundColor(Color::srgba_u8(28, 119, 92, 255)),
-        children![
-            hands_view(),
-            table(),
-        ],
-    ));
+    commands
+        .spawn((
+            Name::new("Game UI root 1"),
+            Node::COLUMN_CENTER.full_size(),
+            Pickable::IGNORE,
+            LevelUiRoot,
+            StateScoped(InGame),
+            BackgroundColor(Color::srgba_u8(28, 119, 92, 255)),
+        ))
+        .with_children(|parent| {
+            // 观战者没有手牌
+            if spectating.is_none() {
+                parent.spawn(hands_view());
+            }
+            parent.spawn(table());
+        });
 }
 
 fn card_select(_: Trigger<Pointer<Click>>) {
@@ -1,4 +1,6 @@
 use crate::prelude::*;
+mod auto_play;
+pub mod chat;
 pub mod level;
 mod state;
 
@@ -15,5 +17,7 @@ pub fn plugin(app: &mut App) {
         stage::plugin,
         hands::plugin, // 本地玩家手牌控制
         table::plugin,
+        chat::plugin,
+        auto_play::plugin,
     ));
 }
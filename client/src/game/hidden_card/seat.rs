@@ -2,17 +2,18 @@ use crate::animation::ui_sprite_animation::{AnimationIndices, AnimationTimer};
 use crate::game::assets::{
     CardAssets, CardBackAssets, Icon64Assets, IndicatorAsset, SmallCardAssets,
 };
+use crate::game::event::TurnCountdown;
 use crate::game::hidden_card::level::{LevelUiRoot, spawn_level};
 pub use crate::game::widget::prelude::*;
 use crate::prelude::*;
-use crate::screens::ScreenState;
+use crate::screens::InGame;
 use crate::theme::palette::ThemeColor;
 use bevy::ecs::observer::TriggerTargets;
 use bevy::tasks::futures_lite::StreamExt;
 use shared::Player;
 use shared::cards::Card;
 use shared::event::GameEvent;
-use shared::the_hidden_card::state::{GameMode, GameState};
+use shared::the_hidden_card::state::{GameMode, GameState, PendingTurn};
 use std::f32::consts::PI;
 use strum_macros::Display;
 
@@ -21,13 +22,20 @@ pub(super) fn plugin(app: &mut App) {
         Update,
         handle_seat_update_event
             .in_set(AppSystems::Update)
-            .run_if(in_state(ScreenState::Gameplay)),
+            .run_if(in_state(InGame)),
     );
     app.add_observer(update_player_seat);
     app.add_observer(update_player_hands_counter);
+    app.add_observer(show_speech_bubble);
+    app.add_systems(
+        Update,
+        (hide_speech_bubbles, update_countdown_rings)
+            .in_set(AppSystems::Update)
+            .run_if(in_state(InGame)),
+    );
 
     app.add_systems(
-        OnEnter(ScreenState::Gameplay),
+        OnEnter(InGame),
         setup_seat_view.after(spawn_level),
     );
 }
@@ -49,7 +57,20 @@ struct TeamIndicator;
 #[derive(Component)]
 struct CoinDisplay;
 
+/// 聊天气泡，显示 [`SPEECH_BUBBLE_SECS`] 秒后隐藏
+#[derive(Component)]
+struct SpeechBubble(Timer);
+
+/// 倒计时圆环，轮到的座位或者没有准备的座位显示
+#[derive(Component)]
+struct CountdownRing;
+
+/// 托管标记，座位托管中时显示
+#[derive(Component)]
+struct AutoPlayBadge;
+
 const COIN_FONT_BOX_HEIGHT: Val = Val::Vw(3.0);
+const SPEECH_BUBBLE_SECS: f32 = 3.0;
 
 fn setup_seat_view(
     mut cmds: Commands,
@@ -120,7 +141,7 @@ fn setup_seat_view(
                 Node::COLUMN_CENTER.full_size(),
                 Pickable::IGNORE,
                 LevelUiRoot,
-                StateScoped(ScreenState::Gameplay),
+                StateScoped(InGame),
             ))
             .with_children(|parent| {
                 for (position, arrow_node, rotation, color) in seat_configs {
@@ -267,6 +288,58 @@ fn setup_seat_view(
                                     text_base("0", Vw(2.4), ThemeColor::PRIMARY_TEXT_LIGHT),
                                 ],
                             ));
+                            // 聊天气泡，上方的座位显示在座位下面，其他座位显示在上面
+                            let (bubble_top, bubble_bottom) = if matches!(position, SeatPosition::Top) {
+                                (Percent(105.), Auto)
+                            } else {
+                                (Auto, Percent(105.))
+                            };
+                            parent.spawn((
+                                Node {
+                                    top: bubble_top,
+                                    bottom: bubble_bottom,
+                                    min_width: Percent(100.),
+                                    padding: UiRect::axes(Vw(0.8), Vw(0.4)),
+                                    justify_content: JustifyContent::Center,
+                                    ..Node::DEFAULT.abs()
+                                },
+                                SpeechBubble(Timer::from_seconds(SPEECH_BUBBLE_SECS, TimerMode::Once)),
+                                Visibility::Hidden,
+                                BorderRadius::all(Vw(1.)),
+                                BackgroundColor(Color::WHITE),
+                                GlobalZIndex(1),
+                                children![body_text("")],
+                            ));
+                            // 倒计时圆环，和箭头一起显示在座位外侧
+                            parent.spawn((
+                                Node {
+                                    top: Vw(0.5),
+                                    left: Vw(0.5),
+                                    width: Px(32.),
+                                    height: Px(32.),
+                                    ..Node::ROW_CENTER.abs()
+                                },
+                                CountdownRing,
+                                Visibility::Hidden,
+                                BorderRadius::MAX,
+                                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                                Outline::new(Px(3.), Px(0.), ThemeColor::SUCCESS),
+                                children![text_base("", Px(16.), ThemeColor::BODY_TEXT_LIGHT)],
+                            ));
+                            // 托管标记
+                            parent.spawn((
+                                Node {
+                                    bottom: Vw(0.5),
+                                    left: Vw(0.5),
+                                    padding: UiRect::axes(Vw(0.4), Vw(0.1)),
+                                    ..Node::DEFAULT.abs()
+                                },
+                                AutoPlayBadge,
+                                Visibility::Hidden,
+                                BorderRadius::all(Vw(0.6)),
+                                BackgroundColor(ThemeColor::BODY),
+                                children![text_base("托管", Vw(1.2), ThemeColor::BODY_TEXT_LIGHT)],
+                            ));
                         });
                 }
             });
@@ -318,13 +391,18 @@ fn handle_seat_update_event(
                 seat_index: _,
                 card: _,
             }
-            | GameEvent::ToDealCardStage => {
+            | GameEvent::ToDealCardStage
+            | GameEvent::PlayerLeave(_)
+            | GameEvent::PlayerConnected(_)
+            | GameEvent::AutoPlay { .. }
+            | GameEvent::SetCoins { .. } => {
                 if *is_seat_position_map_available {
                     cmds.trigger(RunSeatUpdate);
                 }
             },
             GameEvent::SyncState(_) => {
-                let local_index = c!(state.get_player_seat_index_by_id(local_player.id));
+                // 本地玩家不在座位上时（例如观看回放）以 0 号座位为视角
+                let local_index = state.get_player_seat_index_by_id(local_player.id).unwrap_or(0);
                 let seat_map = get_position_relative_to_local(local_index);
                 *is_seat_position_map_available = true;
                 cmds.remove_resource::<SeatPositionMap>();
@@ -359,7 +437,7 @@ fn update_player_hands_counter(
 
         for child in children.iter() {
             if let Ok(_) = hands_counter_query.get(child) {
-                let len = seat.hands.len();
+                let len = seat.hands_count();
                 if let Ok(mut visibility) = visibility_query.get_mut(child) {
                     *visibility = Visibility::from_bool(
                         len > 0 && !matches!(seat_position, SeatPosition::Bottom),
@@ -391,6 +469,7 @@ fn update_player_seat(
     mut indicator_query: Query<&ArrowIndicator>,
     mut team_indicator: Query<&TeamIndicator>,
     mut coin_display: Query<&CoinDisplay>,
+    auto_play_badge: Query<&AutoPlayBadge>,
 
     mut background_query: Query<&mut BackgroundColor>,
     mut visibility_query: Query<&mut Visibility>,
@@ -405,7 +484,8 @@ fn update_player_seat(
     for (entity, children, seat_position) in seats_query {
         let index = c!(seat_position_map.0.get(seat_position));
         let seat = &seats_data[index.clone()];
-        let player_data = c!(seat.get_player());
+        // 玩家离开后座位为空，清空名字
+        let name = seat.get_player().map_or(String::new(), |player| player.name.clone());
 
         if let Ok(mut background_colors) = background_query.get_mut(entity) {
             background_colors.0 = if seat.ready {
@@ -424,7 +504,7 @@ fn update_player_seat(
 
             if let Ok(_) = player_name_query.get(child) {
                 if let Ok(mut text) = text_query.get_mut(child) {
-                    **text = player_data.name.clone();
+                    **text = name.clone();
                 }
             }
 
@@ -438,6 +518,12 @@ fn update_player_seat(
                 }
             }
 
+            if auto_play_badge.contains(child) {
+                if let Ok(mut visibility) = visibility_query.get_mut(child) {
+                    *visibility = Visibility::from_bool(seat.auto_play);
+                }
+            }
+
             if let Ok(_) = indicator_query.get(child) {
                 if let Ok(mut visibility) = visibility_query.get_mut(child) {
                     *visibility =
@@ -487,7 +573,7 @@ fn update_player_seat(
                 {
                     if state.is_hidden_card_shown {
                         *visibility = Visibility::Visible;
-                        if *index == *caller || *index == *callee {
+                        if *index == *caller || Some(*index) == *callee {
                             background_color.0 = TEAM_ONE_COLOR;
                         } else {
                             background_color.0 = TEAM_TWO_COLOR;
@@ -508,6 +594,86 @@ fn update_player_seat(
     }
 }
 
+/// 在座位上显示聊天气泡
+#[derive(Event)]
+pub struct ShowSpeechBubble {
+    pub seat_index: usize,
+    pub text: String,
+}
+
+fn show_speech_bubble(
+    trigger: Trigger<ShowSpeechBubble>,
+    seats_query: Query<(&Children, &SeatPosition)>,
+    mut bubble_query: Query<(&mut SpeechBubble, &mut Visibility, &Children)>,
+    mut text_query: Query<&mut Text>,
+    seat_position_map: Option<Res<SeatPositionMap>>,
+) {
+    let event = trigger.event();
+    let seat_position_map = r!(seat_position_map);
+    for (children, seat_position) in seats_query.iter() {
+        if seat_position_map.0.get(seat_position) != Some(&event.seat_index) {
+            continue;
+        }
+        for child in children.iter() {
+            let (mut bubble, mut visibility, bubble_children) = c!(bubble_query.get_mut(child));
+            bubble.0.reset();
+            *visibility = Visibility::Visible;
+            let text = *c!(bubble_children.first());
+            c!(text_query.get_mut(text)).0 = event.text.clone();
+        }
+    }
+}
+
+fn hide_speech_bubbles(time: Res<Time>, mut bubble_query: Query<(&mut SpeechBubble, &mut Visibility)>) {
+    for (mut bubble, mut visibility) in bubble_query.iter_mut() {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+        if bubble.0.tick(time.delta()).just_finished() {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+/// 倒计时圆环显示剩余秒数，颜色随时间从绿色变为红色
+fn update_countdown_rings(
+    time: Res<Time>,
+    mut countdown: Option<ResMut<TurnCountdown>>,
+    seats_query: Query<(&Children, &SeatPosition)>,
+    mut ring_query: Query<(&mut Visibility, &mut Outline, &Children), With<CountdownRing>>,
+    mut text_query: Query<&mut Text>,
+    state: Res<GameState>,
+    seat_position_map: Option<Res<SeatPositionMap>>,
+) {
+    let seat_position_map = r!(seat_position_map);
+    if let Some(countdown) = countdown.as_mut() {
+        countdown.timer.tick(time.delta());
+    }
+
+    for (children, seat_position) in seats_query.iter() {
+        let index = *c!(seat_position_map.0.get(seat_position));
+        for child in children.iter() {
+            let (mut visibility, mut outline, ring_children) = c!(ring_query.get_mut(child));
+            let Some(countdown) = countdown.as_ref() else {
+                *visibility = Visibility::Hidden;
+                continue;
+            };
+            let waiting = match countdown.turn {
+                PendingTurn::Ready => state.get_seats().get(index).is_some_and(|seat| !seat.ready),
+                PendingTurn::CallCard(seat_index) | PendingTurn::PlayCards(seat_index) => seat_index == index,
+            };
+            *visibility = Visibility::from_bool(waiting);
+            if !waiting {
+                continue;
+            }
+
+            outline.color = ThemeColor::SUCCESS.mix(&ThemeColor::DANGER, countdown.timer.fraction());
+            let text = *c!(ring_children.first());
+            c!(text_query.get_mut(text)).0 = countdown.timer.remaining_secs().ceil().to_string();
+        }
+    }
+}
+
 pub fn seat_click(_: Trigger<Pointer<Click>>) {
     println!("Clicked a seat!");
 }
@@ -7,13 +7,16 @@ use crate::screens::ScreenState;
 use crate::theme::widget::button_mid;
 
 use crate::game::assets::CardAssets;
+use crate::game::event::Spectating;
 use crate::game::hidden_card::hands::{HandsRow, RemoveCardsFromHands, SelectedCards};
 use crate::network::MessageEvent;
 use crate::theme::interaction::InteractionSelected;
 use shared::cards::Card;
-use shared::event::GameEvent;
-use shared::the_hidden_card::prelude::Combination;
-use shared::the_hidden_card::state::GameState;
+use shared::event::{ClientCommand, GameEvent};
+use shared::the_hidden_card::prelude::{
+    BotDifficulty, Combination, EndGameReason, GameError, GameResult, Outcome,
+};
+use shared::the_hidden_card::state::{GameMode, GameState, PlayerSeat};
 use shared::{Player, Reducer, the_hidden_card::state::Stage};
 
 pub(super) fn plugin(app: &mut App) {
@@ -21,7 +24,8 @@ pub(super) fn plugin(app: &mut App) {
         Update,
         state_stage_control
             .in_set(AppSystems::Update)
-            .run_if(in_state(ScreenState::Gameplay)),
+            .run_if(in_state(ScreenState::Gameplay))
+            .run_if(not(resource_exists::<Spectating>)),
     );
 
     app.add_systems(
@@ -36,6 +40,9 @@ pub(super) fn plugin(app: &mut App) {
     app.add_observer(show_call_card_popup);
 
     app.add_observer(show_play_card_popup);
+    app.init_resource::<PlayHints>();
+
+    app.add_observer(show_no_hint_popup);
 
     app.add_observer(show_result_popup);
 }
@@ -81,7 +88,7 @@ fn state_stage_control(
             let seat = &game_state.get_seats()[seat_index];
             if seat.hands_ready && !*is_call_card_event_send {
                 if seat_index == index {
-                    let cards = r!(seat.get_callable_cards());
+                    let cards = r!(game_state.callable_cards(seat_index));
                     cmds.trigger(ShowCallCardPopup(cards));
                 } else {
                     cmds.trigger(ShowCallCardPopup(vec![]));
@@ -158,6 +165,7 @@ fn show_ready_button_popup(trigger: Trigger<ShowReadyPopup>, mut cmds: Commands)
         cmds.trigger(OpenPopupEvent {
             content_builder: Box::new(|parent| {
                 parent.spawn(button_mid("开始", on_ready_botton_click));
+                parent.spawn(button_mid("机器人", on_fill_with_bots_button_click));
             }),
             blocking: true,
         });
@@ -168,17 +176,47 @@ fn show_ready_button_popup(trigger: Trigger<ShowReadyPopup>, mut cmds: Commands)
 fn on_ready_botton_click(
     _: Trigger<Pointer<Click>>,
     mut client: ResMut<RenetClient>,
-    local_player: Res<Player>,
     bincode_config: Res<BincodeConfig>,
 ) {
-    let event = GameEvent::Ready {
-        client_id: local_player.id,
-    };
+    let event = ClientCommand::Ready;
     if client.is_connected() {
         client.send_message(0, encode_to_vec(event, bincode_config.0).unwrap());
     }
 }
 
+/// 选择机器人难度
+fn on_fill_with_bots_button_click(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    cmds.trigger(OpenPopupEvent {
+        blocking: true,
+        content_builder: Box::new(|parent| {
+            parent.spawn(card_display(
+                children![body_text("选择机器人难度")],
+                children![
+                    bot_difficulty_button(BotDifficulty::Easy),
+                    bot_difficulty_button(BotDifficulty::Normal),
+                    bot_difficulty_button(BotDifficulty::Hard),
+                    button_mid("取消", on_bot_difficulty_cancel_button_click)
+                ],
+            ));
+        }),
+    });
+}
+
+/// 请求服务器用所选难度的机器人补满空座位
+fn bot_difficulty_button(difficulty: BotDifficulty) -> impl Bundle {
+    button_mid(
+        difficulty.to_string(),
+        move |_: Trigger<Pointer<Click>>, mut cmds: Commands| {
+            cmds.trigger(ClosePopupEvent);
+            cmds.trigger(MessageEvent(ClientCommand::FillWithBots(difficulty)));
+        },
+    )
+}
+
+fn on_bot_difficulty_cancel_button_click(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    cmds.trigger(ClosePopupEvent);
+}
+
 // ====================== 叫牌 ======================
 
 /// 显示叫牌的弹窗
@@ -225,12 +263,9 @@ fn show_call_card_popup(
 fn on_blocking_botton_click(
     _: Trigger<Pointer<Click>>,
     mut client: ResMut<RenetClient>,
-    local_player: Res<Player>,
-    game_state: Res<GameState>,
     bincode_config: Res<BincodeConfig>,
 ) {
-    let seat_index = r!(game_state.get_player_seat_index_by_id(local_player.id));
-    let event = GameEvent::Blocking(seat_index);
+    let event = ClientCommand::Blocking;
     if client.is_connected() {
         client.send_message(0, encode_to_vec(event, bincode_config.0).unwrap());
     }
@@ -239,18 +274,12 @@ fn on_blocking_botton_click(
 fn on_call_card_click(
     trigger: Trigger<Pointer<Click>>,
     mut client: ResMut<RenetClient>,
-    local_player: Res<Player>,
-    game_state: Res<GameState>,
     bincode_config: Res<BincodeConfig>,
     card_data_query: Query<&CardData>,
 ) {
-    let seat_index = r!(game_state.get_player_seat_index_by_id(local_player.id));
     let target = trigger.target();
     let card = r!(card_data_query.get(target));
-    let event = GameEvent::CallCard {
-        seat_index,
-        card: card.0.clone(),
-    };
+    let event = ClientCommand::CallCard(card.0.clone());
 
     if client.is_connected() {
         client.send_message(0, encode_to_vec(event, bincode_config.0).unwrap());
@@ -262,7 +291,14 @@ fn on_call_card_click(
 #[derive(Event)]
 struct ShowPlayCardPopup;
 
-fn show_play_card_popup(_: Trigger<ShowPlayCardPopup>, mut cmds: Commands, state: Res<GameState>) {
+fn show_play_card_popup(
+    _: Trigger<ShowPlayCardPopup>,
+    mut cmds: Commands,
+    state: Res<GameState>,
+    mut hints: ResMut<PlayHints>,
+) {
+    // 每次轮到本地玩家出牌时重新计算提示
+    *hints = PlayHints::default();
     let state = state.clone();
     cmds.trigger(OpenPopupEvent {
         blocking: false,
@@ -279,20 +315,15 @@ fn show_play_card_popup(_: Trigger<ShowPlayCardPopup>, mut cmds: Commands, state
                     if state.last_played_cards.is_some() {
                         parent.spawn(button_mid("不要", on_pass_button_click));
                     }
+                    parent.spawn(button_mid("提示", on_hint_button_click));
                     parent.spawn(button_mid("出牌", on_play_cards_button_click));
                 });
         }),
     });
 }
 
-fn on_pass_button_click(
-    _: Trigger<Pointer<Click>>,
-    mut cmds: Commands,
-    local_player: Res<Player>,
-    state: Res<GameState>,
-) {
-    let index = r!(state.get_player_seat_index_by_id(local_player.id));
-    cmds.trigger(MessageEvent(GameEvent::Pass(index)));
+fn on_pass_button_click(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    cmds.trigger(MessageEvent(ClientCommand::Pass));
 }
 
 fn on_play_cards_button_click(
@@ -301,7 +332,6 @@ fn on_play_cards_button_click(
     hands_query: Query<&Children, With<HandsRow>>,
     card_data_query: Query<(&CardData, &InteractionSelected)>,
     state: Res<GameState>,
-    local_player: Res<Player>,
 ) {
     let children = r!(hands_query.single());
     let mut cards = Vec::new();
@@ -311,20 +341,101 @@ fn on_play_cards_button_click(
             cards.push(card_data.0.clone());
         }
     }
-    let combination = Combination::analyze(cards.clone());
+    let combination = state.rules.analyze(cards.clone());
 
     if matches!(combination, Combination::Invalid) {
+        cmds.trigger(ToastEvent(GameError::InvalidCombination.to_string()));
         return;
     }
+
+    cmds.trigger(MessageEvent(ClientCommand::PlayCards(cards)));
+}
+
+// ====================== 提示 ======================
+
+/// 本轮出牌的提示状态，`moves` 在第一次点击提示时计算
+#[derive(Resource, Default)]
+struct PlayHints {
+    moves: Option<Vec<Combination>>,
+    next: usize,
+}
+
+/// 按从小到大（炸弹最后）的顺序循环选中可以出的牌
+fn on_hint_button_click(
+    _: Trigger<Pointer<Click>>,
+    mut cmds: Commands,
+    mut hints: ResMut<PlayHints>,
+    mut selected_cards: ResMut<SelectedCards>,
+    hands_query: Query<&Children, With<HandsRow>>,
+    mut card_query: Query<(&CardData, &mut InteractionSelected)>,
+    state: Res<GameState>,
+    local_player: Res<Player>,
+) {
     let index = r!(state.get_player_seat_index_by_id(local_player.id));
+    if hints.moves.is_none() {
+        hints.moves = Some(state.legal_moves(index));
+    }
+
+    let combo = {
+        let moves = r!(hints.moves.as_ref());
+        if moves.is_empty() {
+            None
+        } else {
+            Some(moves[hints.next % moves.len()].clone())
+        }
+    };
+    let Some(combo) = combo else {
+        cmds.trigger(ShowNoHintPopup);
+        return;
+    };
+    hints.next += 1;
 
-    cmds.trigger(MessageEvent(GameEvent::PlayCards(index, cards)));
+    let children = r!(hands_query.single());
+    let mut to_select = combo.to_vec_cards();
+    selected_cards.0.clear();
+    for child in children.iter() {
+        let (card_data, mut selected) = c!(card_query.get_mut(child));
+        let position = to_select.iter().position(|card| *card == card_data.0);
+        selected.0 = position.is_some();
+        if let Some(position) = position {
+            to_select.swap_remove(position);
+            selected_cards.0.push(child);
+        }
+    }
+}
+
+#[derive(Event)]
+struct ShowNoHintPopup;
+
+fn show_no_hint_popup(_: Trigger<ShowNoHintPopup>, mut cmds: Commands) {
+    cmds.trigger(OpenPopupEvent {
+        blocking: true,
+        content_builder: Box::new(|parent| {
+            parent.spawn(card_display(
+                children![body_text("没有牌大过上家")],
+                children![
+                    button_mid("不要", on_no_hint_pass_button_click),
+                    button_mid("取消", on_no_hint_cancel_button_click)
+                ],
+            ));
+        }),
+    });
+}
+
+fn on_no_hint_pass_button_click(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    // 先关闭提示弹窗，出牌弹窗在收到服务器的 Pass 事件后关闭
+    cmds.trigger(ClosePopupEvent);
+    cmds.trigger(MessageEvent(ClientCommand::Pass));
+}
+
+fn on_no_hint_cancel_button_click(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    cmds.trigger(ClosePopupEvent);
 }
 
 // ====================== 结算 ======================
 
 #[derive(Event)]
-struct ShowResultPopup(Vec<(usize, i32)>);
+struct ShowResultPopup(GameResult);
 
 fn show_result_popup(trigger: Trigger<ShowResultPopup>, mut cmds: Commands, state: Res<GameState>) {
     let result = trigger.event().0.clone();
@@ -335,7 +446,10 @@ fn show_result_popup(trigger: Trigger<ShowResultPopup>, mut cmds: Commands, stat
             let result = result.clone();
             let seats = seats.clone();
             let content = Children::spawn(SpawnWith(move |parent: &mut ChildSpawner| {
-                for (index, score) in result {
+                for line in result_summary(&result, &seats) {
+                    parent.spawn(body_text(line));
+                }
+                for (index, score) in result.scores {
                     let player = &seats[index].player;
                     if let Some(player) = player {
                         parent.spawn((
@@ -365,13 +479,32 @@ fn show_result_popup(trigger: Trigger<ShowResultPopup>, mut cmds: Commands, stat
     })
 }
 
-fn on_play_again_button_click(
-    _: Trigger<Pointer<Click>>,
-    mut cmds: Commands,
-    local_player: Res<Player>,
-) {
-    let event = GameEvent::Ready {
-        client_id: local_player.id,
+/// 结算说明：结果类型、两队记牌数和倍数，有玩家中途离开时说明是谁
+fn result_summary(result: &GameResult, seats: &[PlayerSeat]) -> Vec<String> {
+    let team_names = match result.mode {
+        GameMode::HiddenAllies { .. } => ["叫牌方", "另一方"],
+        GameMode::OneVsThree(_) => ["包牌方", "另一方"],
     };
-    cmds.trigger(MessageEvent(event));
+    let mut lines = vec![result.outcome.to_string()];
+    if let EndGameReason::PlayerLeft { player_id } = result.reason {
+        let name = seats
+            .iter()
+            .filter_map(|seat| seat.get_player())
+            .find(|player| player.id == player_id)
+            .map_or("有玩家".to_string(), |player| player.name.clone());
+        lines.push(format!("{} 离开了牌局，提前结算", name));
+    }
+    if matches!(result.outcome, Outcome::SingleWin | Outcome::Draw) {
+        lines.push(format!(
+            "记牌 {} {} : {} {}",
+            team_names[0], result.team_points[0], team_names[1], result.team_points[1]
+        ));
+    }
+    let spring = if result.spring { "（春天）" } else { "" };
+    lines.push(format!("底分 {} × 倍数 {}{}", result.base, result.multiplier, spring));
+    lines
+}
+
+fn on_play_again_button_click(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    cmds.trigger(MessageEvent(ClientCommand::Ready));
 }
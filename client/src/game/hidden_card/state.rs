@@ -1,21 +1,21 @@
 //! 负责初始化和销毁游戏状态
 
 use crate::prelude::*;
-use crate::screens::ScreenState;
+use crate::screens::InGame;
 
 use shared::Reducer;
 use shared::event::GameEvent;
 use shared::the_hidden_card::prelude::*;
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_systems(OnEnter(ScreenState::Gameplay), init_resource);
-    app.add_systems(OnExit(ScreenState::Gameplay), destroy_resource);
+    app.add_systems(OnEnter(InGame), init_resource);
+    app.add_systems(OnExit(InGame), destroy_resource);
 
     app.add_systems(
         Update,
         update_state
             .in_set(AppSystems::UpdateState)
-            .run_if(in_state(ScreenState::Gameplay))
+            .run_if(in_state(InGame))
             .run_if(resource_exists::<GameState>),
     );
 }
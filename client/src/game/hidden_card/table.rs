@@ -4,7 +4,8 @@ use crate::game::assets::{CardAssets, CardBackAssets};
 use crate::game::hidden_card::seat::CARD_WIDTH;
 use crate::game::widget::prelude::{CARD_HEIGHT, card_view};
 use crate::prelude::*;
-use crate::screens::ScreenState;
+use crate::game::event::{CurrentRoom, Spectating};
+use crate::screens::{InGame, ScreenState};
 use bevy::ui::*;
 use shared::cards::Card;
 use shared::event::GameEvent;
@@ -16,7 +17,15 @@ pub(super) fn plugin(app: &mut App) {
         Update,
         handle_game_event
             .in_set(AppSystems::Update)
-            .run_if(in_state(ScreenState::Gameplay)),
+            .run_if(in_state(InGame)),
+    );
+
+    app.add_systems(
+        Update,
+        update_room_code
+            .in_set(AppSystems::Update)
+            .run_if(in_state(ScreenState::Gameplay))
+            .run_if(resource_exists::<CurrentRoom>),
     );
 
     app.add_observer(render_table_hands);
@@ -33,6 +42,8 @@ fn handle_game_event(
             GameEvent::PlayCards(_, _)
             | GameEvent::SyncState(_)
             | GameEvent::Pass(_)
+            | GameEvent::Blocking(_)
+            | GameEvent::GameEnd(_)
             | GameEvent::Ready { client_id: _ } => {
                 if let Some(combo) = state.last_played_cards.clone() {
                     cmds.trigger(RenderTableHands(combo.to_vec_cards()));
@@ -94,12 +105,17 @@ fn update_table_counter(
     _: Trigger<UpdateTableCounter>,
     state: Res<GameState>,
     mut counter_query: Query<(&mut Visibility, &Children), With<TableCardCounter>>,
+    mut multiplier_query: Query<&mut Text, With<TableMultiplierText>>,
     mut image_node_query: Query<&mut ImageNode>,
-    mut text_query: Query<&mut Text>,
+    mut text_query: Query<&mut Text, Without<TableMultiplierText>>,
     mut image_updated: Local<bool>,
     card_assets: Res<CardAssets>,
     card_back_assets: Res<CardBackAssets>,
 ) {
+    if let Ok(mut text) = multiplier_query.single_mut() {
+        **text = format!("倍数 ×{}", state.multiplayer);
+    }
+
     let (mut visible, children) = r!(counter_query.single_mut());
     let visible_bool = state.table_score_counter > 0;
 
@@ -122,12 +138,36 @@ fn update_table_counter(
     }
 }
 
+fn update_room_code(
+    room: Res<CurrentRoom>,
+    spectating: Option<Res<Spectating>>,
+    mut text_query: Query<&mut Text, With<TableRoomCodeText>>,
+) {
+    for mut text in text_query.iter_mut() {
+        let mut code = format!("房间号 {}", room.code);
+        if spectating.is_some() {
+            code.push_str("  观战中");
+        }
+        if text.0 != code {
+            text.0 = code;
+        }
+    }
+}
+
 #[derive(Component)]
 struct TableHandsRow;
 
 #[derive(Component)]
 struct TableCardCounter;
 
+/// 当前倍数，包牌和炸弹后更新
+#[derive(Component)]
+struct TableMultiplierText;
+
+/// 房间号，回放时不显示
+#[derive(Component)]
+struct TableRoomCodeText;
+
 const TABLE_HANDS_BOTTOM_DISTANCE: Val = Vw(20.);
 const TABLE_HANDS_LEFT_DISTANCE: Val = Vw(20.);
 
@@ -181,6 +221,26 @@ pub fn table() -> impl Bundle {
                 ],
                 TableCardCounter,
                 Visibility::Hidden,
+            ),
+            (
+                Node {
+                    position_type: PositionType::Absolute,
+                    bottom: TABLE_HANDS_BOTTOM_DISTANCE.try_add(CARD_HEIGHT).unwrap(),
+                    right: TABLE_HANDS_LEFT_DISTANCE,
+                    ..default()
+                },
+                body_text("倍数 ×1"),
+                TableMultiplierText,
+            ),
+            (
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Vw(1.),
+                    left: Vw(1.),
+                    ..default()
+                },
+                body_text(""),
+                TableRoomCodeText,
             )
         ],
     )
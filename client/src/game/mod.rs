@@ -2,7 +2,7 @@ pub mod bincode;
 pub mod hidden_card;
 mod widget;
 
-mod assets;
+pub mod assets;
 mod dev;
 mod event;
 mod interaction;
@@ -0,0 +1,472 @@
+//! 大厅：浏览公开房间、按房间号加入或观战和创建房间
+//!
+//! 房间号和密码通过键盘输入，点击输入框切换正在输入的一项。
+//! 快速匹配按选择的底分排队，排队期间显示位置和预计等待时间，可以随时取消
+
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::platform::time::Instant;
+use shared::event::ClientCommand;
+use shared::lobby::{MAX_BASE_STAKE, MAX_PASSWORD_CHARS, ROOM_CODE_LEN, RoomSettings, RoomSummary};
+use shared::the_hidden_card::prelude::RuleSet;
+
+use crate::network::MessageEvent;
+use crate::prelude::*;
+use crate::{menus::Menu, theme::widget};
+
+/// 列表最多显示的房间数量
+const MAX_LISTED_ROOMS: usize = 5;
+
+/// 可选的规则，`None` 使用服务器的默认规则
+const RULE_PRESETS: [&str; 3] = ["服务器默认", "标准", "炸弹不翻倍"];
+
+/// 可选的观战延迟秒数
+const SPECTATOR_DELAY_PRESETS: [u64; 4] = [0, 30, 60, 120];
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<LobbyForm>();
+    app.init_resource::<LobbyRooms>();
+    app.add_systems(OnEnter(Menu::Lobby), (spawn_lobby_menu, request_room_list));
+    app.add_systems(
+        Update,
+        (
+            type_into_field,
+            update_form_texts.run_if(resource_changed::<LobbyForm>),
+            render_room_list.run_if(resource_changed::<LobbyRooms>),
+        )
+            .chain()
+            .run_if(in_state(Menu::Lobby)),
+    );
+    app.add_systems(
+        Update,
+        go_back.run_if(in_state(Menu::Lobby).and(input_just_pressed(KeyCode::Escape))),
+    );
+    app.add_systems(
+        Update,
+        (
+            open_match_popup.run_if(resource_added::<MatchQueue>),
+            update_match_text.run_if(resource_exists::<MatchQueue>),
+        )
+            .chain(),
+    );
+}
+
+/// 快速匹配的排队状态，收到服务器的排队信息时更新，匹配成功或取消后移除
+#[derive(Resource)]
+pub struct MatchQueue {
+    position: usize,
+    estimated_wait: Option<Duration>,
+    received_at: Instant,
+}
+
+impl MatchQueue {
+    pub fn new(position: usize, estimated_wait_secs: Option<u64>) -> Self {
+        Self {
+            position,
+            estimated_wait: estimated_wait_secs.map(Duration::from_secs),
+            received_at: Instant::now(),
+        }
+    }
+
+    fn text(&self) -> String {
+        let wait = match self.estimated_wait {
+            Some(wait) => {
+                let remaining = wait.saturating_sub(self.received_at.elapsed());
+                format!("预计 {} 秒", remaining.as_secs())
+            },
+            None => "等待其他玩家".to_string(),
+        };
+        format!("匹配中，第 {} 位，{}", self.position, wait)
+    }
+}
+
+#[derive(Component)]
+struct MatchQueueText;
+
+/// 服务器返回的公开房间列表
+#[derive(Resource, Default)]
+pub struct LobbyRooms(pub Vec<RoomSummary>);
+
+/// 大厅中输入和选择的内容，离开大厅后保留
+#[derive(Resource)]
+struct LobbyForm {
+    code: String,
+    password: String,
+    focus: LobbyField,
+    base_stake: i32,
+    private: bool,
+    rules: usize,
+    /// [`SPECTATOR_DELAY_PRESETS`] 的索引
+    spectator_delay: usize,
+}
+
+impl Default for LobbyForm {
+    fn default() -> Self {
+        Self {
+            code: String::new(),
+            password: String::new(),
+            focus: LobbyField::Code,
+            base_stake: 1,
+            private: false,
+            rules: 0,
+            spectator_delay: 0,
+        }
+    }
+}
+
+impl LobbyForm {
+    fn password(&self) -> Option<String> {
+        (!self.password.is_empty()).then(|| self.password.clone())
+    }
+
+    fn settings(&self) -> RoomSettings {
+        let rules = match self.rules {
+            0 => None,
+            1 => Some(RuleSet::default()),
+            _ => Some(RuleSet {
+                bomb_multiplier: 1,
+                spring_multiplier: 1,
+                ..default()
+            }),
+        };
+        RoomSettings {
+            private: self.private,
+            password: self.password(),
+            base_stake: self.base_stake,
+            rules,
+            spectator_delay_secs: SPECTATOR_DELAY_PRESETS[self.spectator_delay],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum LobbyField {
+    Code,
+    Password,
+}
+
+/// 显示 [`LobbyForm`] 内容的控件
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum LobbyText {
+    Code,
+    Password,
+    BaseStake,
+    Private,
+    Rules,
+    SpectatorDelay,
+}
+
+impl LobbyText {
+    fn text(&self, form: &LobbyForm) -> String {
+        let cursor = |field: LobbyField| if form.focus == field { "_" } else { "" };
+        match self {
+            LobbyText::Code => format!("房间号 {}{}", form.code, cursor(LobbyField::Code)),
+            LobbyText::Password => format!(
+                "密码 {}{}",
+                "*".repeat(form.password.chars().count()),
+                cursor(LobbyField::Password)
+            ),
+            LobbyText::BaseStake => format!("底分 {}", form.base_stake),
+            LobbyText::Private => (if form.private { "私密" } else { "公开" }).to_string(),
+            LobbyText::Rules => RULE_PRESETS[form.rules].to_string(),
+            LobbyText::SpectatorDelay => match SPECTATOR_DELAY_PRESETS[form.spectator_delay] {
+                0 => "实时观战".to_string(),
+                secs => format!("观战延迟 {} 秒", secs),
+            },
+        }
+    }
+}
+
+#[derive(Component)]
+struct LobbyRoomList;
+
+fn spawn_lobby_menu(mut commands: Commands) {
+    commands.spawn((
+        widget::ui_root("Lobby Menu"),
+        GlobalZIndex(2),
+        StateScoped(Menu::Lobby),
+        children![
+            widget::header("大厅"),
+            (
+                Name::new("Room List"),
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Vw(1.),
+                    ..default()
+                },
+                LobbyRoomList,
+            ),
+            (
+                Node {
+                    column_gap: Vw(2.),
+                    ..Node::ROW
+                },
+                children![
+                    (widget::button_mid("", focus_code), LobbyText::Code),
+                    (widget::button_mid("", focus_password), LobbyText::Password),
+                    widget::button_mid("加入", join_by_code),
+                    widget::button_mid("观战", spectate_by_code),
+                    widget::button_mid("刷新", refresh_room_list),
+                ],
+            ),
+            (
+                Node {
+                    column_gap: Vw(2.),
+                    ..Node::ROW
+                },
+                children![
+                    widget::selector(LobbyText::BaseStake, lower_base_stake, raise_base_stake),
+                    widget::selector(LobbyText::Private, toggle_private, toggle_private),
+                ],
+            ),
+            (
+                Node {
+                    column_gap: Vw(2.),
+                    ..Node::ROW
+                },
+                children![
+                    widget::selector(LobbyText::Rules, previous_rules, next_rules),
+                    widget::selector(
+                        LobbyText::SpectatorDelay,
+                        previous_spectator_delay,
+                        next_spectator_delay
+                    ),
+                ],
+            ),
+            (
+                Node {
+                    column_gap: Vw(2.),
+                    ..Node::ROW
+                },
+                children![
+                    widget::button_mid("快速匹配", quick_match),
+                    widget::button_mid("创建房间", create_room),
+                    widget::button_mid("返回", go_back_on_click),
+                ],
+            ),
+        ],
+    ));
+}
+
+fn request_room_list(mut cmds: Commands, mut form: ResMut<LobbyForm>) {
+    cmds.trigger(MessageEvent(ClientCommand::ListRooms));
+    // 让新生成的控件显示当前内容
+    form.set_changed();
+}
+
+fn refresh_room_list(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    cmds.trigger(MessageEvent(ClientCommand::ListRooms));
+}
+
+fn render_room_list(
+    mut cmds: Commands,
+    rooms: Res<LobbyRooms>,
+    list_query: Query<Entity, With<LobbyRoomList>>,
+    children_query: Query<&Children>,
+) {
+    let entity = r!(list_query.single());
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter() {
+            cmds.entity(child).despawn();
+        }
+    }
+
+    cmds.entity(entity).with_children(|parent| {
+        if rooms.0.is_empty() {
+            parent.spawn(widget::label("暂无公开房间"));
+        }
+        for room in rooms.0.iter().take(MAX_LISTED_ROOMS) {
+            parent.spawn((
+                Node {
+                    column_gap: Vw(1.),
+                    ..Node::ROW
+                },
+                children![room_button(room), spectate_button(room)],
+            ));
+        }
+    });
+}
+
+fn room_button(room: &RoomSummary) -> impl Bundle {
+    let mut text = format!("{}  {}/4  底分 {}", room.code, room.players.len(), room.base_stake);
+    if room.has_password {
+        text.push_str("  有密码");
+    }
+    if room.in_game {
+        text.push_str("  游戏中");
+    }
+    if room.spectators > 0 {
+        text.push_str(&format!("  {}人观战", room.spectators));
+    }
+    let code = room.code.clone();
+    widget::button_mid(
+        text,
+        move |_: Trigger<Pointer<Click>>, mut cmds: Commands, form: Res<LobbyForm>| {
+            cmds.trigger(MessageEvent(ClientCommand::JoinRoom {
+                code: code.clone(),
+                password: form.password(),
+            }));
+        },
+    )
+}
+
+fn spectate_button(room: &RoomSummary) -> impl Bundle {
+    let code = room.code.clone();
+    widget::button_mid(
+        "观战",
+        move |_: Trigger<Pointer<Click>>, mut cmds: Commands, form: Res<LobbyForm>| {
+            cmds.trigger(MessageEvent(ClientCommand::Spectate {
+                code: code.clone(),
+                password: form.password(),
+            }));
+        },
+    )
+}
+
+/// 把键盘输入写入正在输入的一项，只接受字母和数字，房间号转为大写
+fn type_into_field(mut events: EventReader<KeyboardInput>, mut form: ResMut<LobbyForm>) {
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        let focus = form.focus;
+        let (field, max_len) = match focus {
+            LobbyField::Code => (&mut form.code, ROOM_CODE_LEN),
+            LobbyField::Password => (&mut form.password, MAX_PASSWORD_CHARS),
+        };
+        match &event.logical_key {
+            Key::Backspace => {
+                field.pop();
+            },
+            Key::Character(chars) => {
+                for c in chars.chars().filter(|c| c.is_ascii_alphanumeric()) {
+                    if field.chars().count() < max_len {
+                        field.push(if focus == LobbyField::Code { c.to_ascii_uppercase() } else { c });
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+fn update_form_texts(
+    form: Res<LobbyForm>,
+    field_query: Query<(Entity, &LobbyText)>,
+    children_query: Query<&Children>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (entity, field) in field_query.iter() {
+        let children = c!(children_query.get(entity));
+        // 按钮的文字是第一个子节点，选择器的文字在中间子节点下
+        let label = match field {
+            LobbyText::Code | LobbyText::Password => *c!(children.first()),
+            _ => *c!(c!(children_query.get(*c!(children.get(1)))).first()),
+        };
+        c!(text_query.get_mut(label)).0 = field.text(&form);
+    }
+}
+
+fn focus_code(_: Trigger<Pointer<Click>>, mut form: ResMut<LobbyForm>) {
+    form.focus = LobbyField::Code;
+}
+
+fn focus_password(_: Trigger<Pointer<Click>>, mut form: ResMut<LobbyForm>) {
+    form.focus = LobbyField::Password;
+}
+
+fn join_by_code(_: Trigger<Pointer<Click>>, mut cmds: Commands, form: Res<LobbyForm>) {
+    if form.code.len() != ROOM_CODE_LEN {
+        cmds.trigger(ToastEvent(format!("请输入{}位房间号", ROOM_CODE_LEN)));
+        return;
+    }
+    cmds.trigger(MessageEvent(ClientCommand::JoinRoom {
+        code: form.code.clone(),
+        password: form.password(),
+    }));
+}
+
+fn spectate_by_code(_: Trigger<Pointer<Click>>, mut cmds: Commands, form: Res<LobbyForm>) {
+    if form.code.len() != ROOM_CODE_LEN {
+        cmds.trigger(ToastEvent(format!("请输入{}位房间号", ROOM_CODE_LEN)));
+        return;
+    }
+    cmds.trigger(MessageEvent(ClientCommand::Spectate {
+        code: form.code.clone(),
+        password: form.password(),
+    }));
+}
+
+fn create_room(_: Trigger<Pointer<Click>>, mut cmds: Commands, form: Res<LobbyForm>) {
+    cmds.trigger(MessageEvent(ClientCommand::CreateRoom(form.settings())));
+}
+
+fn quick_match(_: Trigger<Pointer<Click>>, mut cmds: Commands, form: Res<LobbyForm>) {
+    cmds.trigger(MessageEvent(ClientCommand::QuickMatch {
+        base_stake: form.base_stake,
+    }));
+}
+
+fn open_match_popup(mut cmds: Commands) {
+    cmds.trigger(OpenPopupEvent {
+        content_builder: Box::new(|parent| {
+            parent.spawn(widget::card_display(
+                children![(widget::body_text(""), MatchQueueText)],
+                children![widget::button_mid("取消", cancel_match)],
+            ));
+        }),
+        blocking: true,
+    });
+}
+
+fn update_match_text(queue: Res<MatchQueue>, mut text_query: Query<&mut Text, With<MatchQueueText>>) {
+    for mut text in text_query.iter_mut() {
+        let content = queue.text();
+        if text.0 != content {
+            text.0 = content;
+        }
+    }
+}
+
+fn cancel_match(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    cmds.trigger(MessageEvent(ClientCommand::CancelMatch));
+    cmds.remove_resource::<MatchQueue>();
+    cmds.trigger(ClosePopupEvent);
+}
+
+fn lower_base_stake(_: Trigger<Pointer<Click>>, mut form: ResMut<LobbyForm>) {
+    form.base_stake = (form.base_stake - 1).max(1);
+}
+
+fn raise_base_stake(_: Trigger<Pointer<Click>>, mut form: ResMut<LobbyForm>) {
+    form.base_stake = (form.base_stake + 1).min(MAX_BASE_STAKE);
+}
+
+fn toggle_private(_: Trigger<Pointer<Click>>, mut form: ResMut<LobbyForm>) {
+    form.private = !form.private;
+}
+
+fn previous_rules(_: Trigger<Pointer<Click>>, mut form: ResMut<LobbyForm>) {
+    form.rules = (form.rules + RULE_PRESETS.len() - 1) % RULE_PRESETS.len();
+}
+
+fn next_rules(_: Trigger<Pointer<Click>>, mut form: ResMut<LobbyForm>) {
+    form.rules = (form.rules + 1) % RULE_PRESETS.len();
+}
+
+fn previous_spectator_delay(_: Trigger<Pointer<Click>>, mut form: ResMut<LobbyForm>) {
+    let len = SPECTATOR_DELAY_PRESETS.len();
+    form.spectator_delay = (form.spectator_delay + len - 1) % len;
+}
+
+fn next_spectator_delay(_: Trigger<Pointer<Click>>, mut form: ResMut<LobbyForm>) {
+    form.spectator_delay = (form.spectator_delay + 1) % SPECTATOR_DELAY_PRESETS.len();
+}
+
+fn go_back_on_click(_: Trigger<Pointer<Click>>, mut next_menu: ResMut<NextState<Menu>>) {
+    next_menu.set(Menu::Main);
+}
+
+fn go_back(mut next_menu: ResMut<NextState<Menu>>) {
+    next_menu.set(Menu::Main);
+}
@@ -1,17 +1,10 @@
 //! The main menu (seen on the title screen).
 
-use bevy_renet2::prelude::RenetClient;
-use bincode::serde::encode_to_vec;
-
 use crate::prelude::*;
 
-use shared::Player;
-use shared::event::GameEvent;
-
 #[cfg(target_family = "wasm")]
 use crate::theme::interaction::InteractionDisabled;
 
-use crate::game::bincode::BincodeConfig;
 use crate::prelude::{ClosePopupEvent, OpenPopupEvent};
 use crate::{asset_tracking::ResourceHandles, menus::Menu, screens::ScreenState, theme::widget};
 
@@ -28,6 +21,7 @@ fn spawn_main_menu(mut commands: Commands) {
         children![
             widget::button("开始", enter_loading_or_gameplay_screen),
             widget::button("设置", open_settings_menu),
+            widget::button("回放", open_replays_menu),
             widget::button("打开弹窗", open_popup),
             (
                 widget::button("退出", exit_app),
@@ -47,41 +41,25 @@ fn enter_loading_or_gameplay_screen(
     _: Trigger<Pointer<Click>>,
     resource_handles: Res<ResourceHandles>,
     mut next_screen: ResMut<NextState<ScreenState>>,
-    mut client: ResMut<RenetClient>,
-    player: Res<Player>,
-    bincode_config: Res<BincodeConfig>,
+    mut next_menu: ResMut<NextState<Menu>>,
 ) {
     info!("Entering loading or gameplay screen.");
     if resource_handles.is_all_done() {
-        // next_screen.set(ScreenState::Gameplay);
-        let event = GameEvent::JoinRoom {
-            player: player.clone(),
-            room_id: 0,
-        };
-        client.send_message(0, encode_to_vec(&event, bincode_config.0).unwrap());
+        // 在大厅中选择或创建房间，加入成功后进入游戏屏
+        next_menu.set(Menu::Lobby);
     } else {
         next_screen.set(ScreenState::Loading);
     }
 }
 
-fn open_settings_menu(
-    _: Trigger<Pointer<Click>>,
-    mut next_menu: ResMut<NextState<Menu>>,
-    mut client: ResMut<RenetClient>,
-    bincode_config: Res<BincodeConfig>,
-) {
-    // #[cfg(feature = "dev")]
-    // {
-    //     info!("发送重置游戏房间事件到服务器");
-    //     let event = GameEvent::RoomReset { room_id: 0 };
-    //     client.send_message(0, encode_to_vec(&event, bincode_config.0).unwrap());
-    // }
-    info!("发送重置游戏房间事件到服务器");
-    let event = GameEvent::RoomReset { room_id: 0 };
-    client.send_message(0, encode_to_vec(&event, bincode_config.0).unwrap());
+fn open_settings_menu(_: Trigger<Pointer<Click>>, mut next_menu: ResMut<NextState<Menu>>) {
     next_menu.set(Menu::Settings);
 }
 
+fn open_replays_menu(_: Trigger<Pointer<Click>>, mut next_menu: ResMut<NextState<Menu>>) {
+    next_menu.set(Menu::Replays);
+}
+
 fn open_popup(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
     cmds.trigger(OpenPopupEvent {
         content_builder: Box::new(|parent| {
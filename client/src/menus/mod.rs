@@ -1,18 +1,26 @@
 //! The game's menus and transitions between them.
 
+mod lobby;
 mod main;
 mod pause;
+#[cfg(not(target_family = "wasm"))]
+mod replays;
 mod settings;
 
 use bevy::prelude::*;
 
+pub use lobby::{LobbyRooms, MatchQueue};
+
 pub(super) fn plugin(app: &mut App) {
     app.init_state::<Menu>();
 
     app.add_plugins((
+        lobby::plugin,
         main::plugin,
         settings::plugin,
         pause::plugin,
+        #[cfg(not(target_family = "wasm"))]
+        replays::plugin,
     ));
 }
 
@@ -22,6 +30,8 @@ pub enum Menu {
     #[default]
     None,
     Main,
+    Lobby,
     Settings,
     Pause,
+    Replays,
 }
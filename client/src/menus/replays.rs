@@ -0,0 +1,198 @@
+//! 回放列表，带上游客凭证从 HTTP 服务器获取自己参与过的回放，点击后下载并打开回放
+
+use bevy_http_client::prelude::{
+    HttpClient, HttpRequest, HttpResponse, HttpResponseError, HttpTypedRequestTrait, TypedRequest,
+    TypedResponse, TypedResponseError,
+};
+use shared::replay::{Replay, ReplayEntry};
+
+use crate::network::{GuestKey, SERVER_ADDR};
+use crate::prelude::*;
+use crate::{menus::Menu, screens::ReplayViewer, screens::ScreenState, theme::widget};
+
+/// 列表最多显示的回放数量，按时间从新到旧
+const MAX_LISTED_REPLAYS: usize = 6;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_request_type::<Vec<ReplayEntry>>();
+    app.add_systems(OnEnter(Menu::Replays), (spawn_replays_menu, request_replay_list));
+    app.add_systems(
+        Update,
+        (handle_replay_list, handle_replay_list_error).run_if(in_state(Menu::Replays)),
+    );
+    app.add_systems(
+        Update,
+        go_back.run_if(in_state(Menu::Replays).and(input_just_pressed(KeyCode::Escape))),
+    );
+}
+
+#[derive(Component)]
+struct ReplayList;
+
+fn spawn_replays_menu(mut commands: Commands, guest_key: Option<Res<GuestKey>>) {
+    // 回放只给参与过的玩家看，还没有从服务器拿到游客凭证时无法请求
+    let status = if guest_key.is_some() { "加载中" } else { "连接服务器后才能查看回放" };
+    commands.spawn((
+        widget::ui_root("Replays Menu"),
+        GlobalZIndex(2),
+        StateScoped(Menu::Replays),
+        children![
+            widget::header("回放"),
+            (
+                Name::new("Replay List"),
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Vw(1.),
+                    ..default()
+                },
+                ReplayList,
+                children![widget::label(status)],
+            ),
+            widget::button("返回", go_back_on_click),
+        ],
+    ));
+}
+
+/// 带上游客凭证的回放地址，`path` 以 `/` 开头
+fn replay_url(path: &str, guest_key: &GuestKey) -> Option<String> {
+    let params = [("guest_key", guest_key.0.as_str())];
+    let url = url::Url::parse_with_params(&format!("{}{}", SERVER_ADDR, path), &params).ok()?;
+    Some(url.into())
+}
+
+fn request_replay_list(
+    guest_key: Option<Res<GuestKey>>,
+    mut requests: EventWriter<TypedRequest<Vec<ReplayEntry>>>,
+) {
+    let Some(url) = guest_key.and_then(|key| replay_url("/replays", &key)) else {
+        return;
+    };
+    match HttpClient::new().get(url).try_with_type::<Vec<ReplayEntry>>() {
+        Ok(request) => {
+            requests.write(request);
+        },
+        Err(err) => warn!("error send replay list request: {}", err),
+    }
+}
+
+fn handle_replay_list(
+    mut cmds: Commands,
+    mut events: EventReader<TypedResponse<Vec<ReplayEntry>>>,
+    list_query: Query<Entity, With<ReplayList>>,
+    guest_key: Option<Res<GuestKey>>,
+) {
+    for response in events.read() {
+        let entity = r!(list_query.single());
+        let guest_key = r!(guest_key.as_deref());
+        let entries = response.inner();
+        cmds.entity(entity).despawn_related::<Children>().with_children(|parent| {
+            if entries.is_empty() {
+                parent.spawn(widget::label("暂无回放"));
+            }
+            for entry in entries.iter().take(MAX_LISTED_REPLAYS) {
+                parent.spawn(replay_button(entry, guest_key));
+            }
+        });
+    }
+}
+
+fn handle_replay_list_error(
+    mut cmds: Commands,
+    mut events: EventReader<TypedResponseError<Vec<ReplayEntry>>>,
+    list_query: Query<Entity, With<ReplayList>>,
+) {
+    for error in events.read() {
+        error!("Error retrieving replay list: {}", error.err);
+        let entity = r!(list_query.single());
+        cmds.entity(entity)
+            .despawn_related::<Children>()
+            .with_children(|parent| {
+                parent.spawn(widget::label("回放列表加载失败"));
+            });
+    }
+}
+
+/// 按钮显示文件名和同桌的玩家
+fn replay_button(entry: &ReplayEntry, guest_key: &GuestKey) -> impl Bundle {
+    let players = entry
+        .header
+        .players
+        .iter()
+        .flatten()
+        .map(|player| player.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let url = replay_url(&format!("/replays/{}", entry.name), guest_key);
+    widget::button_mid(
+        format!("{}  {}", entry.name, players),
+        move |_: Trigger<Pointer<Click>>,
+              mut cmds: Commands,
+              mut requests: EventWriter<HttpRequest>| {
+            let Some(url) = url.clone() else {
+                open_error_popup(&mut cmds);
+                return;
+            };
+            // 下载结果只发给这个实体上的观察者
+            let entity = cmds
+                .spawn((StateScoped(Menu::Replays), Name::new("Replay Download")))
+                .observe(open_downloaded_replay)
+                .observe(show_download_error)
+                .id();
+            requests.write(HttpClient::new_with_entity(entity).get(url).build());
+        },
+    )
+}
+
+fn open_downloaded_replay(
+    trigger: Trigger<HttpResponse>,
+    mut cmds: Commands,
+    mut next_screen: ResMut<NextState<ScreenState>>,
+) {
+    cmds.entity(trigger.target()).despawn();
+    let response = trigger.event();
+    let replay = if response.ok {
+        Replay::decode(&response.bytes).map_err(|err| err.to_string())
+    } else {
+        Err(format!("{} {}", response.status, response.status_text))
+    };
+    match replay {
+        Ok(replay) => {
+            cmds.insert_resource(ReplayViewer::new(replay));
+            next_screen.set(ScreenState::Replay);
+        },
+        Err(err) => {
+            error!("Failed to load replay {}: {}", response.url, err);
+            open_error_popup(&mut cmds);
+        },
+    }
+}
+
+fn show_download_error(trigger: Trigger<HttpResponseError>, mut cmds: Commands) {
+    cmds.entity(trigger.target()).despawn();
+    error!("Failed to download replay: {}", trigger.event().err);
+    open_error_popup(&mut cmds);
+}
+
+fn open_error_popup(cmds: &mut Commands) {
+    cmds.trigger(OpenPopupEvent {
+        content_builder: Box::new(|parent| {
+            parent.spawn(widget::card_display(
+                children![widget::body_text("回放文件无法读取")],
+                children![widget::button_mid("关闭", close_popup)],
+            ));
+        }),
+        blocking: true,
+    });
+}
+
+fn close_popup(_: Trigger<Pointer<Click>>, mut cmds: Commands) {
+    cmds.trigger(ClosePopupEvent);
+}
+
+fn go_back_on_click(_: Trigger<Pointer<Click>>, mut next_menu: ResMut<NextState<Menu>>) {
+    next_menu.set(Menu::Main);
+}
+
+fn go_back(mut next_menu: ResMut<NextState<Menu>>) {
+    next_menu.set(Menu::Main);
+}
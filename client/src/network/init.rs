@@ -2,32 +2,35 @@ use crate::prelude::*;
 use std::ops::Deref;
 
 #[cfg(not(target_arch = "wasm32"))]
-use crate::network::native::create_renet_client;
+use crate::network::native::{SOCKET_ID, create_renet_client};
 use crate::network::{SERVER_ADDR};
 use crate::screens::ScreenState;
 use bevy_http_client::HttpClient;
-use bevy_http_client::prelude::{HttpTypedRequestTrait, TypedRequest, TypedResponse};
-use bevy_renet2::netcode::{ClientAuthentication, NETCODE_USER_DATA_BYTES};
+use bevy_http_client::prelude::{HttpTypedRequestTrait, TypedRequest, TypedResponse, TypedResponseError};
 use bevy_renet2::prelude::{ConnectionConfig, RenetClient, client_connected, client_disconnected};
 use renet2_netcode::{
-    NetcodeClientTransport, NetcodeTransportError, ServerCertHash, WebServerDestination,
+    ConnectToken, NetcodeClientTransport, NetcodeTransportError, ServerCertHash, WebServerDestination,
 };
 use serde::{Deserialize, Serialize};
 use shared::Player;
-use shared::event::GameEvent;
+use shared::auth::{ConnectRequest, ConnectResponse};
+use shared::event::ClientCommand;
 use shared::the_hidden_card::prelude::GameState;
 
 #[cfg(target_arch = "wasm32")]
-use crate::network::wasm::create_renet_client;
+use crate::network::wasm::{SOCKET_ID, create_renet_client};
 pub(crate) fn plugin(app: &mut App) {
     app.register_request_type::<ClientConnectionInfo>();
+    app.register_request_type::<ConnectResponse>();
     app.add_systems(OnEnter(ScreenState::Title), send_request.run_if(run_once));
     app.add_systems(Update, handle_response.run_if(in_state(ScreenState::Title)));
+    app.add_systems(Update, (handle_connect_response, handle_connect_error));
     app.add_systems(
         PreUpdate,
         try_reconnect
             .run_if(client_disconnected)
-            .run_if(resource_exists::<RenetClient>),
+            .run_if(resource_exists::<RenetClient>)
+            .run_if(not(resource_exists::<PendingConnect>)),
     );
 
     app.add_event::<MessageEvent>()
@@ -65,47 +68,112 @@ fn handle_response(
     mut cmds: Commands,
     mut events: EventReader<TypedResponse<ClientConnectionInfo>>,
     user: Res<Player>,
+    mut connect_request: EventWriter<TypedRequest<ConnectResponse>>,
 ) {
     for response in events.read() {
         info!("response received");
         let client_info = response.inner().clone();
         info!("{:?}", client_info);
         cmds.insert_resource(client_info.clone());
-        let (client, transport) = create_renet_client(user.deref()).unwrap();
+        send_connect_request(&mut cmds, &mut connect_request, user.deref(), None);
+    }
+}
+
+/// 服务器签发的游客凭证，重连时带上以沿用同一个 `ClientId`
+#[derive(Resource)]
+pub struct GuestKey(pub String);
+
+/// 已经请求连接令牌，还没有收到结果
+#[derive(Resource)]
+struct PendingConnect;
+
+/// 向服务器请求连接令牌，玩家的身份和名字以服务器返回的为准
+fn send_connect_request(
+    cmds: &mut Commands,
+    connect_request: &mut EventWriter<TypedRequest<ConnectResponse>>,
+    user: &Player,
+    guest_key: Option<&GuestKey>,
+) {
+    let request = ConnectRequest {
+        name: user.name.clone(),
+        socket_id: SOCKET_ID,
+        guest_key: guest_key.map(|key| key.0.clone()),
+    };
+    let mut params = vec![
+        ("name", request.name),
+        ("socket_id", request.socket_id.to_string()),
+    ];
+    if let Some(key) = request.guest_key {
+        params.push(("guest_key", key));
+    }
+    let Ok(url) = url::Url::parse_with_params(&format!("{}/connect", SERVER_ADDR), &params) else {
+        warn!("invalid connect url");
+        return;
+    };
+    if let Ok(request) = HttpClient::new()
+        .get(url.as_str())
+        .try_with_type::<ConnectResponse>()
+    {
+        connect_request.write(request);
+        cmds.insert_resource(PendingConnect);
+    } else {
+        warn!("error send connect request");
+    }
+}
+
+fn handle_connect_response(
+    mut cmds: Commands,
+    mut events: EventReader<TypedResponse<ConnectResponse>>,
+    mut user: ResMut<Player>,
+) {
+    for response in events.read() {
+        let response = response.inner();
+        cmds.remove_resource::<PendingConnect>();
+        let connect_token = match ConnectToken::read(&mut response.connect_token.as_slice()) {
+            Ok(connect_token) => connect_token,
+            Err(err) => {
+                error!("invalid connect token: {}", err);
+                continue;
+            },
+        };
+        info!("connect as {:?}", response.player);
+        *user = response.player.clone();
+        cmds.insert_resource(GuestKey(response.guest_key.clone()));
+
+        let (client, transport) = create_renet_client(connect_token).unwrap();
         cmds.insert_resource(client);
         cmds.insert_resource(transport);
     }
 }
 
+/// 请求失败时允许重新请求
+fn handle_connect_error(mut cmds: Commands, mut events: EventReader<TypedResponseError<ConnectResponse>>) {
+    for error in events.read() {
+        error!("Error retrieving connect token: {}", error.err);
+        cmds.remove_resource::<PendingConnect>();
+    }
+}
+
+/// 断线后重新请求连接令牌，收到令牌后在 [`handle_connect_response`] 中替换客户端
 fn try_reconnect(
     mut cmds: Commands,
-    mut transport_errors: EventWriter<NetcodeTransportError>,
-    mut transport: ResMut<NetcodeClientTransport>,
-    mut client: ResMut<RenetClient>,
-    client_info: Res<ClientConnectionInfo>,
+    mut connect_request: EventWriter<TypedRequest<ConnectResponse>>,
     user: Res<Player>,
+    guest_key: Option<Res<GuestKey>>,
 ) {
-    cmds.remove_resource::<RenetClient>();
-    cmds.remove_resource::<NetcodeClientTransport>();
-    let (client, transport) = create_renet_client(user.deref()).unwrap();
-
-    cmds.insert_resource(client);
-    cmds.insert_resource(transport);
-    // if let Err(e) = transport.send_packets(&mut client) {
-    //     transport_errors.write(e);
-    // }
+    send_connect_request(&mut cmds, &mut connect_request, user.deref(), guest_key.as_deref());
 }
 
-/// 统一处理发送给服务器的游戏事件 [GameEvent]
+/// 统一处理发送给服务器的指令 [ClientCommand]
 /// # 试例:
 /// ```
 /// fn system(mut cmds: Commands) {
-///     let event = GameEvent::JoinRoom(O);
-///     cmds.write(Message(event));
+///     let command = ClientCommand::Pass;
+///     cmds.trigger(MessageEvent(command));
 /// }
 /// ```
 #[derive(Event)]
-pub struct MessageEvent(pub GameEvent);
+pub struct MessageEvent(pub ClientCommand);
 fn send_message_to_server(
     trigger: Trigger<MessageEvent>,
     mut client: ResMut<RenetClient>,
@@ -123,9 +191,9 @@ fn send_message_to_server(
 }
 
 /// 客户端启动后发送启动事件
-fn send_launch_event(mut cmds: Commands, mut sent: Local<bool>, local_user: Res<Player>) {
+fn send_launch_event(mut cmds: Commands, mut sent: Local<bool>) {
     if !*sent {
-        cmds.trigger(MessageEvent(GameEvent::ClientJustLaunched(local_user.id)));
+        cmds.trigger(MessageEvent(ClientCommand::ClientJustLaunched));
         *sent = true;
     }
 }
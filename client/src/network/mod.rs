@@ -9,9 +9,9 @@ mod init;
 
 use bevy::prelude::*;
 
-pub use init::{MessageEvent};
+pub use init::{GuestKey, MessageEvent};
 
-pub const PROTOCOL_ID: u64 = 7;
+pub const PROTOCOL_ID: u64 = shared::PROTOCOL_ID;
 
 #[cfg(feature = "dev")]
 pub const SERVER_ADDR: &str = "http://127.0.0.1:8081";
@@ -27,14 +27,6 @@ pub const WS_URL: &str = "ws://[::1]:8085";
 pub const WS_URL: &str = "wss://poker_server.wasdqe.top:1447";
 
 
-// 开发环境 Native 地址
-#[cfg(all(not(target_arch = "wasm32"), feature = "dev"))]
-pub const NATIVE_SOCKET_ADDR: &str = "[::1]:8082";
-
-// 生产环境 Native 地址 TODO 服务器连接不成功
-#[cfg(all(not(target_arch = "wasm32"), not(feature = "dev")))]
-pub const NATIVE_SOCKET_ADDR: &str = "poker_server.wasdqe.top:1445";
-
 pub(super) fn plugin(app: &mut App) {
     app.add_plugins((http_client::plugin, renet2::plugin, init::plugin));
     // #[cfg(target_arch = "wasm32")]
@@ -1,53 +1,31 @@
-use std::io;
 use bevy::prelude::*;
-use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::net::UdpSocket;
 use std::time::SystemTime;
 
 use crate::network::init::ClientConnectionInfo;
-use crate::network::{NATIVE_SOCKET_ADDR, PROTOCOL_ID};
 use crate::screens::ScreenState;
 
 use bevy_http_client::prelude::{HttpTypedRequestTrait, TypedRequest, TypedResponse};
-use bevy_renet2::netcode::{ClientAuthentication, NETCODE_USER_DATA_BYTES};
+use bevy_renet2::netcode::ClientAuthentication;
 use bevy_renet2::prelude::{ConnectionConfig, DefaultChannel, RenetClient};
-use renet2_netcode::{ClientSocket, NativeSocket, NetcodeClientTransport, ServerCertHash, WebServerDestination};
+use renet2_netcode::{ClientSocket, ConnectToken, NativeSocket, NetcodeClientTransport, ServerCertHash, WebServerDestination};
 
 use serde::{Deserialize, Serialize};
-use shared::Player;
+
+/// 原生客户端连接服务器的 UDP socket
+pub(super) const SOCKET_ID: u8 = 0;
 
 // Create a RenetClient that already connected to a server.
 // Returns an Err if connection fails
 pub(super) fn create_renet_client(
-    user: &Player,
+    connect_token: ConnectToken,
 ) -> anyhow::Result<(RenetClient, NetcodeClientTransport)> {
-    let server_addr: SocketAddr =
-        NATIVE_SOCKET_ADDR
-            .to_socket_addrs()?
-            .next()
-            .ok_or(io::Error::new(io::ErrorKind::Other, "Could not resolve address"))?;
-    info!("{:?}", server_addr);
     let client_socket = NativeSocket::new(UdpSocket::bind("[::1]:0")?).unwrap();
 
     let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
 
     info!("Try connect");
-    let username = user.name.clone();
-    let client_id = user.id.clone();
-
-    let mut user_data = [0u8; NETCODE_USER_DATA_BYTES];
-    if username.len() > NETCODE_USER_DATA_BYTES - 8 {
-        panic!("Username is too long");
-    }
-    user_data[0..8].copy_from_slice(&(username.len() as u64).to_le_bytes());
-    user_data[8..username.len() + 8].copy_from_slice(username.as_bytes());
-
-    let authentication = ClientAuthentication::Unsecure {
-        server_addr,
-        client_id,
-        socket_id: 0,
-        user_data: Some(user_data),
-        protocol_id: PROTOCOL_ID,
-    };
+    let authentication = ClientAuthentication::Secure { connect_token };
     let client = RenetClient::new(
         ConnectionConfig {
             // At 60hz this is becoming 28.8 Mbps
@@ -4,38 +4,31 @@ use web_time::SystemTime;
 
 use bevy_http_client::HttpClient;
 use bevy_http_client::prelude::{HttpTypedRequestTrait, TypedRequest, TypedResponse};
-use bevy_renet2::netcode::{ClientAuthentication, NETCODE_USER_DATA_BYTES};
+use bevy_renet2::netcode::ClientAuthentication;
 use bevy_renet2::prelude::{ConnectionConfig, RenetClient, client_disconnected, ChannelConfig, DefaultChannel};
 use renet2_netcode::{
-    NetcodeClientTransport, NetcodeTransportError, ServerCertHash, WebServerDestination,
+    ConnectToken, NetcodeClientTransport, NetcodeTransportError, ServerCertHash, WebServerDestination,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::network::{PROTOCOL_ID, SERVER_ADDR, WS_URL};
+use crate::network::{SERVER_ADDR, WS_URL};
 use renet2_netcode::{
     ClientSocket, WebSocketClient, WebSocketClientConfig, WebTransportClient,
     WebTransportClientConfig, webtransport_is_available_with_cert_hashes,
 };
-use shared::Player;
 
 
 use crate::network::init::ClientConnectionInfo;
 
+/// 浏览器通过 WebSocket 连接服务器，服务器上 WebSocket 的 socket id 为 2
+pub(super) const SOCKET_ID: u8 = 2;
+
 pub(super) fn create_renet_client(
-    user: &Player,
+    connect_token: ConnectToken,
 ) -> anyhow::Result<(RenetClient, NetcodeClientTransport)> {
     let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
 
     info!("Try connect");
-    let username = user.name.clone();
-    let client_id = user.id.clone();
-
-    let mut user_data = [0u8; NETCODE_USER_DATA_BYTES];
-    if username.len() > NETCODE_USER_DATA_BYTES - 8 {
-        panic!("Username is too long");
-    }
-    user_data[0..8].copy_from_slice(&(username.len() as u64).to_le_bytes());
-    user_data[8..username.len() + 8].copy_from_slice(username.as_bytes());
 
     let (client, transport) = {
         let url = url::Url::parse(WS_URL).unwrap();
@@ -50,13 +43,7 @@ pub(super) fn create_renet_client(
             client_channels_config: DefaultChannel::config(),
         }, socket.is_reliable());
 
-        let client_auth = ClientAuthentication::Unsecure {
-            client_id: client_id as u64,
-            protocol_id: PROTOCOL_ID,
-            socket_id: 2, //WebSocket socket id is 2 in this example
-            server_addr: socket.server_address(),
-            user_data: Some(user_data),
-        };
+        let client_auth = ClientAuthentication::Secure { connect_token };
         let transport = NetcodeClientTransport::new(current_time, client_auth, socket).unwrap();
 
         (client, transport)
@@ -1,5 +1,7 @@
 mod popup;
+mod toast;
 
 pub mod prelude {
     pub use super::popup::{PopupPlugin, ClosePopupEvent, OpenPopupEvent};
+    pub use super::toast::{ToastPlugin, ToastEvent};
 }
\ No newline at end of file
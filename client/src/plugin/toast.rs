@@ -0,0 +1,61 @@
+use crate::prelude::*;
+use crate::prelude::ui_palette::ThemeColor;
+
+/// 提示显示的时间
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+
+/// 屏幕上方短暂显示的提示，不阻挡操作，新的提示会替换旧的
+pub struct ToastPlugin;
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(show_toast_system)
+            .add_systems(Update, expire_toast_system);
+    }
+}
+
+#[derive(Event)]
+pub struct ToastEvent(pub String);
+
+#[derive(Component)]
+struct Toast(Timer);
+
+fn show_toast_system(
+    trigger: Trigger<ToastEvent>,
+    mut cmds: Commands,
+    toast_query: Query<Entity, With<Toast>>,
+) {
+    for entity in toast_query.iter() {
+        cmds.entity(entity).despawn();
+    }
+    cmds.spawn((
+        Name::new("Toast"),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Percent(100.),
+            top: Vw(5.),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        Pickable::IGNORE,
+        GlobalZIndex(100),
+        Toast(Timer::new(TOAST_DURATION, TimerMode::Once)),
+        children![(
+            Node {
+                padding: UiRect::axes(Vw(2.), Vw(1.)),
+                ..default()
+            },
+            BackgroundColor(ThemeColor::POPUP),
+            BorderRadius::all(Vw(1.)),
+            Pickable::IGNORE,
+            children![text_base(&trigger.event().0, Vw(2.), ThemeColor::BODY_TEXT_LIGHT)],
+        )],
+    ));
+}
+
+fn expire_toast_system(mut cmds: Commands, time: Res<Time>, mut toast_query: Query<(Entity, &mut Toast)>) {
+    for (entity, mut toast) in toast_query.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            cmds.entity(entity).despawn();
+        }
+    }
+}
@@ -2,10 +2,15 @@
 
 use bevy::{input::common_conditions::input_just_pressed, prelude::*, ui::Val::*};
 
-use crate::{Pause, game::hidden_card::level::spawn_level, menus::Menu, screens::ScreenState};
+use crate::{
+    Pause,
+    game::hidden_card::{chat::chat_open, level::spawn_level},
+    menus::Menu,
+    screens::{InGame, ScreenState},
+};
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_systems(OnEnter(ScreenState::Gameplay), spawn_level);
+    app.add_systems(OnEnter(InGame), spawn_level);
     // Toggle pause on key press.
     app.add_systems(
         Update,
@@ -13,7 +18,12 @@ pub(super) fn plugin(app: &mut App) {
             (pause, spawn_pause_overlay, open_pause_menu).run_if(
                 in_state(ScreenState::Gameplay)
                     .and(in_state(Menu::None))
-                    .and(input_just_pressed(KeyCode::KeyP).or(input_just_pressed(KeyCode::Escape))),
+                    // 聊天输入时 P 键用于打字
+                    .and(
+                        input_just_pressed(KeyCode::KeyP)
+                            .and(not(chat_open))
+                            .or(input_just_pressed(KeyCode::Escape)),
+                    ),
             ),
             close_menu.run_if(
                 in_state(ScreenState::Gameplay)
@@ -2,17 +2,23 @@
 
 mod gameplay;
 mod loading;
+mod replay;
 mod splash;
 mod title;
 
 use crate::prelude::*;
 
+pub use replay::ReplayViewer;
+
 pub(super) fn plugin(app: &mut App) {
     app.init_state::<ScreenState>();
+    app.add_computed_state::<InGame>();
+    app.enable_state_scoped_entities::<InGame>();
 
     app.add_plugins((
         gameplay::plugin,
         loading::plugin,
+        replay::plugin,
         splash::plugin,
         title::plugin,
     ));
@@ -27,6 +33,19 @@ pub enum ScreenState {
     Title,
     Loading,
     Gameplay,
+    Replay,
+}
+
+/// 牌桌界面是否显示，对局和回放共用同一套牌桌显示
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = ScreenState;
+
+    fn compute(screen: ScreenState) -> Option<Self> {
+        matches!(screen, ScreenState::Gameplay | ScreenState::Replay).then_some(InGame)
+    }
 }
 
 #[derive(Resource, Reflect)]
@@ -0,0 +1,202 @@
+//! 回放界面，逐步查看服务器保存的牌局
+//!
+//! 牌桌显示与对局共用，每次切换步数时把该步的状态作为 [`GameEvent::SyncState`] 发送给本地系统。
+
+use shared::Player;
+use shared::event::GameEvent;
+use shared::replay::Replay;
+use shared::the_hidden_card::prelude::*;
+
+use crate::game::assets::SmallCardAssets;
+use crate::prelude::*;
+use crate::screens::ScreenState;
+use crate::theme::palette::ThemeColor;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(ScreenState::Replay), spawn_replay_view);
+    app.add_systems(OnExit(ScreenState::Replay), remove_viewer);
+
+    app.add_systems(
+        Update,
+        (
+            previous_step.run_if(input_just_pressed(KeyCode::ArrowLeft)),
+            next_step.run_if(input_just_pressed(KeyCode::ArrowRight)),
+            go_back.run_if(input_just_pressed(KeyCode::Escape)),
+        )
+            .run_if(in_state(ScreenState::Replay))
+            .run_if(resource_exists::<ReplayViewer>),
+    );
+    app.add_systems(
+        Update,
+        sync_replay_step
+            .in_set(AppSystems::SyncEarly)
+            .run_if(in_state(ScreenState::Replay))
+            .run_if(resource_exists_and_changed::<ReplayViewer>),
+    );
+    app.add_systems(
+        Update,
+        (update_step_text, update_replay_hands)
+            .in_set(AppSystems::Update)
+            .run_if(in_state(ScreenState::Replay))
+            .run_if(resource_exists_and_changed::<ReplayViewer>)
+            .run_if(resource_exists::<GameState>),
+    );
+}
+
+/// 正在查看的回放
+#[derive(Resource)]
+pub struct ReplayViewer {
+    replay: Replay,
+    step: usize,
+}
+
+impl ReplayViewer {
+    /// 从第 1 步开始查看，即录制开始时的状态快照
+    pub fn new(replay: Replay) -> Self {
+        let step = replay.len().min(1);
+        Self { replay, step }
+    }
+}
+
+#[derive(Component)]
+struct ReplayStepText;
+
+/// 其他座位的明牌，数值为相对于底部座位的偏移
+#[derive(Component)]
+struct ReplayHandsRow(usize);
+
+fn spawn_replay_view(mut cmds: Commands) {
+    cmds.spawn((
+        Name::new("Replay UI root"),
+        Node::DEFAULT.full_size().abs(),
+        Pickable::IGNORE,
+        GlobalZIndex(1),
+        StateScoped(ScreenState::Replay),
+        children![
+            (
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Vw(12.),
+                    left: Vw(20.),
+                    width: Vw(60.),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Vw(0.8),
+                    ..default()
+                },
+                Pickable::IGNORE,
+                children![hands_row(1), hands_row(2), hands_row(3)],
+            ),
+            (
+                Node {
+                    position_type: PositionType::Absolute,
+                    right: Vw(2.),
+                    bottom: Vw(2.),
+                    align_items: AlignItems::Center,
+                    column_gap: Vw(1.),
+                    ..default()
+                },
+                children![
+                    (
+                        text_base("", Vw(2.), ThemeColor::PRIMARY_TEXT_LIGHT),
+                        ReplayStepText
+                    ),
+                    button_mid("上一步", previous_step_click),
+                    button_mid("下一步", next_step_click),
+                    button_mid("返回", go_back_click),
+                ],
+            ),
+        ],
+    ));
+}
+
+fn hands_row(offset: usize) -> impl Bundle {
+    (
+        Node {
+            height: Vw(3.6),
+            align_items: AlignItems::Center,
+            column_gap: Vw(0.3),
+            ..default()
+        },
+        Pickable::IGNORE,
+        ReplayHandsRow(offset),
+    )
+}
+
+fn remove_viewer(mut cmds: Commands) {
+    cmds.remove_resource::<ReplayViewer>();
+}
+
+/// 把当前步的状态同步给牌桌显示
+fn sync_replay_step(viewer: Res<ReplayViewer>, mut game_event_writer: EventWriter<GameEvent>) {
+    let state = viewer.replay.state_at(viewer.step);
+    game_event_writer.write(GameEvent::SyncState(state));
+}
+
+fn update_step_text(viewer: Res<ReplayViewer>, mut text_query: Query<&mut Text, With<ReplayStepText>>) {
+    let mut text = r!(text_query.single_mut());
+    **text = format!("第 {}/{} 步", viewer.step, viewer.replay.len());
+}
+
+/// ### 显示其他座位的手牌
+/// 回放包含所有人的手牌，底部座位使用手牌区显示，其余座位在这里以小牌显示。
+fn update_replay_hands(
+    mut cmds: Commands,
+    rows_query: Query<(Entity, &ReplayHandsRow)>,
+    children_query: Query<&Children>,
+    state: Res<GameState>,
+    local_player: Res<Player>,
+    small_card_assets: Res<SmallCardAssets>,
+) {
+    let bottom_index = state.get_player_seat_index_by_id(local_player.id).unwrap_or(0);
+    for (entity, row) in rows_query.iter() {
+        let seat = &state.get_seats()[(bottom_index + row.0) % 4];
+        let name = seat.get_player().map(|player| player.name.clone()).unwrap_or_default();
+
+        if let Ok(children) = children_query.get(entity) {
+            for child in children.iter() {
+                cmds.entity(child).despawn();
+            }
+        }
+        cmds.entity(entity).with_children(|parent| {
+            parent.spawn(text_base(name, Vw(1.6), ThemeColor::PRIMARY_TEXT_LIGHT));
+            for card in seat.hands.iter() {
+                parent.spawn((
+                    Node {
+                        width: Vw(2.4),
+                        height: Vw(3.5),
+                        ..default()
+                    },
+                    small_card_assets.image_node(card),
+                ));
+            }
+        });
+    }
+}
+
+fn previous_step(mut viewer: ResMut<ReplayViewer>) {
+    if viewer.step > 0 {
+        viewer.step -= 1;
+    }
+}
+
+fn next_step(mut viewer: ResMut<ReplayViewer>) {
+    if viewer.step < viewer.replay.len() {
+        viewer.step += 1;
+    }
+}
+
+fn go_back(mut next_screen: ResMut<NextState<ScreenState>>) {
+    next_screen.set(ScreenState::Title);
+}
+
+fn previous_step_click(_: Trigger<Pointer<Click>>, viewer: ResMut<ReplayViewer>) {
+    previous_step(viewer);
+}
+
+fn next_step_click(_: Trigger<Pointer<Click>>, viewer: ResMut<ReplayViewer>) {
+    next_step(viewer);
+}
+
+fn go_back_click(_: Trigger<Pointer<Click>>, next_screen: ResMut<NextState<ScreenState>>) {
+    go_back(next_screen);
+}
@@ -62,7 +62,11 @@ fn apply_interaction_palette<C: Component<Mutability = Mutable> + Clone>(
             Option<&InteractionDisabled>,
             Option<&InteractionSelected>,
         ),
-        Or<(Changed<Interaction>, Changed<InteractionDisabled>)>,
+        Or<(
+            Changed<Interaction>,
+            Changed<InteractionDisabled>,
+            Changed<InteractionSelected>,
+        )>,
     >,
 ) {
     for (interaction, palette, mut value, disabled, selected) in &mut palette_query {
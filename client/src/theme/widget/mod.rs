@@ -115,6 +115,30 @@ where
     )
 }
 
+/// 文字较短的小按钮，用于聊天的快捷短语等
+pub fn button_compact<E, B, M, I>(text: impl Into<String>, action: I) -> impl Bundle
+where
+    E: Event,
+    B: Bundle,
+    I: IntoObserverSystem<E, B, M>,
+{
+    button_base(
+        text,
+        Vw(1.6),
+        action,
+        (
+            Node {
+                height: Vw(3.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                padding: UiRect::horizontal(Vw(1.)),
+                ..default()
+            },
+            BorderRadius::all(Vw(0.6)),
+        ),
+    )
+}
+
 /// A small square button with text and an action defined as an [`Observer`].
 pub fn button_small<E, B, M, I>(text: impl Into<String>, action: I) -> impl Bundle
 where
@@ -0,0 +1,119 @@
+//! 服务器端机器人玩家
+//! 机器人没有客户端连接，它们的动作和真人玩家一样通过 [`Room::process_event`](super::room::Room) 校验后生效。
+//!
+//! 托管的玩家也由机器人代打，使用玩家自己的身份，见 [`Bot::managed`]。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use renet2::ClientId;
+use shared::Player;
+use shared::event::GameEvent;
+use shared::the_hidden_card::prelude::{BotDifficulty, PlayerView, Strategy};
+use shared::the_hidden_card::state::{GameState, Stage};
+
+/// 机器人 id 从 `u64::MAX / 2` 开始分配，游客 id 由 `GuestStore` 在这之下随机签发，两者不会冲突
+static NEXT_BOT_ID: AtomicU64 = AtomicU64::new(u64::MAX / 2);
+
+pub struct Bot {
+    player: Player,
+    strategy: Box<dyn Strategy>,
+    // 每局叫牌阶段只考虑一次是否包牌
+    block_considered: bool,
+    // 代替托管的玩家出牌，不替玩家包牌，也不替玩家准备
+    managed: bool,
+}
+
+impl Bot {
+    pub fn new(seat_index: usize, difficulty: BotDifficulty) -> Self {
+        let id = NEXT_BOT_ID.fetch_add(1, Ordering::Relaxed);
+        Self {
+            player: Player {
+                id,
+                name: format!("机器人{}", seat_index + 1),
+                avatar: None,
+            },
+            strategy: difficulty.strategy(id),
+            block_considered: false,
+            managed: false,
+        }
+    }
+
+    /// 代替托管的玩家 `player` 出牌
+    pub fn managed(player: Player, difficulty: BotDifficulty) -> Self {
+        Self {
+            strategy: difficulty.strategy(player.id),
+            player,
+            block_considered: true,
+            managed: true,
+        }
+    }
+
+    pub fn is_managed(&self) -> bool {
+        self.managed
+    }
+
+    pub fn id(&self) -> ClientId {
+        self.player.id
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    /// 机器人是否需要行动，每帧调用，不做策略计算
+    pub fn is_pending(&self, state: &GameState) -> bool {
+        let Some(seat_index) = state.get_player_seat_index_by_id(self.id()) else {
+            return false;
+        };
+        let seat = &state.get_seats()[seat_index];
+
+        match state.stage {
+            Stage::PreGame | Stage::Ended(_) => !self.managed && !seat.ready,
+            Stage::DealCards => !seat.hands.is_empty() && !seat.hands_ready,
+            Stage::CallCard(caller_index) => caller_index == seat_index || !self.block_considered,
+            Stage::PlayCards => state.current_player_seat == Some(seat_index),
+        }
+    }
+
+    /// 由策略决定机器人的下一步动作，不需要行动时返回 `None`
+    pub fn decide(&mut self, state: &GameState) -> Option<GameEvent> {
+        let seat_index = state.get_player_seat_index_by_id(self.id())?;
+        let seat = &state.get_seats()[seat_index];
+
+        match state.stage {
+            Stage::PreGame | Stage::Ended(_) => {
+                (!self.managed && !seat.ready).then_some(GameEvent::Ready { client_id: self.id() })
+            },
+            Stage::DealCards => {
+                self.block_considered = self.managed;
+                (!seat.hands.is_empty() && !seat.hands_ready)
+                    .then_some(GameEvent::DealCardsDone(self.id()))
+            },
+            Stage::CallCard(caller_index) => {
+                let view = PlayerView::new(state, seat_index);
+                if !self.block_considered {
+                    self.block_considered = true;
+                    if self.strategy.should_block(&view) {
+                        return Some(GameEvent::Blocking(seat_index));
+                    }
+                }
+                if caller_index != seat_index {
+                    return None;
+                }
+                let callable = state.callable_cards(seat_index)?;
+                let card = self.strategy.call_card(&view, &callable);
+                Some(GameEvent::CallCard { seat_index, card })
+            },
+            Stage::PlayCards => {
+                if state.current_player_seat != Some(seat_index) {
+                    return None;
+                }
+                let view = PlayerView::new(state, seat_index);
+                match self.strategy.play(&view) {
+                    Some(cards) => Some(GameEvent::PlayCards(seat_index, cards)),
+                    None => Some(GameEvent::Pass(seat_index)),
+                }
+            },
+        }
+    }
+}
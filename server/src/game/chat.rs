@@ -0,0 +1,41 @@
+//! ### 聊天限流
+//! 每个客户端有一个令牌桶，最多连续发送 [`CHAT_BURST`] 条消息，之后每 [`CHAT_REFILL`] 恢复一条。
+
+use std::time::{Duration, Instant};
+
+/// 连续发送的消息数上限
+const CHAT_BURST: u32 = 3;
+/// 恢复一条消息额度的时间
+const CHAT_REFILL: Duration = Duration::from_secs(2);
+
+pub struct ChatLimiter {
+    tokens: u32,
+    refilled_at: Instant,
+}
+
+impl ChatLimiter {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            tokens: CHAT_BURST,
+            refilled_at: now,
+        }
+    }
+
+    /// 还有额度时消耗一条并返回 `true`
+    pub fn try_send(&mut self, now: Instant) -> bool {
+        let refills = (now.duration_since(self.refilled_at).as_millis() / CHAT_REFILL.as_millis()) as u32;
+        if refills > 0 {
+            self.tokens = (self.tokens + refills).min(CHAT_BURST);
+            self.refilled_at += CHAT_REFILL * refills;
+        }
+        // 额度是满的时候从这次发送开始计时
+        if self.tokens == CHAT_BURST {
+            self.refilled_at = now;
+        }
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
@@ -0,0 +1,294 @@
+//! ### 快速匹配
+//! 玩家按底分排队，同一底分凑齐四人后一起坐进新房间；
+//! 排在最前的玩家等待超过 [`RoomConfig::match_timeout`] 后，用机器人补满空位。
+//!
+//! [`RoomConfig::match_timeout`]: crate::game::RoomConfig::match_timeout
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use log::{error, info};
+use renet2::ClientId;
+use shared::Player;
+use shared::error::RoomServiceError;
+use shared::event::{ClientCommand, ServerEvent};
+use shared::lobby::RoomSettings;
+
+use crate::game::Rooms;
+use crate::game_server::RenetServerWithConfig;
+
+/// 一桌的人数
+const TABLE_SIZE: usize = 4;
+
+struct QueuedPlayer {
+    player: Player,
+    since: Instant,
+}
+
+#[derive(Default)]
+pub struct Matchmaker {
+    /// 按底分分开的队列
+    queues: BTreeMap<i32, VecDeque<QueuedPlayer>>,
+}
+
+impl Matchmaker {
+    pub fn process_command(
+        &mut self,
+        player: &Player,
+        command: ClientCommand,
+        rooms: &Rooms,
+        server: &mut RenetServerWithConfig,
+    ) -> Result<(), RoomServiceError> {
+        match command {
+            ClientCommand::QuickMatch { base_stake } => {
+                if rooms.room_of(player.id).is_some() || self.stake_of(player.id).is_some() {
+                    return Err(RoomServiceError::AlreadyInRoom);
+                }
+                RoomSettings { base_stake, ..Default::default() }.validate()?;
+
+                info!("Client {} queued for quick match, base stake {}", player.id, base_stake);
+                self.queues.entry(base_stake).or_default().push_back(QueuedPlayer {
+                    player: player.clone(),
+                    since: Instant::now(),
+                });
+                self.notify_queue(base_stake, rooms, server);
+                Ok(())
+            },
+            ClientCommand::CancelMatch => {
+                self.remove(player.id, rooms, server);
+                server.send_event(player.id, ServerEvent::MatchCancelled);
+                Ok(())
+            },
+            _ => Err(RoomServiceError::ActionNotAllowed),
+        }
+    }
+
+    /// 玩家断开连接或取消匹配时离开队列
+    pub fn remove(&mut self, client_id: ClientId, rooms: &Rooms, server: &mut RenetServerWithConfig) {
+        let Some(base_stake) = self.stake_of(client_id) else {
+            return;
+        };
+        if let Some(queue) = self.queues.get_mut(&base_stake) {
+            queue.retain(|queued| queued.player.id != client_id);
+        }
+        self.notify_queue(base_stake, rooms, server);
+    }
+
+    /// 每帧调用，凑齐四人或等待超时的玩家坐进新房间
+    pub fn update(&mut self, now: Instant, rooms: &mut Rooms, server: &mut RenetServerWithConfig) {
+        let timeout = rooms.config().match_timeout;
+        let stakes = self.queues.keys().copied().collect::<Vec<_>>();
+        for base_stake in stakes {
+            let queue = self.queues.get_mut(&base_stake).unwrap();
+            let mut matched = false;
+            loop {
+                let timed_out = match (queue.front(), timeout) {
+                    (Some(first), Some(timeout)) => now.duration_since(first.since) >= timeout,
+                    _ => false,
+                };
+                if queue.len() < TABLE_SIZE && !timed_out {
+                    break;
+                }
+                // 排队期间已经通过大厅进入其他房间的玩家不再匹配
+                let players = queue
+                    .drain(..TABLE_SIZE.min(queue.len()))
+                    .map(|queued| queued.player)
+                    .filter(|player| rooms.room_of(player.id).is_none())
+                    .collect::<Vec<_>>();
+                matched = true;
+                if players.is_empty() {
+                    continue;
+                }
+                info!("Quick match found {} players at base stake {}", players.len(), base_stake);
+                let ids = players.iter().map(|player| player.id).collect::<Vec<_>>();
+                let result = rooms.create_match_room(players, base_stake, server);
+                if let Err(err) = &result {
+                    error!("Failed to create quick match room: {}", err);
+                }
+                // 已经移出队列但没有坐进房间的玩家结束匹配，由玩家决定是否重新排队
+                for id in ids.into_iter().filter(|id| rooms.room_of(*id).is_none()) {
+                    if let Err(err) = &result {
+                        server.send_event(id, ServerEvent::RoomError(err.clone()));
+                    }
+                    server.send_event(id, ServerEvent::MatchCancelled);
+                }
+            }
+            if matched {
+                self.notify_queue(base_stake, rooms, server);
+            }
+        }
+        self.queues.retain(|_, queue| !queue.is_empty());
+    }
+
+    fn stake_of(&self, client_id: ClientId) -> Option<i32> {
+        self.queues
+            .iter()
+            .find(|(_, queue)| queue.iter().any(|queued| queued.player.id == client_id))
+            .map(|(base_stake, _)| *base_stake)
+    }
+
+    /// 把排队位置和预计等待时间发给队列中的每个玩家
+    ///
+    /// 每四人一组，预计等待时间是这一组排在最前的玩家到达超时的剩余时间，没有超时设置时无法估计。
+    /// 前面的组总是先超时，轮到这一组时它的计时已经在走，所以不用累加前面组的等待时间
+    fn notify_queue(&self, base_stake: i32, rooms: &Rooms, server: &mut RenetServerWithConfig) {
+        let events = self.queue_events(base_stake, Instant::now(), rooms.config().match_timeout);
+        for (client_id, event) in events {
+            server.send_event(client_id, event);
+        }
+    }
+
+    fn queue_events(&self, base_stake: i32, now: Instant, timeout: Option<Duration>) -> Vec<(ClientId, ServerEvent)> {
+        let Some(queue) = self.queues.get(&base_stake) else {
+            return Vec::new();
+        };
+        queue
+            .iter()
+            .enumerate()
+            .map(|(index, queued)| {
+                let group_first = &queue[index / TABLE_SIZE * TABLE_SIZE];
+                let estimated_wait_secs = timeout
+                    .map(|timeout| timeout.saturating_sub(now.duration_since(group_first.since)).as_secs());
+                let event = ServerEvent::MatchQueued {
+                    position: index + 1,
+                    estimated_wait_secs,
+                };
+                (queued.player.id, event)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{RoomConfig, RoomList};
+    use renet2::{ConnectionConfig, DefaultChannel, RenetServer};
+
+    fn test_server() -> RenetServerWithConfig {
+        RenetServerWithConfig::new(RenetServer::new(ConnectionConfig {
+            available_bytes_per_tick: 60_000,
+            server_channels_config: DefaultChannel::config(),
+            client_channels_config: DefaultChannel::config(),
+        }))
+    }
+
+    fn test_rooms() -> Rooms {
+        let config = RoomConfig {
+            match_timeout: Some(Duration::from_secs(30)),
+            bot_fill_timeout: None,
+            ..Default::default()
+        };
+        Rooms::new(config, RoomList::default())
+    }
+
+    fn player(id: ClientId) -> Player {
+        Player { id, name: format!("玩家{}", id), avatar: None }
+    }
+
+    fn quick_match(
+        matchmaker: &mut Matchmaker,
+        id: ClientId,
+        base_stake: i32,
+        rooms: &Rooms,
+        server: &mut RenetServerWithConfig,
+    ) {
+        let command = ClientCommand::QuickMatch { base_stake };
+        matchmaker.process_command(&player(id), command, rooms, server).unwrap();
+    }
+
+    #[test]
+    fn test_same_stake_players_are_seated_together() {
+        let mut server = test_server();
+        let mut rooms = test_rooms();
+        let mut matchmaker = Matchmaker::default();
+        for id in 1..=4 {
+            quick_match(&mut matchmaker, id, 1, &rooms, &mut server);
+        }
+        quick_match(&mut matchmaker, 5, 2, &rooms, &mut server);
+        matchmaker.update(Instant::now(), &mut rooms, &mut server);
+
+        let room_id = rooms.room_of(1).unwrap();
+        assert!((2..=4).all(|id| rooms.room_of(id) == Some(room_id)));
+        let state = rooms.game_state(room_id).unwrap();
+        assert!(!state.has_empty_seat());
+        // 其他底分的玩家还在排队
+        assert_eq!(rooms.room_of(5), None);
+        assert_eq!(matchmaker.stake_of(5), Some(2));
+    }
+
+    #[test]
+    fn test_timeout_fills_with_bots() {
+        let mut server = test_server();
+        let mut rooms = test_rooms();
+        let mut matchmaker = Matchmaker::default();
+        quick_match(&mut matchmaker, 1, 1, &rooms, &mut server);
+
+        matchmaker.update(Instant::now(), &mut rooms, &mut server);
+        assert_eq!(rooms.room_of(1), None);
+
+        matchmaker.update(Instant::now() + Duration::from_secs(31), &mut rooms, &mut server);
+        let room_id = rooms.room_of(1).unwrap();
+        let state = rooms.game_state(room_id).unwrap();
+        assert!(!state.has_empty_seat());
+        assert!(state.get_player_seat_index_by_id(1).is_some());
+        assert_eq!(matchmaker.stake_of(1), None);
+    }
+
+    #[test]
+    fn test_cancel_leaves_queue() {
+        let mut server = test_server();
+        let mut rooms = test_rooms();
+        let mut matchmaker = Matchmaker::default();
+        for id in 1..=4 {
+            quick_match(&mut matchmaker, id, 1, &rooms, &mut server);
+        }
+        matchmaker.process_command(&player(4), ClientCommand::CancelMatch, &rooms, &mut server).unwrap();
+        assert_eq!(matchmaker.stake_of(4), None);
+
+        // 取消后只剩三人，没有超时前不会开桌
+        matchmaker.update(Instant::now(), &mut rooms, &mut server);
+        assert!((1..=4).all(|id| rooms.room_of(id).is_none()));
+
+        // 取消后可以重新排队，重复排队被拒绝
+        quick_match(&mut matchmaker, 4, 1, &rooms, &mut server);
+        let command = ClientCommand::QuickMatch { base_stake: 1 };
+        assert_eq!(
+            matchmaker.process_command(&player(4), command, &rooms, &mut server),
+            Err(RoomServiceError::AlreadyInRoom)
+        );
+    }
+
+    #[test]
+    fn test_queue_positions() {
+        let mut server = test_server();
+        let rooms = test_rooms();
+        let mut matchmaker = Matchmaker::default();
+        for id in 1..=6 {
+            quick_match(&mut matchmaker, id, 1, &rooms, &mut server);
+        }
+        matchmaker.remove(2, &rooms, &mut server);
+
+        let timeout = Some(Duration::from_secs(30));
+        let first_since = matchmaker.queues[&1][0].since;
+        let events = matchmaker.queue_events(1, first_since + Duration::from_secs(10), timeout);
+        let positions = events
+            .iter()
+            .map(|(id, event)| match event {
+                ServerEvent::MatchQueued { position, .. } => (*id, *position),
+                _ => panic!("unexpected event {}", event),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![(1, 1), (3, 2), (4, 3), (5, 4), (6, 5)]);
+
+        // 第一组按排在最前的玩家估计，第二组从第五个人开始计时
+        let ServerEvent::MatchQueued { estimated_wait_secs, .. } = &events[0].1 else {
+            unreachable!()
+        };
+        assert_eq!(*estimated_wait_secs, Some(20));
+        assert!(matchmaker.queue_events(1, first_since, None).iter().all(|(_, event)| matches!(
+            event,
+            ServerEvent::MatchQueued { estimated_wait_secs: None, .. }
+        )));
+    }
+}
@@ -1,3 +1,10 @@
+mod bot;
+mod chat;
+mod matchmaking;
+mod profile;
 mod room;
 
-pub use room::Rooms;
\ No newline at end of file
+pub use matchmaking::Matchmaker;
+pub use profile::spawn_profile_saver;
+
+pub use room::{RoomConfig, RoomList, Rooms};
\ No newline at end of file
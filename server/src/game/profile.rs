@@ -0,0 +1,154 @@
+//! ### 玩家资料存储
+//! 所有玩家的 [`PlayerProfile`] 保存在一个 JSON 文件中，不依赖外部服务。
+//!
+//! 修改只在内存中标记，由 [`spawn_profile_saver`] 的线程定期写入，游戏循环不等待文件写入。
+//! 写入时先写临时文件再替换原文件，写到一半退出也不会损坏已有的数据。
+//! 文件无法解析时不再写入，避免覆盖原来的数据。
+
+use log::{error, info};
+use renet2::ClientId;
+use shared::Player;
+use shared::profile::PlayerProfile;
+use shared::the_hidden_card::prelude::GameResult;
+use shared::the_hidden_card::state::GameState;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 玩家资料写入文件的间隔
+const PROFILE_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+pub struct ProfileStore {
+    /// 保存的文件，`None` 表示只在内存中记录
+    path: Option<PathBuf>,
+    profiles: HashMap<ClientId, PlayerProfile>,
+    /// 有还没有写入文件的修改
+    dirty: bool,
+}
+
+impl ProfileStore {
+    /// 读取 `path`，文件不存在时从空开始
+    pub fn open(path: PathBuf) -> Self {
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                info!("Profile file {} not found, starting empty", path.display());
+                return Self { path: Some(path), ..Default::default() };
+            },
+            Err(err) => {
+                error!("Failed to read profiles from {}: {}, profiles will not be saved", path.display(), err);
+                return Self::default();
+            },
+        };
+        match serde_json::from_str::<Vec<PlayerProfile>>(&json) {
+            Ok(profiles) => {
+                info!("Loaded {} profiles from {}", profiles.len(), path.display());
+                Self {
+                    path: Some(path),
+                    profiles: profiles.into_iter().map(|profile| (profile.id, profile)).collect(),
+                    dirty: false,
+                }
+            },
+            Err(err) => {
+                error!("Failed to parse profiles from {}: {}, profiles will not be saved", path.display(), err);
+                Self::default()
+            },
+        }
+    }
+
+    /// 玩家入座时读取资料，没有时创建，名字和头像更新为最新的
+    pub fn load(&mut self, player: &Player) -> &PlayerProfile {
+        let created = !self.profiles.contains_key(&player.id);
+        let profile = self
+            .profiles
+            .entry(player.id)
+            .or_insert_with(|| PlayerProfile::new(player));
+        let changed = profile.name != player.name || profile.avatar != player.avatar;
+        profile.name = player.name.clone();
+        profile.avatar = player.avatar.clone();
+        self.dirty |= created || changed;
+        &self.profiles[&player.id]
+    }
+
+    /// 一手牌结算后写入座位上玩家的得分和统计，`players` 为 (座位索引, 玩家)，不包括机器人
+    pub fn record_hand(&mut self, players: &[(usize, Player)], state: &GameState, result: &GameResult) {
+        for (seat_index, player) in players {
+            let profile = self
+                .profiles
+                .entry(player.id)
+                .or_insert_with(|| PlayerProfile::new(player));
+            profile.coins = profile.coins.saturating_add(result.score_of(*seat_index));
+            profile.stats.record_hand(*seat_index, state, result);
+        }
+        self.dirty = true;
+    }
+
+    /// 有修改时编码要写入的内容并清除修改标记，文件在锁外写入
+    fn take_changes(&mut self) -> Option<(PathBuf, String)> {
+        let path = self.path.clone()?;
+        if !self.dirty {
+            return None;
+        }
+        self.dirty = false;
+        let mut profiles = self.profiles.values().collect::<Vec<_>>();
+        profiles.sort_by_key(|profile| profile.id);
+        match serde_json::to_string_pretty(&profiles) {
+            Ok(json) => Some((path, json)),
+            Err(err) => {
+                error!("Failed to encode profiles: {}", err);
+                None
+            },
+        }
+    }
+}
+
+/// 启动定期写入玩家资料的线程，写入失败时保留修改标记，下次重试
+pub fn spawn_profile_saver(store: Arc<Mutex<ProfileStore>>) {
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(PROFILE_SAVE_INTERVAL);
+            let Some((path, json)) = store.lock().unwrap().take_changes() else {
+                continue;
+            };
+            if let Err(err) = write_file(&path, json) {
+                error!("Failed to save profiles to {}: {}", path.display(), err);
+                store.lock().unwrap().dirty = true;
+            }
+        }
+    });
+}
+
+/// 先写临时文件再替换原文件
+fn write_file(path: &Path, json: String) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&tmp, json))
+        .and_then(|_| fs::rename(&tmp, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_are_taken_once() {
+        let path = std::env::temp_dir().join(format!("profiles-{}.json", std::process::id()));
+        let mut store = ProfileStore::open(path.clone());
+        assert_eq!(store.take_changes(), None);
+
+        let player = Player { id: 1, name: "玩家".to_string(), avatar: None };
+        store.load(&player);
+        let (changed_path, json) = store.take_changes().unwrap();
+        assert_eq!(changed_path, path);
+        assert!(json.contains("玩家"));
+        assert_eq!(store.take_changes(), None);
+
+        // 名字没有变化时不需要写入
+        store.load(&player);
+        assert_eq!(store.take_changes(), None);
+    }
+}
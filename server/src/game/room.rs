@@ -2,35 +2,340 @@ use crate::game_server::{RenetGameServer, RenetServerWithConfig};
 use bincode::config::Configuration;
 use log::{error, info};
 use renet2::{ClientId, RenetServer};
-use shared::cards::{Card, Deck};
+use shared::cards::{Deck, deal};
+use shared::chat::{ChatContent, ChatMessage, WordFilter};
 use shared::error::RoomServiceError;
-use shared::event::GameEvent;
-use shared::the_hidden_card::state::{GameState, Stage};
+use shared::event::{ClientCommand, GameEvent, ServerEvent, TurnDeadline};
+use shared::lobby::{LobbyEvent, RoomSettings, RoomSummary, normalize_room_code, random_room_code};
+use shared::replay::{REPLAY_FILE_EXTENSION, Replay};
+use shared::the_hidden_card::prelude::{AbandonPolicy, BotDifficulty, GameResult, RuleSet};
+use shared::the_hidden_card::state::{GameState, PendingTurn, Stage};
 use shared::{Player, Reducer};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex, RwLock};
-use tiny_bail::prelude::r;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+use tiny_bail::prelude::{c, r};
+
+use crate::game::bot::Bot;
+use crate::game::chat::ChatLimiter;
+use crate::game::profile::ProfileStore;
 
 type RoomId = u64;
 
+/// 机器人每次行动前的思考时间，让真人玩家看得清出牌过程
+const BOT_THINK_TIME: Duration = Duration::from_millis(800);
+
+/// 房间配置，由环境变量读取
+#[derive(Debug, Clone)]
+pub struct RoomConfig {
+    /// 有真人玩家等待超过该时间后，用机器人补满空座位，`None` 表示不自动补位
+    pub bot_fill_timeout: Option<Duration>,
+    /// 固定的发牌种子，用于重现玩家反馈的牌局，`None` 表示每局随机
+    pub deal_seed: Option<u64>,
+    /// 回放文件保存目录，`None` 表示不保存回放
+    pub replay_dir: Option<PathBuf>,
+    /// 房间设置中没有指定规则时使用的默认规则
+    pub rules: RuleSet,
+    /// 快速匹配排在最前的玩家等待超过该时间后，用机器人凑满一桌，`None` 表示一直等到凑齐四人
+    pub match_timeout: Option<Duration>,
+    /// 房间里的玩家全部断线超过该时间后关闭房间，玩家全部离开时立即关闭
+    pub room_close_grace: Duration,
+    /// 聊天的屏蔽词
+    pub chat_filter: WordFilter,
+    /// 座位坐满后等待准备的时间，超时自动准备，`None` 表示一直等待
+    pub ready_timeout: Option<Duration>,
+    /// 叫牌的时间，超时自动叫牌
+    pub call_timeout: Option<Duration>,
+    /// 出牌的时间，超时自动不要或出最小的单张
+    pub play_timeout: Option<Duration>,
+    /// 玩家在牌局中断线超过该时间后自动托管，`None` 表示不自动托管
+    pub auto_play_grace: Option<Duration>,
+    /// 玩家在牌局中离开后等待回来的时间，超时后按规则中的 [`AbandonPolicy`] 处理
+    pub abandon_timeout: Duration,
+    /// 玩家资料，所有房间共用
+    pub profiles: Arc<Mutex<ProfileStore>>,
+    /// 可以重置房间和服务器的管理员
+    pub admins: HashSet<ClientId>,
+}
+
+impl RoomConfig {
+    /// * `BOT_FILL_TIMEOUT_SECS` - 机器人自动补位的等待秒数，为 0 时关闭自动补位
+    /// * `DEAL_SEED` - 固定的发牌种子，不设置时每局随机
+    /// * `REPLAY_DIR` - 回放文件保存目录，不设置或为空时不保存回放
+    /// * `RULES_FILE` - JSON 格式的规则文件，只需要写和默认规则不同的字段，不设置时使用默认规则
+    /// * `MATCH_TIMEOUT_SECS` - 快速匹配用机器人凑桌前的等待秒数，为 0 时不用机器人
+    /// * `ROOM_CLOSE_GRACE_SECS` - 玩家全部断线后保留房间的秒数，默认 60
+    /// * `CHAT_FILTER_FILE` - 聊天屏蔽词文件，每行一个词，不设置时不过滤
+    /// * `READY_TIMEOUT_SECS` `CALL_TIMEOUT_SECS` `PLAY_TIMEOUT_SECS` - 准备、叫牌、出牌的秒数，
+    ///   默认 30、20、20，为 0 时不限时
+    /// * `AUTO_PLAY_GRACE_SECS` - 牌局中断线多少秒后自动托管，默认 10，为 0 时不自动托管
+    /// * `ABANDON_TIMEOUT_SECS` - 牌局中离开的玩家多少秒内可以回到原来的座位，默认 30
+    /// * `PROFILE_FILE` - 玩家资料的 JSON 文件，不设置或为空时只保存在内存中
+    /// * `ADMIN_IDS` - 逗号分隔的管理员 `ClientId`，只有管理员可以重置房间和服务器，不设置时没有管理员
+    pub fn from_env() -> Self {
+        let bot_fill_timeout = env::var("BOT_FILL_TIMEOUT_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs);
+        let match_timeout = timeout_from_env("MATCH_TIMEOUT_SECS", 30);
+        let ready_timeout = timeout_from_env("READY_TIMEOUT_SECS", 30);
+        let call_timeout = timeout_from_env("CALL_TIMEOUT_SECS", 20);
+        let play_timeout = timeout_from_env("PLAY_TIMEOUT_SECS", 20);
+        let auto_play_grace = timeout_from_env("AUTO_PLAY_GRACE_SECS", 10);
+        let abandon_timeout = env::var("ABANDON_TIMEOUT_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .map_or(Duration::from_secs(30), Duration::from_secs);
+        let room_close_grace = env::var("ROOM_CLOSE_GRACE_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .map_or(Duration::from_secs(60), Duration::from_secs);
+        let deal_seed = env::var("DEAL_SEED")
+            .ok()
+            .and_then(|seed| seed.parse::<u64>().ok());
+        let replay_dir = env::var("REPLAY_DIR")
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from);
+        let chat_filter = env::var("CHAT_FILTER_FILE")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map(|path| load_chat_filter(&path))
+            .unwrap_or_default();
+        let profiles = env::var("PROFILE_FILE")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map(|path| ProfileStore::open(PathBuf::from(path)))
+            .unwrap_or_default();
+        let rules = env::var("RULES_FILE")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map(|path| load_rules(&path))
+            .unwrap_or_default();
+        let admins = env::var("ADMIN_IDS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|id| id.trim().parse::<ClientId>().ok())
+            .collect();
+        Self {
+            bot_fill_timeout,
+            deal_seed,
+            replay_dir,
+            rules,
+            match_timeout,
+            room_close_grace,
+            chat_filter,
+            ready_timeout,
+            call_timeout,
+            play_timeout,
+            auto_play_grace,
+            abandon_timeout,
+            profiles: Arc::new(Mutex::new(profiles)),
+            admins,
+        }
+    }
+}
+
+/// 读取秒数，不设置时使用 `default_secs`，为 0 时返回 `None`
+fn timeout_from_env(name: &str, default_secs: u64) -> Option<Duration> {
+    let secs = env::var(name)
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .unwrap_or(default_secs);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// 读取规则文件，读取失败或规则没有通过 [`RuleSet::validate`] 时使用默认规则
+fn load_rules(path: &str) -> RuleSet {
+    let rules = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|json| serde_json::from_str::<RuleSet>(&json).map_err(|err| err.to_string()))
+        .and_then(|rules| rules.validate().map(|_| rules).map_err(|err| err.to_string()));
+    match rules {
+        Ok(rules) => {
+            info!("Loaded rules from {}", path);
+            rules
+        },
+        Err(err) => {
+            error!("Failed to load rules from {}: {}, using default rules", path, err);
+            RuleSet::default()
+        },
+    }
+}
+
+/// 读取屏蔽词文件，读取失败时不过滤
+fn load_chat_filter(path: &str) -> WordFilter {
+    match fs::read_to_string(path) {
+        Ok(text) => {
+            let filter = WordFilter::from_lines(&text);
+            info!("Loaded {} chat filter words from {}", filter.len(), path);
+            filter
+        },
+        Err(err) => {
+            error!("Failed to load chat filter from {}: {}", path, err);
+            WordFilter::default()
+        },
+    }
+}
+
+impl Default for RoomConfig {
+    fn default() -> Self {
+        Self {
+            bot_fill_timeout: Some(Duration::from_secs(60)),
+            deal_seed: None,
+            replay_dir: None,
+            rules: RuleSet::default(),
+            match_timeout: Some(Duration::from_secs(30)),
+            room_close_grace: Duration::from_secs(60),
+            chat_filter: WordFilter::default(),
+            ready_timeout: Some(Duration::from_secs(30)),
+            call_timeout: Some(Duration::from_secs(20)),
+            play_timeout: Some(Duration::from_secs(20)),
+            auto_play_grace: Some(Duration::from_secs(10)),
+            abandon_timeout: Duration::from_secs(30),
+            profiles: Arc::default(),
+            admins: HashSet::new(),
+        }
+    }
+}
+
+/// 当前操作的截止时间
+struct Deadline {
+    turn: PendingTurn,
+    at: Instant,
+    total: Duration,
+}
+
 pub struct Room {
     id: RoomId,
+    /// 用于分享和加入的房间号
+    code: String,
     creator_id: ClientId,
+    /// 创建时的设置，`rules` 已经替换为实际使用的规则
+    settings: RoomSettings,
     game_state: GameState,
-    deck: Deck,
+    // 当前牌局的发牌种子，用于重现牌局
+    seed: Option<u64>,
     players: HashSet<ClientId>,
+    /// 断线玩家的断线时间，重新连接或离开房间时移除
+    offline_since: HashMap<ClientId, Instant>,
+    /// 牌局中离开但还占着座位的玩家和离开的时间，这一手牌结算后让出座位
+    abandoned: HashMap<ClientId, Instant>,
+    /// 观战者，不占座位，不影响房间是否无人
+    spectators: HashSet<ClientId>,
+    /// 观战者看到的状态，比 `game_state` 晚 `settings.spectator_delay_secs`
+    spectator_state: GameState,
+    /// 还没有发给观战者的事件和它发生的时间
+    spectator_feed: VecDeque<(Instant, GameEvent)>,
+
+    config: RoomConfig,
+    bots: Vec<Bot>,
+    // 第一个真人玩家入座后开始计时，座位坐满后清空
+    waiting_since: Option<Instant>,
+    bot_action_at: Option<Instant>,
+    deadline: Option<Deadline>,
+    /// 上次刷新截止时间后有玩家出牌或不要，同一个座位再次轮到时也要重新计时
+    turn_taken: bool,
 }
 
 impl Room {
+    pub fn new(
+        id: RoomId,
+        code: String,
+        creator_id: ClientId,
+        mut settings: RoomSettings,
+        config: RoomConfig,
+    ) -> Self {
+        settings.rules.get_or_insert_with(|| config.rules.clone());
+        let game_state = Self::new_game_state(&settings);
+        let spectator_state = game_state.clone();
+        Self {
+            id,
+            code,
+            creator_id,
+            settings,
+            game_state,
+            seed: None,
+            players: HashSet::new(),
+            offline_since: HashMap::new(),
+            abandoned: HashMap::new(),
+            spectators: HashSet::new(),
+            spectator_state,
+            spectator_feed: VecDeque::new(),
+            config,
+            bots: Vec::new(),
+            waiting_since: None,
+            bot_action_at: None,
+            deadline: None,
+            turn_taken: false,
+        }
+    }
+
+    fn new_game_state(settings: &RoomSettings) -> GameState {
+        let mut state = GameState::with_rules(settings.rules.clone().unwrap_or_default());
+        state.base = settings.base_stake;
+        state
+    }
+
+    /// 房间列表中显示的信息，私密房间不显示
+    pub fn summary(&self) -> Option<RoomSummary> {
+        if self.settings.private {
+            return None;
+        }
+        Some(RoomSummary {
+            room_id: self.id,
+            code: self.code.clone(),
+            players: self
+                .game_state
+                .get_seats()
+                .iter()
+                .filter_map(|seat| seat.get_player())
+                .map(|player| player.name.clone())
+                .collect(),
+            has_password: self.settings.password.is_some(),
+            base_stake: self.settings.base_stake,
+            rules: self.game_state.rules.clone(),
+            in_game: self.in_hand(),
+            spectators: self.spectators.len(),
+        })
+    }
+
+    /// 处理客户端发来的指令，执行的座位由 `client_id` 确定，校验失败时把原因发回给该客户端
+    pub fn process_command(
+        &mut self,
+        client_id: ClientId,
+        command: ClientCommand,
+        server: &mut RenetServerWithConfig,
+    ) {
+        let event = command
+            .to_game_event(client_id, &self.game_state)
+            .and_then(|event| self.game_state.validate(&event).map(|_| event));
+        match event {
+            Ok(event) => self.process_event(event, server),
+            Err(err) => {
+                info!("Rejected command from client {}: {:?}, {}", client_id, command, err);
+                server.send_event(client_id, ServerEvent::ActionRejected(err));
+            },
+        }
+    }
+
     pub fn process_event(&mut self, event: GameEvent, server: &mut RenetServerWithConfig) {
-        if !self.game_state.validate(&event) {
-            info!("Invalid event: {:?}", event);
+        if let Err(err) = self.game_state.validate(&event) {
+            info!("Invalid event: {:?}, {}", event, err);
             return;
         }
+        self.game_state.add_history(event.clone());
         self.game_state.reduce(&event);
         for client_id in self.players.iter() {
-            // TODO 发牌事件处理，不是玩家自己的手牌，考虑隐藏
-            server.send_event_next(client_id.clone(), event.clone());
+            // 其他玩家的手牌只发送数量
+            server.send_event_next(*client_id, event.view_for(*client_id));
+        }
+        self.spectator_feed.push_back((Instant::now(), event.clone()));
+        if matches!(event, GameEvent::PlayCards(_, _) | GameEvent::Pass(_)) {
+            self.turn_taken = true;
         }
 
         // 对事件做额外的处理
@@ -41,42 +346,14 @@ impl Room {
                     self.process_event(event, server);
 
                     // 发牌
-                    // TODO 性能优化
-                    self.deck.shuffle();
-                    let mut deck = VecDeque::from(self.deck.get().clone());
-                    let mut hands: HashMap<ClientId, Vec<Card>> = HashMap::new();
-
-                    let seats: Vec<ClientId> = self
-                        .game_state
-                        .get_seats()
-                        .iter()
-                        .map(|seat| seat.player.clone().unwrap().id)
-                        .collect();
-
-                    for &client_id in &seats {
-                        hands.insert(client_id, Vec::with_capacity(13));
-                    }
-
-                    for i in 0..52 {
-                        if let Some(card) = deck.pop_front() {
-                            let seat_index = i % seats.len();
-                            let client_id = &seats[seat_index];
-
-                            if let Some(hand) = hands.get_mut(client_id) {
-                                hand.push(card);
-                            }
-                        } else {
-                            break;
-                        }
-                    }
+                    let seed = self.config.deal_seed.unwrap_or_else(Deck::random_seed);
+                    info!("Room {} dealing with seed {}", self.id, seed);
+                    self.seed = Some(seed);
 
-                    // 发送发牌事件给所有玩家
-                    // TODO 改为以 seat_index 区分用户，因为手牌是挂在 PlayerSeat上面的
-                    for (client_id, hand) in hands.iter_mut() {
-                        let event = GameEvent::DealCards {
-                            client_id: client_id.clone(),
-                            cards: hand.clone(),
-                        };
+                    let hands = deal(seed, 0);
+                    for (seat_index, cards) in hands.into_iter().enumerate() {
+                        let client_id = r!(self.game_state.get_seats()[seat_index].get_player()).id;
+                        let event = GameEvent::DealCards { client_id, cards };
                         self.process_event(event, server);
                     }
                 }
@@ -88,6 +365,10 @@ impl Room {
                     self.process_event(event, server);
                 }
             }
+            GameEvent::GameEnd(result) => {
+                self.save_replay();
+                self.record_profiles(&result);
+            },
             GameEvent::Pass(_) | GameEvent::PlayCards(_, _) => {
                 if let Some(stage) = self.game_state.game_end_check() {
                     if let Stage::Ended(result) = stage {
@@ -104,16 +385,58 @@ impl Room {
         }
     }
 
-    pub fn flush_history(&mut self, client_id: ClientId, server: &mut RenetServerWithConfig) {
-        // let history = self.game_state.get_history();
-        // history.iter().for_each(|event| {
-        //     server.send_event_next(client_id.clone(), event.clone());
-        // });
+    /// 把刚结算的这一手牌的得分和统计写入座位上真人玩家的资料，托管的玩家也要写入
+    fn record_profiles(&self, result: &GameResult) {
+        let players = self
+            .game_state
+            .get_seats()
+            .iter()
+            .enumerate()
+            .filter_map(|(seat_index, seat)| Some((seat_index, seat.get_player()?.clone())))
+            .filter(|(_, player)| !self.bots.iter().any(|bot| !bot.is_managed() && bot.id() == player.id))
+            .collect::<Vec<_>>();
+        self.config
+            .profiles
+            .lock()
+            .unwrap()
+            .record_hand(&players, &self.game_state, result);
+    }
+
+    /// 保存刚结束的这一手牌的回放文件
+    fn save_replay(&self) {
+        let Some(dir) = &self.config.replay_dir else {
+            return;
+        };
+        let bytes = match Replay::new(&self.game_state, self.seed).encode() {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error!("Room {} failed to encode replay: {}", self.id, err);
+                return;
+            },
+        };
 
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+        let path = dir.join(format!("room{}-{}.{}", self.id, millis, REPLAY_FILE_EXTENSION));
+        match fs::create_dir_all(dir).and_then(|_| fs::write(&path, bytes)) {
+            Ok(_) => info!("Room {} replay saved to {}", self.id, path.display()),
+            Err(err) => error!("Room {} failed to write replay {}: {}", self.id, path.display(), err),
+        }
+    }
+
+    /// 将这一手牌的事件记录发送给客户端，记录以状态快照开头，客户端依次应用后即可追上当前状态
+    pub fn flush_history(&mut self, client_id: ClientId, server: &mut RenetServerWithConfig) {
+        let history = self.game_state.get_history();
+        history.iter().for_each(|event| {
+            server.send_event_next(client_id, event.view_for(client_id));
+        });
     }
 
     pub fn sync_state(&mut self, client_id: ClientId, server: &mut RenetServerWithConfig) {
-        server.send_event_next(client_id.clone(), GameEvent::SyncState(self.game_state.clone()));
+        let event = GameEvent::SyncState(self.game_state.clone());
+        server.send_event_next(client_id, event.view_for(client_id));
     }
 
     pub fn join(
@@ -121,6 +444,14 @@ impl Room {
         player: Player,
         server: &mut RenetServerWithConfig,
     ) -> Result<(), RoomServiceError> {
+        // 牌局中离开的玩家在结算前回来，回到原来的座位并收回控制
+        if self.abandoned.remove(&player.id).is_some() {
+            info!("Room {} player {} came back", self.id, player.id);
+            self.add_client(player.id);
+            self.rejoin(player.clone(), server)?;
+            self.process_event(GameEvent::PlayerConnected(player.id), server);
+            return Ok(());
+        }
         if !self.game_state.has_empty_seat() {
             return Err(RoomServiceError::RoomFull);
         }
@@ -130,11 +461,12 @@ impl Room {
         self.add_client(player.id);
 
         // 发送加入房间成功事件
-        server.send_event(player.id, GameEvent::JoinRoomOk { room_id: self.id });
+        server.send_event(player.id, ServerEvent::JoinRoomOk { room_id: self.id, code: self.code.clone() });
         // 加入房间成功，下一帧将历史事件发送给客户端
         // self.flush_hisotry(player.id.clone(), server);
 
         self.sync_state(player.id.clone(), server);
+        self.send_deadline(player.id, server);
 
 
         // 下面的所有事件会同步给每一个客户端，每个客户端发送的事件不会直接应用到本地状态，
@@ -146,10 +478,267 @@ impl Room {
             },
             server,
         );
+        let coins = self.config.profiles.lock().unwrap().load(&player).coins;
+        self.process_event(GameEvent::SetCoins { seat_index, coins }, server);
+
+        if self.game_state.has_empty_seat() {
+            self.waiting_since.get_or_insert_with(Instant::now);
+        } else {
+            self.waiting_since = None;
+        }
 
         Ok(())
     }
 
+    /// 房主请求用机器人补满空座位，房主不在房间时任何玩家都可以请求
+    pub fn request_bots(
+        &mut self,
+        client_id: ClientId,
+        difficulty: BotDifficulty,
+        server: &mut RenetServerWithConfig,
+    ) -> Result<(), RoomServiceError> {
+        if !self.players.contains(&client_id) {
+            return Err(RoomServiceError::ClientNotInRoom);
+        }
+        if client_id != self.creator_id && self.players.contains(&self.creator_id) {
+            return Err(RoomServiceError::ActionNotAllowed);
+        }
+        self.fill_with_bots(difficulty, server);
+        Ok(())
+    }
+
+    /// 为每个空座位安排一个机器人
+    fn fill_with_bots(&mut self, difficulty: BotDifficulty, server: &mut RenetServerWithConfig) {
+        while let Some(seat_index) = self.game_state.get_empty_seat_index() {
+            let bot = Bot::new(seat_index, difficulty);
+            let player = bot.player().clone();
+            info!("Room {} seat {} taken by bot {}", self.id, seat_index, player.id);
+            self.bots.push(bot);
+            self.process_event(GameEvent::AssignSeats { player, seat_index }, server);
+        }
+        self.waiting_since = None;
+    }
+
+    /// 每帧调用，处理等待超时和机器人的动作
+    pub fn update(&mut self, now: Instant, server: &mut RenetServerWithConfig) {
+        if let (Some(since), Some(timeout)) = (self.waiting_since, self.config.bot_fill_timeout)
+            && now.duration_since(since) >= timeout
+        {
+            info!("Room {} waited {:?}, filling empty seats with bots", self.id, timeout);
+            self.fill_with_bots(BotDifficulty::default(), server);
+        }
+
+        self.run_abandoned(now, server);
+        self.run_auto_play(now, server);
+        self.run_bots(now, server);
+        self.run_deadline(now, server);
+        self.release_spectator_feed(now, server);
+    }
+
+    fn turn_timeout(&self, turn: PendingTurn) -> Option<Duration> {
+        match turn {
+            PendingTurn::Ready => self.config.ready_timeout,
+            PendingTurn::CallCard(_) => self.config.call_timeout,
+            PendingTurn::PlayCards(_) => self.config.play_timeout,
+        }
+    }
+
+    /// 等待的操作变化时重新计时并通知客户端，超时后代替玩家执行
+    fn run_deadline(&mut self, now: Instant, server: &mut RenetServerWithConfig) {
+        let pending = self.game_state.pending_turn();
+        let current = self.deadline.as_ref().map(|deadline| deadline.turn);
+        if pending != current || self.turn_taken {
+            self.turn_taken = false;
+            self.deadline = pending.and_then(|turn| {
+                let total = self.turn_timeout(turn)?;
+                Some(Deadline { turn, at: now + total, total })
+            });
+            if self.deadline.is_some() || current.is_some() {
+                // 有延迟的观战者看到的牌局和截止时间对不上，不发给他们
+                let spectators = self.spectators.iter().filter(|_| self.settings.spectator_delay_secs == 0);
+                let clients = self.players.iter().chain(spectators).copied().collect::<Vec<_>>();
+                clients.into_iter().for_each(|client_id| self.send_deadline(client_id, server));
+            }
+        }
+
+        let Some(deadline) = &self.deadline else {
+            return;
+        };
+        if now < deadline.at {
+            return;
+        }
+        let turn = deadline.turn;
+        self.deadline = None;
+        for event in self.game_state.timeout_events(turn) {
+            info!("Room {} {:?} timed out, auto {:?}", self.id, turn, event);
+            self.process_event(event, server);
+        }
+    }
+
+    /// 把当前的截止时间发给客户端，加入或重新加入房间时也要发送
+    fn send_deadline(&self, client_id: ClientId, server: &mut RenetServerWithConfig) {
+        let deadline = self.deadline.as_ref().map(|deadline| {
+            let remaining = deadline.at.saturating_duration_since(Instant::now());
+            let deadline_ms = (SystemTime::now() + remaining)
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default();
+            TurnDeadline {
+                turn: deadline.turn,
+                deadline_ms,
+                remaining_ms: remaining.as_millis() as u64,
+                total_ms: deadline.total.as_millis() as u64,
+            }
+        });
+        server.send_event_next(client_id, ServerEvent::TurnDeadline(deadline));
+    }
+
+    /// 把已经过了观战延迟的事件应用到观战状态并发给观战者
+    fn release_spectator_feed(&mut self, now: Instant, server: &mut RenetServerWithConfig) {
+        let delay = Duration::from_secs(self.settings.spectator_delay_secs);
+        while let Some((at, _)) = self.spectator_feed.front() {
+            if now.duration_since(*at) < delay {
+                break;
+            }
+            let (_, event) = self.spectator_feed.pop_front().unwrap();
+            self.spectator_state.reduce(&event);
+            for client_id in self.spectators.iter() {
+                // 观战者不在座位上，所有手牌都只发送数量
+                server.send_event_next(*client_id, event.view_for(*client_id));
+            }
+        }
+    }
+
+    /// 开始观战，先发送观战状态的快照，之后的事件随观战延迟发送
+    pub fn spectate(&mut self, client_id: ClientId, server: &mut RenetServerWithConfig) {
+        self.spectators.insert(client_id);
+        server.send_event(client_id, ServerEvent::SpectateOk { room_id: self.id, code: self.code.clone() });
+        let event = GameEvent::SyncState(self.spectator_state.clone());
+        server.send_event_next(client_id, event.view_for(client_id));
+        if self.settings.spectator_delay_secs == 0 {
+            self.send_deadline(client_id, server);
+        }
+    }
+
+    pub fn stop_spectating(&mut self, client_id: ClientId) {
+        self.spectators.remove(&client_id);
+    }
+
+    /// 转发聊天消息，玩家的消息发给所有人，观战者的消息只发给观战者
+    pub fn chat(&self, message: ChatMessage, server: &mut RenetServerWithConfig) {
+        let players = self.players.iter().filter(|_| !message.spectator);
+        for client_id in players.chain(self.spectators.iter()) {
+            server.send_event(*client_id, ServerEvent::Chat(message.clone()));
+        }
+    }
+
+    /// 牌局中离开的玩家超时没有回来时按 [`AbandonPolicy`] 处理：机器人代打或者直接结算，
+    /// 结算后的下一帧让出座位，客户端先按结算时的座位显示结果
+    fn run_abandoned(&mut self, now: Instant, server: &mut RenetServerWithConfig) {
+        if self.abandoned.is_empty() {
+            return;
+        }
+        if matches!(self.game_state.stage, Stage::PreGame | Stage::Ended(_)) {
+            let left = self.abandoned.drain().map(|(client_id, _)| client_id).collect::<Vec<_>>();
+            for client_id in left {
+                self.process_event(GameEvent::PlayerLeave(client_id), server);
+            }
+            return;
+        }
+
+        let timeout = self.config.abandon_timeout;
+        let expired = self
+            .abandoned
+            .iter()
+            .filter(|(_, since)| now.duration_since(**since) >= timeout)
+            .map(|(client_id, _)| *client_id)
+            .collect::<Vec<_>>();
+        for client_id in expired {
+            let seat_index = c!(self.game_state.get_player_seat_index_by_id(client_id));
+            let result = match self.game_state.rules.abandon.policy {
+                AbandonPolicy::EndHand => self.game_state.abandon_result(client_id),
+                AbandonPolicy::BotTakeover => None,
+            };
+            if let Some(result) = result {
+                info!("Room {} player {} abandoned the hand, ending it", self.id, client_id);
+                self.process_event(GameEvent::GameEnd(result), server);
+                return;
+            }
+            // 代打到结算，直接结算时代打到出牌阶段模式确定之后
+            if !self.game_state.get_seats()[seat_index].auto_play {
+                info!("Room {} player {} abandoned the hand, bot takes over", self.id, client_id);
+                self.process_event(GameEvent::AutoPlay { seat_index, enabled: true }, server);
+            }
+        }
+    }
+
+    /// 一手牌已经发牌还没有结算
+    fn in_hand(&self) -> bool {
+        matches!(self.game_state.stage, Stage::DealCards | Stage::CallCard(_) | Stage::PlayCards)
+    }
+
+    /// 牌局中断线超过 `auto_play_grace` 的玩家自动托管，托管的座位由机器人代打
+    fn run_auto_play(&mut self, now: Instant, server: &mut RenetServerWithConfig) {
+        if let (true, Some(grace)) = (self.in_hand(), self.config.auto_play_grace) {
+            let seats = self
+                .offline_since
+                .iter()
+                .filter(|(_, since)| now.duration_since(**since) >= grace)
+                .filter_map(|(client_id, _)| self.game_state.get_player_seat_index_by_id(*client_id))
+                .filter(|seat_index| !self.game_state.get_seats()[*seat_index].auto_play)
+                .collect::<Vec<_>>();
+            for seat_index in seats {
+                info!("Room {} seat {} disconnected for {:?}, auto play", self.id, seat_index, grace);
+                self.process_event(GameEvent::AutoPlay { seat_index, enabled: true }, server);
+            }
+        }
+
+        // 代打的机器人和托管的座位保持一致
+        let seats = self.game_state.get_seats();
+        let is_auto_play = |client_id: ClientId| {
+            seats
+                .iter()
+                .any(|seat| seat.auto_play && seat.get_player().is_some_and(|player| player.id == client_id))
+        };
+        self.bots.retain(|bot| !bot.is_managed() || is_auto_play(bot.id()));
+        let players = seats
+            .iter()
+            .filter(|seat| seat.auto_play)
+            .filter_map(|seat| seat.get_player())
+            .filter(|player| !self.bots.iter().any(|bot| bot.id() == player.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        for player in players {
+            self.bots.push(Bot::managed(player, BotDifficulty::default()));
+        }
+    }
+
+    /// 机器人每次只执行一个动作，动作之间间隔 [`BOT_THINK_TIME`]
+    fn run_bots(&mut self, now: Instant, server: &mut RenetServerWithConfig) {
+        if !self.bots.iter().any(|bot| bot.is_pending(&self.game_state)) {
+            self.bot_action_at = None;
+            return;
+        }
+
+        match self.bot_action_at {
+            None => self.bot_action_at = Some(now + BOT_THINK_TIME),
+            Some(at) if now >= at => {
+                self.bot_action_at = None;
+                // 不包牌的决定不产生事件，接着询问下一个机器人
+                for index in 0..self.bots.len() {
+                    if !self.bots[index].is_pending(&self.game_state) {
+                        continue;
+                    }
+                    if let Some(event) = self.bots[index].decide(&self.game_state) {
+                        self.process_event(event, server);
+                        break;
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
     pub fn rejoin(
         &mut self,
         player: Player,
@@ -160,14 +749,67 @@ impl Room {
         }
 
         // 当前帧发送重新加入房间成功事件
-        server.send_event(player.id, GameEvent::ReJoinRoomOk { room_id: self.id });
+        server.send_event(player.id, ServerEvent::ReJoinRoomOk { room_id: self.id, code: self.code.clone() });
         // 加入房间成功，下一帧将历史事件发送给客户端
-        // self.flush_hisotry(player.id.clone(), server);
-        self.sync_state(player.id.clone(), server);
+        self.flush_history(player.id, server);
+        self.send_deadline(player.id, server);
 
         Ok(())
     }
 
+    /// 玩家离开房间，房主离开时按座位顺序交给下一位玩家
+    pub fn leave(&mut self, client_id: ClientId, server: &mut RenetServerWithConfig) {
+        self.process_event(GameEvent::PlayerLeave(client_id), server);
+        self.remove_client(client_id);
+        self.offline_since.remove(&client_id);
+        // 牌局进行中座位会保留到结算
+        if self.game_state.get_player_seat_index_by_id(client_id).is_some() {
+            info!("Room {} player {} left during the hand", self.id, client_id);
+            self.abandoned.insert(client_id, Instant::now());
+        }
+
+        if client_id != self.creator_id {
+            return;
+        }
+        let next_creator = self
+            .game_state
+            .get_seats()
+            .iter()
+            .filter_map(|seat| seat.get_player())
+            .map(|player| player.id)
+            .find(|id| self.players.contains(id));
+        if let Some(next_creator) = next_creator {
+            info!("Room {} creator {} left, handed to {}", self.id, client_id, next_creator);
+            self.creator_id = next_creator;
+        }
+    }
+
+    /// 记录玩家的连接状态变化，用于判断房间是否无人
+    fn set_online(&mut self, client_id: ClientId, online: bool, now: Instant) {
+        if !self.players.contains(&client_id) {
+            return;
+        }
+        if online {
+            self.offline_since.remove(&client_id);
+        } else {
+            self.offline_since.entry(client_id).or_insert(now);
+        }
+    }
+
+    /// 房间里没有玩家，或者所有玩家断线都超过了 `grace`
+    ///
+    /// 有玩家在牌局中离开，或者有托管的座位在代打时，这一手牌还会结算，要等结算后再关闭，
+    /// 否则离开的玩家不会被扣分，结果也不会写入资料
+    pub fn is_abandoned(&self, now: Instant, grace: Duration) -> bool {
+        let settling = !self.abandoned.is_empty()
+            || (self.in_hand() && self.game_state.get_seats().iter().any(|seat| seat.auto_play));
+        !settling && self.players.iter().all(|client_id| {
+            self.offline_since
+                .get(client_id)
+                .is_some_and(|since| now.duration_since(*since) >= grace)
+        })
+    }
+
     pub fn add_client(&mut self, client_id: ClientId) {
         self.players.insert(client_id);
     }
@@ -177,85 +819,250 @@ impl Room {
     }
 }
 
+/// 公开房间列表，游戏循环每帧刷新，HTTP 服务器的 `/rooms` 从这里读取
+pub type RoomList = Arc<RwLock<Vec<RoomSummary>>>;
+
 pub struct Rooms {
     rooms: HashMap<RoomId, Arc<RwLock<Room>>>,
 
     client_room_map: HashMap<ClientId, RoomId>,
+    /// 观战者所在的房间，观战者不在 `client_room_map` 中
+    spectator_room_map: HashMap<ClientId, RoomId>,
+    /// 房间号到 [`RoomId`] 的映射
+    codes: HashMap<String, RoomId>,
 
     next_room_id: RoomId,
-    // TODO 销毁房间, 房间所有用户断开连接后立即销毁房间或60秒后销毁房间或立即销毁
+    config: RoomConfig,
+    lobby: RoomList,
+    /// 请求过房间列表、还没有进入房间的客户端，房间列表变化时推送 [`LobbyEvent`]
+    lobby_clients: HashSet<ClientId>,
+    chat_limiters: HashMap<ClientId, ChatLimiter>,
 }
 
 impl Rooms {
-    pub fn with_test_room() -> Self {
-        let mut rooms = Self {
+    pub fn new(config: RoomConfig, lobby: RoomList) -> Self {
+        Self {
             rooms: HashMap::new(),
             client_room_map: HashMap::new(),
+            spectator_room_map: HashMap::new(),
+            codes: HashMap::new(),
             next_room_id: 0,
-        };
-        let room_id = rooms.next_room_id;
-        let room = Arc::new(RwLock::new(Room {
-            id: room_id,
-            game_state: GameState::default(),
-            players: HashSet::new(),
-            creator_id: 0,
-            deck: Deck::new(),
-        }));
+            config,
+            lobby,
+            lobby_clients: HashSet::new(),
+            chat_limiters: HashMap::new(),
+        }
+    }
 
-        rooms.next_room_id += 1;
+    pub fn create_room(
+        &mut self,
+        player: Player,
+        settings: RoomSettings,
+        server: &mut RenetServerWithConfig,
+    ) -> Result<(), RoomServiceError> {
+        if self.client_room_map.contains_key(&player.id) {
+            return Err(RoomServiceError::AlreadyInRoom);
+        }
+        let password = settings.password.clone();
+        let code = self.new_room(player.id, settings)?;
 
-        rooms.rooms.insert(room_id, room);
+        self.join_room(player, &code, password.as_deref(), server)?;
 
-        rooms
+        Ok(())
     }
-    pub fn create_room(
+
+    /// 快速匹配凑到的玩家坐进一个新的私密房间，人数不足四人时用机器人补满
+    pub fn create_match_room(
         &mut self,
-        player: Player,
+        players: Vec<Player>,
+        base_stake: i32,
         server: &mut RenetServerWithConfig,
     ) -> Result<(), RoomServiceError> {
+        let creator_id = players.first().ok_or(RoomServiceError::InvalidSettings)?.id;
+        let settings = RoomSettings {
+            private: true,
+            base_stake,
+            ..Default::default()
+        };
+        let code = self.new_room(creator_id, settings)?;
+        // 加入失败的玩家跳过，由调用方通知，其他玩家照常开桌；没有人入座的空房间下一帧关闭
+        let mut joined = false;
+        let mut last_error = None;
+        for player in players {
+            let player_id = player.id;
+            match self.join_room(player, &code, None, server) {
+                Ok(()) => joined = true,
+                Err(err) => {
+                    error!("Client {} failed to join quick match room {}: {}", player_id, code, err);
+                    last_error = Some(err);
+                },
+            }
+        }
+        if !joined {
+            return Err(last_error.unwrap_or(RoomServiceError::RoomNotFound));
+        }
+
+        let room_id = self.codes[&code];
+        let room = self.rooms.get(&room_id).ok_or(RoomServiceError::RoomNotFound)?;
+        room.write().unwrap().fill_with_bots(BotDifficulty::default(), server);
+        Ok(())
+    }
+
+    /// 新建一个空房间，返回房间号
+    fn new_room(&mut self, creator_id: ClientId, settings: RoomSettings) -> Result<String, RoomServiceError> {
+        settings.validate()?;
+
         let room_id = self.next_room_id;
         self.next_room_id += 1;
 
-        let room = Arc::new(RwLock::new(Room {
-            id: room_id,
-            game_state: GameState::default(),
-            creator_id: player.id,
-            players: HashSet::new(),
-            deck: Deck::new(),
-        }));
+        let mut code = random_room_code();
+        while self.codes.contains_key(&code) {
+            code = random_room_code();
+        }
+        info!("Room {} created with code {} by {}", room_id, code, creator_id);
 
-        self.rooms.insert(room_id, room);
+        let room = Room::new(room_id, code.clone(), creator_id, settings, self.config.clone());
+        self.rooms.insert(room_id, Arc::new(RwLock::new(room)));
+        self.codes.insert(code.clone(), room_id);
+        Ok(code)
+    }
 
-        self.join_room(player, room_id, server)?;
+    /// 玩家所在的房间
+    pub fn room_of(&self, client_id: ClientId) -> Option<RoomId> {
+        self.client_room_map.get(&client_id).copied()
+    }
 
-        Ok(())
+    /// 房间当前的牌局状态，测试中检查入座情况
+    #[cfg(test)]
+    pub fn game_state(&self, room_id: RoomId) -> Option<GameState> {
+        let room = self.rooms.get(&room_id)?;
+        Some(room.read().unwrap().game_state.clone())
     }
 
+    pub fn config(&self) -> &RoomConfig {
+        &self.config
+    }
+
+    /// 按房间号加入房间
     pub fn join_room(
         &mut self,
         player: Player,
-        room_id: RoomId,
+        code: &str,
+        password: Option<&str>,
         server: &mut RenetServerWithConfig,
     ) -> Result<(), RoomServiceError> {
         if self.client_room_map.contains_key(&player.id) {
             return Err(RoomServiceError::AlreadyInRoom);
         }
 
+        let room_id = *self
+            .codes
+            .get(&normalize_room_code(code))
+            .ok_or(RoomServiceError::RoomNotFound)?;
         let room = self
             .rooms
             .get(&room_id)
             .ok_or(RoomServiceError::RoomNotFound)?;
 
         let mut room = room.write().unwrap();
+        room.settings.check_password(password)?;
 
         let result = room.join(player.clone(), server);
 
         if let Ok(()) = result {
+            drop(room);
+            // 等待的观战者坐下后不再观战
+            self.stop_spectating(player.id);
             self.client_room_map.insert(player.id.clone(), room_id);
+            self.lobby_clients.remove(&player.id);
         }
         result
     }
 
+    /// 按房间号观战，已经在观战其他房间时先离开原来的房间
+    pub fn spectate_room(
+        &mut self,
+        client_id: ClientId,
+        code: &str,
+        password: Option<&str>,
+        server: &mut RenetServerWithConfig,
+    ) -> Result<(), RoomServiceError> {
+        if self.client_room_map.contains_key(&client_id) {
+            return Err(RoomServiceError::AlreadyInRoom);
+        }
+
+        let room_id = *self
+            .codes
+            .get(&normalize_room_code(code))
+            .ok_or(RoomServiceError::RoomNotFound)?;
+        let room = self
+            .rooms
+            .get(&room_id)
+            .ok_or(RoomServiceError::RoomNotFound)?
+            .clone();
+        room.read().unwrap().settings.check_password(password)?;
+
+        self.stop_spectating(client_id);
+        room.write().unwrap().spectate(client_id, server);
+        self.spectator_room_map.insert(client_id, room_id);
+        self.lobby_clients.remove(&client_id);
+        info!("Client {} spectating room {}", client_id, room_id);
+        Ok(())
+    }
+
+    /// 把聊天消息转发到发送者所在或正在观战的房间，文字消息经过屏蔽词过滤
+    fn chat(
+        &mut self,
+        player: &Player,
+        content: ChatContent,
+        server: &mut RenetServerWithConfig,
+    ) -> Result<(), RoomServiceError> {
+        content.validate()?;
+        let (room_id, spectator) = match self.client_room_map.get(&player.id) {
+            Some(room_id) => (*room_id, false),
+            None => (
+                *self
+                    .spectator_room_map
+                    .get(&player.id)
+                    .ok_or(RoomServiceError::ClientNotInRoom)?,
+                true,
+            ),
+        };
+        let room = self.rooms.get(&room_id).ok_or(RoomServiceError::RoomNotFound)?;
+
+        let now = Instant::now();
+        let limiter = self
+            .chat_limiters
+            .entry(player.id)
+            .or_insert_with(|| ChatLimiter::new(now));
+        if !limiter.try_send(now) {
+            return Err(RoomServiceError::ChatTooFast);
+        }
+
+        let content = match content {
+            ChatContent::Text(text) => ChatContent::Text(self.config.chat_filter.apply(&text)),
+            content => content,
+        };
+        let message = ChatMessage {
+            sender: player.id,
+            name: player.name.clone(),
+            content,
+            spectator,
+        };
+        room.read().unwrap().chat(message, server);
+        Ok(())
+    }
+
+    fn stop_spectating(&mut self, client_id: ClientId) {
+        let room = self
+            .spectator_room_map
+            .remove(&client_id)
+            .and_then(|room_id| self.rooms.get(&room_id));
+        if let Some(room) = room {
+            room.write().unwrap().stop_spectating(client_id);
+        }
+    }
+
     pub fn rejoin_room(
         &mut self,
         player: Player,
@@ -277,43 +1084,118 @@ impl Rooms {
         result
     }
 
-    /// 方便开发 重置服务器
-    fn reset_room(&mut self, room_id: RoomId) -> Result<(), RoomServiceError> {
+    /// 管理员重置房间，房间里的玩家和观战者都被移出房间
+    fn reset_room(
+        &mut self,
+        room_id: RoomId,
+        server: &mut RenetServerWithConfig,
+    ) -> Result<(), RoomServiceError> {
         let room = self
             .rooms
             .get(&room_id)
             .ok_or(RoomServiceError::RoomNotFound)?;
 
         let mut room = room.write().unwrap();
+        for client_id in room.spectators.drain() {
+            server.send_event(client_id, ServerEvent::SpectateEnded);
+        }
+        room.players.clear();
+        room.offline_since.clear();
 
-        room.game_state = GameState::default();
-        self.client_room_map.clear();
+        room.game_state = Room::new_game_state(&room.settings);
+        room.spectator_state = room.game_state.clone();
+        room.spectator_feed.clear();
+        room.bots.clear();
+        room.seed = None;
+        room.waiting_since = None;
+        room.bot_action_at = None;
+        room.deadline = None;
+        room.abandoned.clear();
+        self.client_room_map.retain(|_, id| *id != room_id);
+        self.spectator_room_map.retain(|_, id| *id != room_id);
         info!("Reset room: {}", room_id);
         Ok(())
     }
 
-    /// 重置服务器
+    /// 管理员重置服务器
     fn reset_server(&mut self) {
-        *self = Self::with_test_room();
+        *self = Self::new(self.config.clone(), self.lobby.clone());
+    }
+
+    /// 每帧调用，驱动各个房间的计时和机器人，关闭无人的房间并把房间列表的变化推送给大厅中的客户端
+    pub fn update(&mut self, now: Instant, server: &mut RenetServerWithConfig) {
+        for room in self.rooms.values() {
+            room.write().unwrap().update(now, server);
+        }
+        self.close_abandoned_rooms(now, server);
+
+        let list = self.room_list();
+        let events = LobbyEvent::diff(&self.lobby.read().unwrap(), &list);
+        for event in events {
+            for client_id in self.lobby_clients.iter() {
+                server.send_event(*client_id, ServerEvent::Lobby(event.clone()));
+            }
+        }
+        *self.lobby.write().unwrap() = list;
+    }
+
+    /// 关闭无人的房间，观战者收到 [`ServerEvent::SpectateEnded`]
+    fn close_abandoned_rooms(&mut self, now: Instant, server: &mut RenetServerWithConfig) {
+        let grace = self.config.room_close_grace;
+        let closed = self
+            .rooms
+            .iter()
+            .filter(|(_, room)| room.read().unwrap().is_abandoned(now, grace))
+            .map(|(room_id, _)| *room_id)
+            .collect::<Vec<_>>();
+        for room_id in closed {
+            let Some(room) = self.rooms.remove(&room_id) else {
+                continue;
+            };
+            let code = room.read().unwrap().code.clone();
+            self.codes.remove(&code);
+            self.client_room_map.retain(|_, id| *id != room_id);
+            self.spectator_room_map.retain(|_, id| *id != room_id);
+            for client_id in room.read().unwrap().spectators.iter() {
+                server.send_event(*client_id, ServerEvent::SpectateEnded);
+            }
+            info!("Room {} ({}) closed", room_id, code);
+        }
     }
 
-    /// 尝试处理事件，如果事件是创建房间或者加入房间，则处理，否则尝试获取房间并将事件交给房间处理
-    pub fn process_event(
+    /// 公开房间列表，按创建顺序排列
+    pub fn room_list(&self) -> Vec<RoomSummary> {
+        let mut list = self
+            .rooms
+            .values()
+            .filter_map(|room| room.read().unwrap().summary())
+            .collect::<Vec<_>>();
+        list.sort_by_key(|summary| summary.room_id);
+        list
+    }
+
+    /// 处理客户端指令，创建、加入房间等由这里处理，其他指令交给玩家所在的房间
+    ///
+    /// 玩家身份一律使用连接的 `client_id`，不使用指令中的玩家 id
+    pub fn process_command(
         &mut self,
-        client_id: ClientId,
-        event: GameEvent,
+        player: &Player,
+        command: ClientCommand,
         server: &mut RenetServerWithConfig,
     ) -> Result<(), RoomServiceError> {
-        match event {
-            GameEvent::SyncState(_) | GameEvent::GameEnd(_) => {
-                // 阻止非法事件
+        let client_id = player.id;
+        match command {
+            ClientCommand::ServerReset | ClientCommand::RoomReset { .. }
+                if !self.config.admins.contains(&client_id) =>
+            {
+                info!("Client {} is not an admin, ignoring {}", client_id, command);
                 Err(RoomServiceError::ActionNotAllowed)
-            }
-            GameEvent::ServerReset => {
+            },
+            ClientCommand::ServerReset => {
                 self.reset_server();
                 Ok(())
             }
-            GameEvent::ClientJustLaunched(client_id) => {
+            ClientCommand::ClientJustLaunched => {
                 let room_id = self.client_room_map.get(&client_id);
 
                 if let Some(room_id) = room_id {
@@ -322,7 +1204,7 @@ impl Rooms {
                         .get(room_id);
                     if let Some(room) = room {
                         // 向用户发送确认重新加入房间事件
-                        server.send_event(client_id, GameEvent::AskForRejoinRoom(room_id.clone()))
+                        server.send_event(client_id, ServerEvent::AskForRejoinRoom(*room_id))
                     } else {
                         // 房间已经不存在，将玩家移除 [ClientId] - [RoomId] 映射
                         self.client_room_map.remove(&client_id);
@@ -330,38 +1212,48 @@ impl Rooms {
                 }
                 Ok(())
             }
-            GameEvent::CreateRoom { player } => self.create_room(player, server),
-            GameEvent::JoinRoom { player, room_id } => self.join_room(player, room_id, server),
-            GameEvent::RoomReset { room_id } => self.reset_room(room_id),
-            GameEvent::ReJoinRoom { player} => self.rejoin_room(player, server),
-            GameEvent::PlayerConnected(client_id) => {
-                let room_id = self.client_room_map.get(&client_id);
-                // 如果玩家已经加入房间，则将事件交给房间处理
-                if let Some(room_id) = room_id {
-                    let room = self
-                        .rooms
-                        .get(room_id);
-                    if let Some(room) = room {
-                        room.write().unwrap().process_event(event.clone(), server);
-                    } else {
-                        // 房间已经不存在，将玩家移除 [ClientId] - [RoomId] 映射
-                        self.client_room_map.remove(&client_id);
-                    }
+            ClientCommand::ListRooms => {
+                if !self.client_room_map.contains_key(&client_id) {
+                    self.lobby_clients.insert(client_id);
                 }
+                server.send_event(client_id, ServerEvent::RoomList(self.room_list()));
                 Ok(())
             },
-            GameEvent::PlayerLeave(client_id) => {
-                let room_id = self.client_room_map.get(&client_id);
-                // 如果玩家已经加入房间，则将事件交给房间处理
-                if let Some(room_id) = room_id {
-                    let room = self
-                        .rooms
-                        .get(room_id);
-                    if let Some(room) = room {
-                        room.write().unwrap().process_event(event.clone(), server);
-                    }
-                    // 房间已经不存在，将玩家移除 [ClientId] - [RoomId] 映射
-                    self.client_room_map.remove(&client_id);
+            ClientCommand::CreateRoom(settings) => self.create_room(player.clone(), settings, server),
+            ClientCommand::JoinRoom { code, password } => {
+                self.join_room(player.clone(), &code, password.as_deref(), server)
+            },
+            ClientCommand::RoomReset { room_id } => self.reset_room(room_id, server),
+            ClientCommand::FillWithBots(difficulty) => {
+                let room_id = self
+                    .client_room_map
+                    .get(&client_id)
+                    .ok_or(RoomServiceError::ClientNotInRoom)?;
+
+                let room = self
+                    .rooms
+                    .get(room_id)
+                    .ok_or(RoomServiceError::RoomNotFound)?;
+
+                room.write().unwrap().request_bots(client_id, difficulty, server)
+            },
+            ClientCommand::ReJoinRoom => self.rejoin_room(player.clone(), server),
+            ClientCommand::Spectate { code, password } => {
+                self.spectate_room(client_id, &code, password.as_deref(), server)
+            },
+            ClientCommand::Chat(content) => self.chat(player, content, server),
+            ClientCommand::LeaveRoom if self.spectator_room_map.contains_key(&client_id) => {
+                self.stop_spectating(client_id);
+                Ok(())
+            },
+            ClientCommand::LeaveRoom => {
+                // 将玩家移除 [ClientId] - [RoomId] 映射
+                let room = self
+                    .client_room_map
+                    .remove(&client_id)
+                    .and_then(|room_id| self.rooms.get(&room_id));
+                if let Some(room) = room {
+                    room.write().unwrap().leave(client_id, server);
                 }
                 Ok(())
             }
@@ -376,10 +1268,125 @@ impl Rooms {
                     .get(room_id)
                     .ok_or(RoomServiceError::RoomNotFound)?;
 
-                room.write().unwrap().process_event(event.clone(), server);
+                room.write().unwrap().process_command(client_id, command, server);
 
                 Ok(())
             },
         }
     }
+
+    /// 服务器产生的事件（连接状态变化等），交给玩家所在的房间处理
+    pub fn process_room_event(
+        &mut self,
+        client_id: ClientId,
+        event: GameEvent,
+        server: &mut RenetServerWithConfig,
+    ) {
+        if let GameEvent::PlayerDisconnected(_) = event {
+            self.lobby_clients.remove(&client_id);
+            self.chat_limiters.remove(&client_id);
+            self.stop_spectating(client_id);
+        }
+        let Some(room_id) = self.client_room_map.get(&client_id) else {
+            return;
+        };
+        if let Some(room) = self.rooms.get(room_id) {
+            let mut room = room.write().unwrap();
+            match event {
+                GameEvent::PlayerConnected(_) => room.set_online(client_id, true, Instant::now()),
+                GameEvent::PlayerDisconnected(_) => room.set_online(client_id, false, Instant::now()),
+                _ => {},
+            }
+            room.process_event(event, server);
+        } else {
+            // 房间已经不存在，将玩家移除 [ClientId] - [RoomId] 映射
+            self.client_room_map.remove(&client_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use renet2::{ConnectionConfig, DefaultChannel};
+
+    fn test_server() -> RenetServerWithConfig {
+        RenetServerWithConfig::new(RenetServer::new(ConnectionConfig {
+            available_bytes_per_tick: 60_000,
+            server_channels_config: DefaultChannel::config(),
+            client_channels_config: DefaultChannel::config(),
+        }))
+    }
+
+    #[test]
+    fn test_last_player_leaves_bot_room() {
+        let config = RoomConfig {
+            bot_fill_timeout: None,
+            ready_timeout: None,
+            call_timeout: None,
+            play_timeout: None,
+            abandon_timeout: Duration::ZERO,
+            room_close_grace: Duration::ZERO,
+            ..Default::default()
+        };
+        let mut server = test_server();
+        let mut rooms = Rooms::new(config, RoomList::default());
+        let player = Player { id: 1, name: "玩家".to_string(), avatar: None };
+        rooms.process_command(&player, ClientCommand::CreateRoom(RoomSettings::default()), &mut server).unwrap();
+        rooms.process_command(&player, ClientCommand::FillWithBots(BotDifficulty::Easy), &mut server).unwrap();
+        rooms.process_command(&player, ClientCommand::Ready, &mut server).unwrap();
+        let room_id = rooms.room_of(player.id).unwrap();
+        let stage = |rooms: &Rooms| rooms.rooms[&room_id].read().unwrap().game_state.stage.clone();
+
+        // 机器人准备后发牌，唯一的真人玩家在牌局中离开
+        let mut now = Instant::now();
+        while stage(&rooms) != Stage::DealCards {
+            now += BOT_THINK_TIME;
+            rooms.update(now, &mut server);
+        }
+        rooms.process_command(&player, ClientCommand::LeaveRoom, &mut server).unwrap();
+
+        // 结算前房间不会关闭
+        for _ in 0..100 {
+            now += BOT_THINK_TIME;
+            rooms.update(now, &mut server);
+            if !rooms.rooms.contains_key(&room_id) {
+                break;
+            }
+            assert!(!matches!(stage(&rooms), Stage::PreGame), "hand was not settled before the seat was vacated");
+        }
+        assert!(!rooms.rooms.contains_key(&room_id));
+
+        let profiles = rooms.config.profiles.clone();
+        let mut profiles = profiles.lock().unwrap();
+        let profile = profiles.load(&player);
+        assert_eq!(profile.stats.hands_played, 1);
+        assert!(profile.coins < 0);
+    }
+
+    #[test]
+    fn test_match_room_skips_failed_join() {
+        let mut server = test_server();
+        let mut rooms = Rooms::new(RoomConfig { bot_fill_timeout: None, ..Default::default() }, RoomList::default());
+        let seated = Player { id: 1, name: "玩家1".to_string(), avatar: None };
+        let queued = Player { id: 2, name: "玩家2".to_string(), avatar: None };
+        rooms.process_command(&seated, ClientCommand::CreateRoom(RoomSettings::default()), &mut server).unwrap();
+        let other_room = rooms.room_of(seated.id).unwrap();
+
+        // 已经在其他房间的玩家加入失败，其他玩家照常开桌，空位用机器人补满
+        rooms.create_match_room(vec![seated.clone(), queued.clone()], 1, &mut server).unwrap();
+        assert_eq!(rooms.room_of(seated.id), Some(other_room));
+        let room_id = rooms.room_of(queued.id).unwrap();
+        assert_ne!(room_id, other_room);
+        assert!(!rooms.rooms[&room_id].read().unwrap().game_state.has_empty_seat());
+
+        // 没有人能入座时返回错误，空房间下一帧关闭
+        let room_count = rooms.rooms.len();
+        assert_eq!(
+            rooms.create_match_room(vec![seated], 1, &mut server),
+            Err(RoomServiceError::AlreadyInRoom)
+        );
+        rooms.update(Instant::now(), &mut server);
+        assert_eq!(rooms.rooms.len(), room_count);
+    }
 }
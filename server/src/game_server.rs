@@ -6,20 +6,30 @@ use log::{error, info, trace};
 use renet2::{ClientId, ConnectionConfig, DefaultChannel, RenetServer, ServerEvent};
 use renet2_netcode::NetcodeServerTransport;
 
-use crate::game::Rooms;
+use crate::game::{Matchmaker, RoomConfig, RoomList, Rooms};
+use crate::utils::name_from_user_data;
 use shared::Player;
-use shared::event::GameEvent;
+use shared::event::{ClientCommand, GameEvent};
 
 pub struct RenetServerWithConfig {
     config: Configuration,
     server: RenetServer,
 
     // 使用 HashMap 存储每个客户端待发送的事件列表
-    event_buffer: HashMap<ClientId, Vec<GameEvent>>,
+    event_buffer: HashMap<ClientId, Vec<shared::event::ServerEvent>>,
 }
 
 impl RenetServerWithConfig {
-    pub fn send_event(&mut self, client_id: ClientId, event: GameEvent) {
+    pub fn new(server: RenetServer) -> Self {
+        Self {
+            config: bincode::config::standard(),
+            server,
+            event_buffer: HashMap::new(),
+        }
+    }
+
+    pub fn send_event(&mut self, client_id: ClientId, event: impl Into<shared::event::ServerEvent>) {
+        let event = event.into();
         if !self.server.is_connected(client_id) {
             error!("Client disconnected: {}", client_id);
             error!("Current connected: {}", self.server.connected_clients());
@@ -34,11 +44,11 @@ impl RenetServerWithConfig {
     }
 
     /// 延迟到下一帧发送事件
-    pub fn send_event_next(&mut self, client_id: ClientId, event: GameEvent) {
+    pub fn send_event_next(&mut self, client_id: ClientId, event: impl Into<shared::event::ServerEvent>) {
         self.event_buffer
             .entry(client_id)
             .or_insert_with(Vec::new)
-            .push(event);
+            .push(event.into());
     }
 
     /// 刷新所有缓冲事件（在游戏循环结束时调用）
@@ -61,11 +71,16 @@ pub struct RenetGameServer {
     last_update: Instant,
 
     room_manager: Rooms,
+    matchmaker: Matchmaker,
     client_player_cache: HashMap<ClientId, Player>,
 }
 
 impl RenetGameServer {
-    pub fn with_transport(transport: NetcodeServerTransport) -> Self {
+    pub fn with_transport(
+        transport: NetcodeServerTransport,
+        room_config: RoomConfig,
+        lobby: RoomList,
+    ) -> Self {
         let bincode_config = bincode::config::standard();
         let server = RenetServer::new(ConnectionConfig {
             available_bytes_per_tick: 60_000,
@@ -74,14 +89,11 @@ impl RenetGameServer {
         });
         Self {
             bincode_config,
-            server: RenetServerWithConfig {
-                config: bincode_config,
-                server,
-                event_buffer: HashMap::new(),
-            },
+            server: RenetServerWithConfig::new(server),
             last_update: Instant::now(),
             transport,
-            room_manager: Rooms::with_test_room(), // 测试房间id为u64默认值 0
+            room_manager: Rooms::new(room_config, lobby),
+            matchmaker: Matchmaker::default(),
             client_player_cache: HashMap::new(),
         }
     }
@@ -99,12 +111,21 @@ impl RenetGameServer {
                 ServerEvent::ClientConnected { client_id } => {
                     // 处理用户重新连接，恢复用户状态
                     info!("Client connected: {}", client_id);
-                    let _ = self.room_manager.process_event(client_id, GameEvent::PlayerConnected(client_id), &mut self.server);
+                    // 名字来自 HTTP 服务器签发的连接令牌，不信任指令中的内容
+                    let name = self
+                        .transport
+                        .user_data(client_id)
+                        .map(|user_data| name_from_user_data(&user_data))
+                        .unwrap_or_default();
+                    self.client_player_cache.insert(client_id, Player { id: client_id, name, avatar: None });
+                    self.room_manager.process_room_event(client_id, GameEvent::PlayerConnected(client_id), &mut self.server);
                 },
                 ServerEvent::ClientDisconnected { client_id, reason } => {
                     // 处理用户断开连接， 更新用户状态为离线
                     info!("Client disconnected: {}", client_id);
-                    let _ = self.room_manager.process_event(client_id, GameEvent::PlayerDisconnected(client_id), &mut self.server);
+                    self.client_player_cache.remove(&client_id);
+                    self.matchmaker.remove(client_id, &self.room_manager, &mut self.server);
+                    self.room_manager.process_room_event(client_id, GameEvent::PlayerDisconnected(client_id), &mut self.server);
                 },
             }
         }
@@ -114,23 +135,36 @@ impl RenetGameServer {
 
         for (client_id) in self.server.server.clients_id() {
             while let Some(message) = self.server.server.receive_message(client_id, 0) {
-                if let Ok((event, _)) =
-                    decode_from_slice::<GameEvent, Configuration>(&message, self.bincode_config)
+                if let Ok((command, _)) =
+                    decode_from_slice::<ClientCommand, Configuration>(&message, self.bincode_config)
                 {
-                    info!("Received event from client {:?}, {}", client_id, event);
-                    let res =
-                        self.room_manager
-                            .process_event(client_id, event.clone(), &mut self.server);
+                    info!("Received command from client {:?}, {}", client_id, command);
+                    let Some(player) = self.client_player_cache.get(&client_id) else {
+                        continue;
+                    };
+                    let res = match command {
+                        ClientCommand::QuickMatch { .. } | ClientCommand::CancelMatch => self
+                            .matchmaker
+                            .process_command(player, command, &self.room_manager, &mut self.server),
+                        _ => self.room_manager.process_command(player, command, &mut self.server),
+                    };
                     if let Err(err) = res {
                         info!(
-                            "Error processing event from client {:?}, {}",
+                            "Error processing command from client {:?}, {}",
                             client_id, err
                         );
+                        self.server.send_event(client_id, shared::event::ServerEvent::RoomError(err));
                     }
                 }
             }
         }
 
+        // 快速匹配
+        self.matchmaker.update(now, &mut self.room_manager, &mut self.server);
+
+        // 房间计时与机器人动作
+        self.room_manager.update(now, &mut self.server);
+
         self.transport.send_packets(&mut self.server.server);
         std::thread::sleep(Duration::from_millis(50));
     }
@@ -0,0 +1,226 @@
+//! ### 游客凭证
+//! `/connect` 为每个游客签发一个随机凭证，客户端之后带上凭证就能沿用原来的 `ClientId`。
+//!
+//! 凭证保存在 JSON 文件中，服务器重启后游客的身份不变。凭证数量有上限，
+//! 长时间不用的凭证会过期，新游客的签发速度也有限制，避免未认证的请求无限占用内存和文件。
+//! 签发速度按来源地址限制，单个地址请求过快不会影响其他新玩家，另有总的上限兜底。
+
+use log::{error, info};
+use renet2::ClientId;
+use renet2_netcode::generate_random_bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// 最多保存的凭证数，超出时移除最久没有使用的
+const MAX_GUESTS: usize = 100_000;
+/// 超过这么久没有使用的凭证过期
+const GUEST_EXPIRE: Duration = Duration::from_secs(90 * 24 * 3600);
+/// 每个来源地址每分钟最多签发的新游客数
+const MAX_NEW_GUESTS_PER_ADDR: usize = 5;
+/// 所有来源每分钟最多签发的新游客数
+const MAX_NEW_GUESTS_PER_MINUTE: usize = 600;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Guest {
+    client_id: ClientId,
+    /// 最后一次使用的时间，UNIX 秒
+    last_seen: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct GuestStore {
+    /// 保存的文件，`None` 表示只在内存中记录
+    path: Option<PathBuf>,
+    guests: HashMap<String, Guest>,
+    /// 最近一分钟内签发新游客的时间，UNIX 秒
+    recent: Vec<u64>,
+    /// 按来源地址记录的最近一分钟内签发新游客的时间，见 [`rate_limit_key`]
+    recent_by_addr: HashMap<IpAddr, Vec<u64>>,
+    /// 有还没有写入文件的修改
+    dirty: bool,
+}
+
+impl GuestStore {
+    /// 读取 `path`，文件不存在时从空开始，文件无法解析时不再写入
+    pub fn open(path: PathBuf) -> Self {
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                info!("Guest file {} not found, starting empty", path.display());
+                return Self { path: Some(path), ..Default::default() };
+            },
+            Err(err) => {
+                error!("Failed to read guests from {}: {}, guests will not be saved", path.display(), err);
+                return Self::default();
+            },
+        };
+        match serde_json::from_str::<HashMap<String, Guest>>(&json) {
+            Ok(guests) => {
+                info!("Loaded {} guests from {}", guests.len(), path.display());
+                Self { path: Some(path), guests, ..Default::default() }
+            },
+            Err(err) => {
+                error!("Failed to parse guests from {}: {}, guests will not be saved", path.display(), err);
+                Self::default()
+            },
+        }
+    }
+
+    /// 查找凭证对应的 `ClientId` 并刷新使用时间，过期的凭证视为不存在
+    pub fn lookup(&mut self, key: &str, now: u64) -> Option<ClientId> {
+        let guest = self.guests.get_mut(key)?;
+        if now.saturating_sub(guest.last_seen) >= GUEST_EXPIRE.as_secs() {
+            self.guests.remove(key);
+            self.dirty = true;
+            return None;
+        }
+        guest.last_seen = now;
+        self.dirty = true;
+        Some(guest.client_id)
+    }
+
+    /// 为来自 `addr` 的请求签发新的游客凭证，该地址或总的签发速度超限时返回 `None`
+    pub fn register(&mut self, now: u64, addr: IpAddr) -> Option<(String, ClientId)> {
+        let recent = |at: &u64| now.saturating_sub(*at) < 60;
+        self.recent.retain(recent);
+        self.recent_by_addr.retain(|_, times| {
+            times.retain(recent);
+            !times.is_empty()
+        });
+        let addr_recent = self.recent_by_addr.entry(rate_limit_key(addr)).or_default();
+        if addr_recent.len() >= MAX_NEW_GUESTS_PER_ADDR
+            || self.recent.len() >= MAX_NEW_GUESTS_PER_MINUTE
+        {
+            return None;
+        }
+        addr_recent.push(now);
+        self.recent.push(now);
+
+        self.guests
+            .retain(|_, guest| now.saturating_sub(guest.last_seen) < GUEST_EXPIRE.as_secs());
+        while self.guests.len() >= MAX_GUESTS {
+            let oldest = self
+                .guests
+                .iter()
+                .min_by_key(|(_, guest)| guest.last_seen)
+                .map(|(key, _)| key.clone());
+            let Some(oldest) = oldest else {
+                break;
+            };
+            self.guests.remove(&oldest);
+        }
+
+        let key = hex(&generate_random_bytes::<32>());
+        let client_id = new_guest_id();
+        self.guests.insert(key.clone(), Guest { client_id, last_seen: now });
+        self.dirty = true;
+        Some((key, client_id))
+    }
+
+    /// 有修改时写入文件，定期调用，先写临时文件再替换原文件
+    pub fn flush(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let json = match serde_json::to_string(&self.guests) {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Failed to encode guests: {}", err);
+                return;
+            },
+        };
+        let tmp = path.with_extension("tmp");
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&tmp, json))
+            .and_then(|_| fs::rename(&tmp, path));
+        if let Err(err) = result {
+            error!("Failed to save guests to {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// 限速使用的地址，IPv6 按 /64 前缀合并，同一台设备通常可以随意使用前缀下的地址
+fn rate_limit_key(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(Ipv6Addr::from(v6.to_bits() & !u128::from(u64::MAX))),
+        },
+        v4 => v4,
+    }
+}
+
+/// 游客的 `ClientId`，与机器人的 id 段错开
+fn new_guest_id() -> ClientId {
+    let id = u64::from_le_bytes(generate_random_bytes::<8>()) % (u64::MAX / 2);
+    id.max(1)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(last: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last])
+    }
+
+    #[test]
+    fn test_guest_store() {
+        let mut store = GuestStore::default();
+        let (key, client_id) = store.register(0, addr(1)).unwrap();
+        assert_eq!(store.lookup(&key, 10), Some(client_id));
+        assert_eq!(store.lookup("unknown", 10), None);
+
+        // 同一地址签发速度受限，一分钟后恢复，不影响其他地址
+        for _ in 1..MAX_NEW_GUESTS_PER_ADDR {
+            store.register(30, addr(1)).unwrap();
+        }
+        assert!(store.register(30, addr(1)).is_none());
+        assert!(store.register(30, addr(2)).is_some());
+        assert!(store.register(70, addr(1)).is_some());
+
+        // 长时间不用的凭证过期
+        let later = 100 + GUEST_EXPIRE.as_secs();
+        assert_eq!(store.lookup(&key, later), None);
+        store.register(later, addr(1)).unwrap();
+        assert_eq!(store.guests.len(), 1);
+    }
+
+    // 总的签发速度有上限，一分钟后恢复
+    #[test]
+    fn test_global_rate_limit() {
+        let mut store = GuestStore::default();
+        for index in 0..MAX_NEW_GUESTS_PER_MINUTE {
+            let addr = IpAddr::from([10, 1, (index / 256) as u8, (index % 256) as u8]);
+            store.register(0, addr).unwrap();
+        }
+        assert!(store.register(0, addr(1)).is_none());
+        assert!(store.register(60, addr(1)).is_some());
+    }
+
+    // 同一个 /64 前缀下的 IPv6 地址共用限额
+    #[test]
+    fn test_ipv6_prefix_shares_limit() {
+        let mut store = GuestStore::default();
+        for last in 0..MAX_NEW_GUESTS_PER_ADDR as u16 {
+            let addr = IpAddr::from([0x2001, 0xdb8, 0, 1, 0, 0, 0, last]);
+            store.register(0, addr).unwrap();
+        }
+        assert!(store.register(0, IpAddr::from([0x2001, 0xdb8, 0, 1, 0, 0, 0, 0xffff])).is_none());
+        assert!(store.register(0, IpAddr::from([0x2001, 0xdb8, 0, 2, 0, 0, 0, 1])).is_some());
+    }
+}
@@ -1,34 +1,271 @@
 use axum::{
     Json, Router,
+    extract::{ConnectInfo, Path, Query, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
     routing::{get, post},
 };
+use log::{error, info};
+use renet2::ClientId;
+use renet2_netcode::{ConnectToken, NETCODE_KEY_BYTES};
+use serde::Deserialize;
+use shared::Player;
+use shared::auth::{ConnectRequest, ConnectResponse};
+use shared::replay::{REPLAY_FILE_EXTENSION, Replay, ReplayEntry};
 
 use crate::ClientConnectionInfo;
+use crate::game::RoomList;
+use crate::guests::GuestStore;
+use crate::utils::name_to_user_data;
 
 use axum::http::{HeaderMap, HeaderName, HeaderValue, header};
+use std::fs;
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
-pub async fn run_http_server(http_addr: SocketAddr, client_connection_info: ClientConnectionInfo) {
+/// 连接令牌的有效期，客户端拿到令牌后需要在这段时间内发起连接
+const TOKEN_EXPIRE_SECS: u64 = 300;
+/// 连接建立后多久收不到数据包视为断线
+const TOKEN_TIMEOUT_SECS: i32 = 15;
+/// 名字的最大字符数
+const MAX_NAME_CHARS: usize = 16;
+/// 游客凭证写入文件的间隔
+const GUEST_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+/// `/replays` 最多返回的回放数量
+const MAX_LISTED_REPLAYS: usize = 20;
+/// `/replays` 最多检查的回放文件数量，按修改时间从新到旧
+const MAX_SCANNED_REPLAYS: usize = 500;
+
+/// `/connect` 签发令牌需要的配置
+pub struct AuthConfig {
+    pub private_key: [u8; NETCODE_KEY_BYTES],
+    pub protocol_id: u64,
+    /// 与服务器 socket 顺序一致的地址，令牌中的地址必须与服务器的 socket 地址一致
+    pub socket_addresses: Vec<Vec<SocketAddr>>,
+    /// 游客凭证的保存文件，`None` 表示只保存在内存中，服务器重启后游客会拿到新的身份
+    pub guest_file: Option<PathBuf>,
+}
+
+struct AppState {
+    auth: AuthConfig,
+    lobby: RoomList,
+    guests: Mutex<GuestStore>,
+    /// 回放文件目录，与牌局服务器保存回放的目录相同
+    replay_dir: Option<PathBuf>,
+}
+
+/// `/replays` 和 `/replays/{name}` 的查询参数，回放中所有手牌都是明牌，只给参与过的玩家看
+#[derive(Deserialize)]
+struct ReplayRequest {
+    /// `/connect` 签发的游客凭证，用来确定请求的玩家
+    guest_key: String,
+}
+
+pub async fn run_http_server(
+    http_addr: SocketAddr,
+    client_connection_info: ClientConnectionInfo,
+    auth: AuthConfig,
+    lobby: RoomList,
+    replay_dir: Option<PathBuf>,
+) {
     let listener = tokio::net::TcpListener::bind(http_addr)
         .await
         .expect("could not listen on HTTP address/port");
 
     // let client_connection_info = Arc::new(client_connection_info);
     let json_info = serde_json::to_string(&client_connection_info).unwrap();
+    let guests = auth.guest_file.clone().map(GuestStore::open).unwrap_or_default();
+    let state = Arc::new(AppState {
+        auth,
+        lobby,
+        guests: Mutex::new(guests),
+        replay_dir,
+    });
+    // 定期写入游客凭证，不在请求中写文件
+    let saver = state.clone();
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(GUEST_SAVE_INTERVAL);
+            saver.guests.lock().unwrap().flush();
+        }
+    });
     let app = Router::new()
         .route("/", get(|| async { "Hello, World!" }))
         .route(
             "/info",
-            get(|| async move {
-                let mut headers = HeaderMap::new();
-                headers.insert(
-                    header::ACCESS_CONTROL_ALLOW_ORIGIN,
-                    HeaderValue::from_static("*"),
-                );
-                (headers, json_info.clone())
-            }),
-        );
-
-    axum::serve(listener, app).await.unwrap();
+            get(|| async move { (cors_headers(), json_info.clone()) }),
+        )
+        .route("/connect", get(connect))
+        .route("/rooms", get(rooms))
+        .route("/replays", get(replays))
+        .route("/replays/{name}", get(replay_file))
+        .with_state(state);
+
+    // 新游客按来源地址限速，需要连接的地址
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
+}
+
+fn cors_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    headers
+}
+
+/// 签发连接令牌
+///
+/// 带上已知的游客凭证时沿用原来的 `ClientId`，否则分配新的游客身份，同一地址新游客签发过快时返回 429，
+/// 名字写入令牌的 user data，服务器在连接建立后从中读取
+async fn connect(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    Query(request): Query<ConnectRequest>,
+) -> Response {
+    let Some(addresses) = state.auth.socket_addresses.get(request.socket_id as usize) else {
+        return (StatusCode::BAD_REQUEST, cors_headers(), "unknown socket id").into_response();
+    };
+
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    let guest = {
+        let mut guests = state.guests.lock().unwrap();
+        let now = current_time.as_secs();
+        request
+            .guest_key
+            .and_then(|key| Some((guests.lookup(&key, now)?, key)))
+            .or_else(|| guests.register(now, remote.ip()).map(|(key, client_id)| (client_id, key)))
+    };
+    let Some((client_id, guest_key)) = guest else {
+        return (StatusCode::TOO_MANY_REQUESTS, cors_headers(), "too many new guests").into_response();
+    };
+
+    let name = request.name.trim().chars().take(MAX_NAME_CHARS).collect::<String>();
+    let name = if name.is_empty() {
+        format!("游客{}", client_id % 10000)
+    } else {
+        name
+    };
+
+    let token = ConnectToken::generate(
+        current_time,
+        state.auth.protocol_id,
+        TOKEN_EXPIRE_SECS,
+        client_id,
+        TOKEN_TIMEOUT_SECS,
+        request.socket_id,
+        addresses.clone(),
+        Some(&name_to_user_data(&name)),
+        &state.auth.private_key,
+    );
+    let mut connect_token = Vec::new();
+    if let Err(err) = token.map_err(|err| err.to_string()).and_then(|token| {
+        token.write(&mut connect_token).map_err(|err| err.to_string())
+    }) {
+        error!("Failed to generate connect token for {}: {}", client_id, err);
+        return (StatusCode::INTERNAL_SERVER_ERROR, cors_headers()).into_response();
+    }
+
+    info!("Issued connect token for {} ({})", client_id, name);
+    let response = ConnectResponse {
+        player: Player {
+            id: client_id,
+            name,
+            avatar: None,
+        },
+        guest_key,
+        connect_token,
+    };
+    (cors_headers(), Json(response)).into_response()
+}
+
+/// 公开房间列表
+async fn rooms(State(state): State<Arc<AppState>>) -> Response {
+    let list = state.lobby.read().unwrap().clone();
+    (cors_headers(), Json(list)).into_response()
+}
+
+/// 游客凭证对应的玩家，凭证未知或已过期时返回 `None`
+fn replay_player(state: &AppState, request: &ReplayRequest) -> Option<ClientId> {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    state.guests.lock().unwrap().lookup(&request.guest_key, now)
+}
+
+/// 请求的玩家参与过的最近几手牌的回放
+async fn replays(
+    State(state): State<Arc<AppState>>,
+    Query(request): Query<ReplayRequest>,
+) -> Response {
+    let Some(player_id) = replay_player(&state, &request) else {
+        return (StatusCode::UNAUTHORIZED, cors_headers(), "unknown guest key").into_response();
+    };
+    let Some(dir) = state.replay_dir.clone() else {
+        return (cors_headers(), Json(Vec::<ReplayEntry>::new())).into_response();
+    };
+    let list = tokio::task::spawn_blocking(move || list_replays(&dir, player_id))
+        .await
+        .unwrap_or_default();
+    (cors_headers(), Json(list)).into_response()
+}
+
+fn list_replays(dir: &std::path::Path, player_id: ClientId) -> Vec<ReplayEntry> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == REPLAY_FILE_EXTENSION))
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect::<Vec<_>>();
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    files
+        .into_iter()
+        .take(MAX_SCANNED_REPLAYS)
+        .filter_map(|(_, path)| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            let replay = Replay::decode(&fs::read(&path).ok()?).ok()?;
+            Some(ReplayEntry { name, header: replay.header })
+        })
+        .filter(|entry| entry.header.has_player(player_id))
+        .take(MAX_LISTED_REPLAYS)
+        .collect()
+}
+
+/// 下载回放文件，`name` 为 `/replays` 返回的文件名，只有参与过这手牌的玩家可以下载
+async fn replay_file(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(request): Query<ReplayRequest>,
+) -> Response {
+    let Some(player_id) = replay_player(&state, &request) else {
+        return (StatusCode::UNAUTHORIZED, cors_headers(), "unknown guest key").into_response();
+    };
+    let Some(dir) = state.replay_dir.clone() else {
+        return (StatusCode::NOT_FOUND, cors_headers()).into_response();
+    };
+    // 文件名只由房间号和时间组成，拒绝其他字符，防止读取目录外的文件
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return (StatusCode::BAD_REQUEST, cors_headers(), "invalid replay name").into_response();
+    }
+    let path = dir.join(format!("{}.{}", name, REPLAY_FILE_EXTENSION));
+    // 没有参与过的回放与不存在的回放一样返回 404，不暴露其他玩家的牌局
+    let read = move || {
+        let bytes = fs::read(path).ok()?;
+        let replay = Replay::decode(&bytes).ok()?;
+        replay.header.has_player(player_id).then_some(bytes)
+    };
+    match tokio::task::spawn_blocking(read).await {
+        Ok(Some(bytes)) => (cors_headers(), bytes).into_response(),
+        _ => (StatusCode::NOT_FOUND, cors_headers()).into_response(),
+    }
 }
@@ -2,6 +2,7 @@ mod http_server;
 mod game_server;
 mod game;
 mod utils;
+mod guests;
 
 use std::env;
 use std::{
@@ -12,28 +13,34 @@ use std::{
 
 use bincode::config::Configuration;
 
-use log::{info, trace};
+use log::{info, trace, warn};
 
 use renet2::{ConnectionConfig, RenetServer, ServerEvent};
-use renet2_netcode::{BoxedSocket, NETCODE_USER_DATA_BYTES, NativeSocket, NetcodeServerTransport, ServerAuthentication, ServerCertHash, ServerConfig, ServerSetupConfig, WebServerDestination, WebSocketServer, WebSocketServerConfig, WebTransportServer, WebTransportServerConfig, ServerSocket, WebSocketAcceptor};
+use renet2_netcode::{BoxedSocket, NETCODE_KEY_BYTES, NativeSocket, NetcodeServerTransport, ServerAuthentication, ServerCertHash, ServerConfig, ServerSetupConfig, WebServerDestination, WebSocketServer, WebSocketServerConfig, WebTransportServer, WebTransportServerConfig, ServerSocket, WebSocketAcceptor, generate_random_bytes};
 use serde::{Deserialize, Serialize};
+use crate::game::{RoomConfig, RoomList, spawn_profile_saver};
 use crate::game_server::RenetGameServer;
-use crate::http_server::run_http_server;
+use crate::http_server::{AuthConfig, run_http_server};
 
 // used to make sure players use the most recent version of the client.
-pub const PROTOCOL_ID: u64 = 7;
-
-/// Utility function for extracting a players name from renet user data
-fn name_from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> String {
-    let mut buffer = [0u8; 8];
-    buffer.copy_from_slice(&user_data[0..8]);
-    let mut len = u64::from_le_bytes(buffer) as usize;
-    len = len.min(NETCODE_USER_DATA_BYTES - 8);
-    let data = user_data[8..len + 8].to_vec();
-    String::from_utf8(data).unwrap()
+pub const PROTOCOL_ID: u64 = shared::PROTOCOL_ID;
+
+/// 读取 `NETCODE_PRIVATE_KEY`（64 位十六进制）作为连接令牌的签名私钥，
+/// 不设置时随机生成，重启后之前签发的令牌全部失效
+fn load_private_key() -> [u8; NETCODE_KEY_BYTES] {
+    let Ok(hex) = env::var("NETCODE_PRIVATE_KEY") else {
+        warn!("NETCODE_PRIVATE_KEY not set, using a random private key");
+        return generate_random_bytes();
+    };
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect::<Option<Vec<u8>>>();
+    bytes
+        .and_then(|bytes| bytes.try_into().ok())
+        .expect("NETCODE_PRIVATE_KEY must be 64 hex characters")
 }
 
-
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct ClientConnectionInfo {
     native_addr: String,
@@ -92,16 +99,25 @@ fn main() {
     let current_time = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap();
+    // socket 顺序：0 原生 UDP，1 WebTransport，2 WebSocket
+    let socket_addresses = vec![
+        vec![native_socket.addr().unwrap()],
+        vec![wt_socket.addr().unwrap()],
+        vec![ws_socket.addr().unwrap()],
+    ];
+    let private_key = load_private_key();
+    let auth_config = AuthConfig {
+        private_key,
+        protocol_id: PROTOCOL_ID,
+        socket_addresses: socket_addresses.clone(),
+        guest_file: env::var("GUEST_FILE").ok().filter(|path| !path.is_empty()).map(Into::into),
+    };
     let server_config = ServerSetupConfig {
         current_time,
         max_clients: max_clients,
         protocol_id: PROTOCOL_ID,
-        socket_addresses: vec![
-            vec![native_socket.addr().unwrap()],
-            vec![wt_socket.addr().unwrap()],
-            vec![ws_socket.addr().unwrap()],
-        ],
-        authentication: ServerAuthentication::Unsecure,
+        socket_addresses,
+        authentication: ServerAuthentication::Secure { private_key },
     };
 
     // let mut transport = NetcodeServerTransport::new(server_config, socket).unwrap();
@@ -114,8 +130,15 @@ fn main() {
         ]),
     )
     .unwrap();
-    let mut renet_game_server = RenetGameServer::with_transport(transport);
-    runtime.spawn(async move { run_http_server(http_addr, client_connection_info).await});
+    let lobby = RoomList::default();
+    let room_config = RoomConfig::from_env();
+    let replay_dir = room_config.replay_dir.clone();
+    spawn_profile_saver(room_config.profiles.clone());
+    let mut renet_game_server =
+        RenetGameServer::with_transport(transport, room_config, lobby.clone());
+    runtime.spawn(async move {
+        run_http_server(http_addr, client_connection_info, auth_config, lobby, replay_dir).await
+    });
 
     loop {
         renet_game_server.update();
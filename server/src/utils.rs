@@ -1,11 +1,25 @@
 use renet2_netcode::NETCODE_USER_DATA_BYTES;
 
 /// Utility function for extracting a players name from renet user data
-fn name_from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> String {
+pub fn name_from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> String {
     let mut buffer = [0u8; 8];
     buffer.copy_from_slice(&user_data[0..8]);
     let mut len = u64::from_le_bytes(buffer) as usize;
     len = len.min(NETCODE_USER_DATA_BYTES - 8);
     let data = user_data[8..len + 8].to_vec();
-    String::from_utf8(data).unwrap()
-}
\ No newline at end of file
+    String::from_utf8_lossy(&data).into_owned()
+}
+
+/// 把名字写入连接令牌的 user data，格式与 [`name_from_user_data`] 对应，过长的名字按字符截断
+pub fn name_to_user_data(name: &str) -> [u8; NETCODE_USER_DATA_BYTES] {
+    let mut end = name.len().min(NETCODE_USER_DATA_BYTES - 8);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    let name = &name[..end];
+
+    let mut user_data = [0u8; NETCODE_USER_DATA_BYTES];
+    user_data[0..8].copy_from_slice(&(name.len() as u64).to_le_bytes());
+    user_data[8..name.len() + 8].copy_from_slice(name.as_bytes());
+    user_data
+}
@@ -0,0 +1,33 @@
+//! 无界面批量模拟对局并输出统计
+//!
+//! 用法：`cargo run -p shared --release --example simulate -- [局数] [easy|normal|hard] [起始种子]`
+
+use std::env;
+use std::process::ExitCode;
+
+use shared::the_hidden_card::prelude::BotDifficulty;
+use shared::the_hidden_card::simulation::{SimulationStats, Simulator};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let games = args.first().and_then(|arg| arg.parse().ok()).unwrap_or(1000u64);
+    let difficulty = match args.get(1).map(String::as_str) {
+        None | Some("normal") => BotDifficulty::Normal,
+        Some("easy") => BotDifficulty::Easy,
+        Some("hard") => BotDifficulty::Hard,
+        Some(other) => {
+            eprintln!("未知难度 {other}，可选 easy、normal、hard");
+            return ExitCode::FAILURE;
+        },
+    };
+    let first_seed = args.get(2).and_then(|arg| arg.parse().ok()).unwrap_or(0u64);
+
+    println!("难度: {difficulty}，种子: {first_seed}..{}", first_seed + games);
+    let mut simulator = Simulator::with_difficulty(difficulty, first_seed);
+    let mut stats = SimulationStats::default();
+    for seed in first_seed..first_seed + games {
+        stats.add(&simulator.run(seed));
+    }
+    println!("{stats}");
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,28 @@
+//! ### 连接认证
+//! 客户端先请求 HTTP 服务器的 `/connect`，拿到服务器私钥签名的连接令牌后再建立 netcode 连接，
+//! 令牌中绑定了 `ClientId` 和玩家名字，服务器只认令牌中的身份。
+
+use serde::{Deserialize, Serialize};
+
+use crate::Player;
+
+/// `/connect` 的查询参数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectRequest {
+    /// 希望使用的名字，服务器会截断过长的名字
+    pub name: String,
+    /// 服务器的 socket 索引：0 为原生 UDP，1 为 WebTransport，2 为 WebSocket
+    pub socket_id: u8,
+    /// 之前拿到的游客凭证，带上后沿用同一个 `ClientId`，断线重连时才能回到原来的房间
+    pub guest_key: Option<String>,
+}
+
+/// `/connect` 的返回
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectResponse {
+    /// 服务器分配的身份，客户端用它替换本地的 [`Player`]
+    pub player: Player,
+    pub guest_key: String,
+    /// 序列化后的 `ConnectToken`
+    pub connect_token: Vec<u8>,
+}
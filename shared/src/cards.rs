@@ -2,8 +2,9 @@ use std::cmp::Ordering;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use rand::rng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng, rng};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, EnumIter, PartialOrd, Ord)]
@@ -81,7 +82,58 @@ impl Deck {
         self.value.shuffle(&mut rng());
     }
 
+    /// 使用种子洗牌，同样的种子得到同样的牌序（同一版本的 rand 下）
+    pub fn shuffle_with_seed(&mut self, seed: u64) {
+        self.value.shuffle(&mut StdRng::seed_from_u64(seed));
+    }
+
+    /// 生成一个新的随机种子
+    pub fn random_seed() -> u64 {
+        rng().random()
+    }
+
     pub fn get(&self) -> &Vec<Card> {
         &self.value
     }
+}
+
+/// 用种子洗一副新牌，从 `first_seat` 开始按座位顺序轮流发给 4 个座位，每个座位 13 张。
+///
+/// 返回值按座位索引排列，同样的种子和起始座位总是得到同样的手牌。
+pub fn deal(seed: u64, first_seat: usize) -> [Vec<Card>; 4] {
+    let mut deck = Deck::new();
+    deck.shuffle_with_seed(seed);
+
+    let mut hands: [Vec<Card>; 4] = Default::default();
+    for (i, card) in deck.value.into_iter().enumerate() {
+        hands[(first_seat + i) % 4].push(card);
+    }
+    hands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_deal_is_reproducible() {
+        assert_eq!(deal(42, 0), deal(42, 0));
+        assert_ne!(deal(42, 0), deal(43, 0));
+
+        // 起始座位只改变发牌顺序
+        let hands = deal(42, 0);
+        let rotated = deal(42, 1);
+        for seat in 0..4 {
+            assert_eq!(hands[seat], rotated[(seat + 1) % 4]);
+        }
+    }
+
+    #[test]
+    fn test_deal_uses_whole_deck() {
+        let hands = deal(7, 2);
+        let cards: HashSet<&Card> = hands.iter().flatten().collect();
+        assert!(hands.iter().all(|hand| hand.len() == 13));
+        assert_eq!(cards.len(), 52);
+    }
 }
\ No newline at end of file
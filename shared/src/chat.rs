@@ -0,0 +1,170 @@
+//! ### 房间聊天
+//! 文字消息、快捷短语和表情，由服务器按房间转发。
+//!
+//! 文字消息在服务器上经过 [`WordFilter`] 过滤后再转发，快捷短语和表情不需要过滤。
+//! 观战者的消息只发给观战者，避免把延迟观战看到的牌局透露给玩家。
+
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+use crate::ClientId;
+use crate::error::RoomServiceError;
+
+/// 文字消息的最大字符数
+pub const MAX_CHAT_CHARS: usize = 60;
+
+/// 快捷短语
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+pub enum QuickPhrase {
+    #[strum(to_string = "快点")]
+    Hurry,
+    #[strum(to_string = "好牌")]
+    NiceHand,
+    #[strum(to_string = "打得好")]
+    WellPlayed,
+    #[strum(to_string = "队友是谁")]
+    WhoIsMyAlly,
+    #[strum(to_string = "不好意思")]
+    Sorry,
+    #[strum(to_string = "再来一局")]
+    OneMore,
+}
+
+impl QuickPhrase {
+    pub const ALL: [QuickPhrase; 6] = [
+        QuickPhrase::Hurry,
+        QuickPhrase::NiceHand,
+        QuickPhrase::WellPlayed,
+        QuickPhrase::WhoIsMyAlly,
+        QuickPhrase::Sorry,
+        QuickPhrase::OneMore,
+    ];
+}
+
+/// 表情
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+pub enum Emote {
+    #[strum(to_string = "(^_^)")]
+    Smile,
+    #[strum(to_string = "(T_T)")]
+    Cry,
+    #[strum(to_string = "(>_<)")]
+    Angry,
+    #[strum(to_string = "(o_O)")]
+    Surprised,
+}
+
+impl Emote {
+    pub const ALL: [Emote; 4] = [Emote::Smile, Emote::Cry, Emote::Angry, Emote::Surprised];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Display)]
+pub enum ChatContent {
+    #[strum(to_string = "{0}")]
+    Text(String),
+    #[strum(to_string = "{0}")]
+    Phrase(QuickPhrase),
+    #[strum(to_string = "{0}")]
+    Emote(Emote),
+}
+
+impl ChatContent {
+    /// 文字消息去掉首尾空白后不能为空，也不能超过 [`MAX_CHAT_CHARS`]
+    pub fn validate(&self) -> Result<(), RoomServiceError> {
+        match self {
+            ChatContent::Text(text) => {
+                let len = text.trim().chars().count();
+                if len == 0 || len > MAX_CHAT_CHARS {
+                    return Err(RoomServiceError::InvalidChat);
+                }
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 服务器转发的聊天消息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
+pub struct ChatMessage {
+    pub sender: ClientId,
+    pub name: String,
+    pub content: ChatContent,
+    /// 发送者是观战者
+    pub spectator: bool,
+}
+
+/// 屏蔽词过滤，命中的部分替换为 `*`，英文字母不区分大小写
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    words: Vec<Vec<char>>,
+}
+
+impl WordFilter {
+    pub fn new<S: AsRef<str>>(words: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            words: words
+                .into_iter()
+                .map(|word| word.as_ref().trim().chars().collect::<Vec<_>>())
+                .filter(|word| !word.is_empty())
+                .collect(),
+        }
+    }
+
+    /// 每行一个屏蔽词，空行忽略
+    pub fn from_lines(text: &str) -> Self {
+        Self::new(text.lines())
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut chars = text.trim().chars().collect::<Vec<_>>();
+        for word in self.words.iter() {
+            let mut index = 0;
+            while index + word.len() <= chars.len() {
+                let window = &mut chars[index..index + word.len()];
+                if window.iter().zip(word).all(|(a, b)| a.eq_ignore_ascii_case(b)) {
+                    window.fill('*');
+                    index += word.len();
+                } else {
+                    index += 1;
+                }
+            }
+        }
+        chars.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_filter() {
+        let filter = WordFilter::from_lines("笨蛋\n\n  bad \n");
+        assert_eq!(filter.len(), 2);
+        assert_eq!(filter.apply(" 你个笨蛋，BAD move "), "你个**，*** move");
+        assert_eq!(filter.apply("好牌"), "好牌");
+        assert_eq!(WordFilter::default().apply("笨蛋"), "笨蛋");
+    }
+
+    #[test]
+    fn test_chat_content_validate() {
+        assert_eq!(ChatContent::Text("  ".to_string()).validate(), Err(RoomServiceError::InvalidChat));
+        assert_eq!(
+            ChatContent::Text("a".repeat(MAX_CHAT_CHARS + 1)).validate(),
+            Err(RoomServiceError::InvalidChat)
+        );
+        assert_eq!(ChatContent::Text("好牌".to_string()).validate(), Ok(()));
+        assert_eq!(ChatContent::Phrase(QuickPhrase::Hurry).validate(), Ok(()));
+        assert_eq!(ChatContent::Phrase(QuickPhrase::Hurry).to_string(), "快点");
+    }
+}
@@ -2,9 +2,22 @@ use strum_macros::Display;
 use serde::{Deserialize, Serialize};
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize,  Clone, Display)]
 pub enum RoomServiceError {
+    #[strum(to_string = "已经在房间中")]
     AlreadyInRoom,
+    #[strum(to_string = "房间不存在")]
     RoomNotFound,
+    #[strum(to_string = "房间已满")]
     RoomFull,
+    #[strum(to_string = "不在房间中")]
     ClientNotInRoom,
+    #[strum(to_string = "没有权限")]
     ActionNotAllowed,
-}
\ No newline at end of file
+    #[strum(to_string = "密码错误")]
+    WrongPassword,
+    #[strum(to_string = "房间设置无效")]
+    InvalidSettings,
+    #[strum(to_string = "发言太频繁")]
+    ChatTooFast,
+    #[strum(to_string = "消息不能为空或太长")]
+    InvalidChat,
+}
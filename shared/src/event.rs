@@ -3,47 +3,143 @@ use serde::{Deserialize, Serialize};
 use strum_macros::Display;
 
 use crate::cards::Card;
+use crate::chat::{ChatContent, ChatMessage};
 use crate::error::RoomServiceError;
-use crate::the_hidden_card::state::{GameState, Stage};
+use crate::lobby::{LobbyEvent, RoomSettings, RoomSummary};
+use crate::the_hidden_card::prelude::GameError;
+use crate::the_hidden_card::result::GameResult;
+use crate::the_hidden_card::state::{GameState, PendingTurn, Stage};
+use crate::the_hidden_card::strategy::BotDifficulty;
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub enum EndGameReason {
-    PlayerLeft { player_id: ClientId },
-    PlayerWon { winner: ClientId },
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Display)]
-#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
-pub enum GameEvent {
-    // 开发用
+/// 客户端发给服务器的指令
+///
+/// 指令不包含执行的玩家和座位，服务器按连接的 `ClientId` 确定，不信任客户端发来的身份，
+/// 玩家名字来自连接令牌的 user data，见 [`crate::auth`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display)]
+pub enum ClientCommand {
+    // 管理员用，服务器只接受 `ADMIN_IDS` 中的客户端发来的重置
     RoomReset { room_id: RoomId },
     ServerReset,
 
+    ClientJustLaunched,
+
+    ListRooms,
+    CreateRoom(RoomSettings),
+    /// 按房间号加入，房间设置了密码时需要带上密码
+    JoinRoom { code: String, password: Option<String> },
+    ReJoinRoom,
+    /// 离开房间，观战时停止观战
+    LeaveRoom,
+    /// 按房间号观战，不占座位，只能看到公开的信息
+    Spectate { code: String, password: Option<String> },
+    /// 房间内聊天，玩家和观战者都可以发送
+    Chat(ChatContent),
+    /// 快速匹配，和底分相同的玩家排队凑成一桌
+    QuickMatch { base_stake: i32 },
+    CancelMatch,
+
+    FillWithBots(BotDifficulty), // 用机器人补满空座位
+    /// 开启或取消托管
+    AutoPlay(bool),
+    Ready,
+    DealCardsDone,
+    CallCard(Card),
+    Blocking,
+    PlayCards(Vec<Card>),
+    Pass,
+}
+
+/// 服务器发给客户端的事件，房间内的游戏事件放在 [`ServerEvent::Game`] 中
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display)]
+pub enum ServerEvent {
     RoomError(RoomServiceError),
+    ActionRejected(GameError), // 客户端的指令没有通过校验，只发给该客户端
 
-    ClientJustLaunched(ClientId),
+    RoomList(Vec<RoomSummary>),
+    /// 发给正在浏览大厅的客户端，见 [`LobbyEvent`]
+    Lobby(LobbyEvent),
+    JoinRoomOk { room_id: RoomId, code: String }, // 用户需要在收到该事件后再初始化游戏状态并进入游戏页面
+    AskForRejoinRoom(RoomId),       // 重新加入房间事件
+    /// 快速匹配排队中，`position` 从 1 开始，排队人数变化时重新发送
+    MatchQueued { position: usize, estimated_wait_secs: Option<u64> },
+    MatchCancelled,
+    ReJoinRoomOk { room_id: RoomId, code: String },
+    /// 开始观战，之后收到的游戏事件与其他玩家看到的相同，但所有手牌都只有数量
+    SpectateOk { room_id: RoomId, code: String },
+    /// 观战的房间已经关闭
+    SpectateEnded,
+    Chat(ChatMessage),
+    /// 当前操作的截止时间，`None` 表示没有在等待的操作
+    TurnDeadline(Option<TurnDeadline>),
 
-    IsInRoom(ClientId),
-    CreateRoom { player: Player },
-    JoinRoom { player: Player, room_id: RoomId },
-    JoinRoomOk { room_id: RoomId }, // 用户需要在收到该事件后再初始化游戏状态并进入游戏页面
-    SyncState(GameState),
+    Game(Box<GameEvent>),
+}
+
+/// 操作的截止时间，超时后服务器代替玩家执行，见 [`GameState::timeout_events`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnDeadline {
+    pub turn: PendingTurn,
+    /// 截止时间的 Unix 毫秒时间戳
+    pub deadline_ms: u64,
+    /// 发送时剩余的毫秒数，客户端用它计时，不受两端时钟偏差影响
+    pub remaining_ms: u64,
+    /// 这一步的总时长
+    pub total_ms: u64,
+}
+
+impl ClientCommand {
+    /// 把房间内的指令转换为游戏事件，执行的玩家为 `client_id`，座位从 `state` 中查找
+    pub fn to_game_event(&self, client_id: ClientId, state: &GameState) -> Result<GameEvent, GameError> {
+        let seat_index = || {
+            state
+                .get_player_seat_index_by_id(client_id)
+                .ok_or(GameError::NotSeated)
+        };
+        match self {
+            ClientCommand::Ready => Ok(GameEvent::Ready { client_id }),
+            ClientCommand::DealCardsDone => Ok(GameEvent::DealCardsDone(client_id)),
+            ClientCommand::CallCard(card) => Ok(GameEvent::CallCard {
+                seat_index: seat_index()?,
+                card: card.clone(),
+            }),
+            ClientCommand::Blocking => Ok(GameEvent::Blocking(seat_index()?)),
+            ClientCommand::PlayCards(cards) => Ok(GameEvent::PlayCards(seat_index()?, cards.clone())),
+            ClientCommand::Pass => Ok(GameEvent::Pass(seat_index()?)),
+            ClientCommand::AutoPlay(enabled) => Ok(GameEvent::AutoPlay {
+                seat_index: seat_index()?,
+                enabled: *enabled,
+            }),
+            _ => Err(GameError::InvalidEvent),
+        }
+    }
+}
 
-    // 重新加入房间事件
-    AskForRejoinRoom(RoomId),
+impl From<GameEvent> for ServerEvent {
+    fn from(event: GameEvent) -> Self {
+        ServerEvent::Game(Box::new(event))
+    }
+}
 
-    ReJoinRoom { player: Player },
-    ReJoinRoomOk { room_id: RoomId},
+/// 房间内的游戏事件，由服务器产生，经过 [`Reducer`](crate::Reducer) 校验后应用到每一端的 [`GameState`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
+pub enum GameEvent {
+    SyncState(GameState),
 
     PlayerDisconnected(ClientId),
     PlayerConnected(ClientId),
     PlayerLeave(ClientId),
+    /// 开启或取消托管，玩家断线超过一段时间后服务器自动开启
+    AutoPlay { seat_index: usize, enabled: bool },
 
     AssignSeats { player: Player, seat_index: usize },
+    /// 玩家入座后按服务器保存的资料设置金币，见 [`crate::profile`]
+    SetCoins { seat_index: usize, coins: i32 },
     Ready { client_id: ClientId },
 
     ToDealCardStage,
     DealCards { client_id: ClientId, cards: Vec<Card>},
+    DealHiddenCards { client_id: ClientId, count: usize }, // 发给其他玩家的牌，只同步数量
     DealCardsDone(ClientId),
 
     ToCallCardStage(usize),
@@ -53,5 +149,84 @@ pub enum GameEvent {
     PlayCards(usize, Vec<Card>),
     Pass(usize),
 
-    GameEnd(Vec<(usize, i32)>)
+    GameEnd(GameResult)
+}
+
+impl GameEvent {
+    /// 发送给 `recipient` 的事件：其他玩家的手牌替换为数量，同步的状态替换为该玩家的视角
+    pub fn view_for(&self, recipient: ClientId) -> GameEvent {
+        match self {
+            GameEvent::DealCards { client_id, cards } if *client_id != recipient => {
+                GameEvent::DealHiddenCards {
+                    client_id: *client_id,
+                    count: cards.len(),
+                }
+            },
+            GameEvent::SyncState(state) => {
+                let view = match state.get_player_seat_index_by_id(recipient) {
+                    Some(seat_index) => state.view_for(seat_index),
+                    None => state.public_view(),
+                };
+                GameEvent::SyncState(view)
+            },
+            _ => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reducer;
+    use crate::cards::{CardValue, Suit};
+
+    #[test]
+    fn test_deal_cards_view() {
+        let cards = vec![Card::new(Suit::Spades, CardValue::Seven); 13];
+        let event = GameEvent::DealCards { client_id: 1, cards };
+
+        assert_eq!(event.view_for(1), event);
+        assert_eq!(
+            event.view_for(2),
+            GameEvent::DealHiddenCards { client_id: 1, count: 13 }
+        );
+    }
+
+    #[test]
+    fn test_spectator_view() {
+        let cards = vec![Card::new(Suit::Spades, CardValue::Seven); 13];
+        let mut state = GameState::default();
+        state.assign_seat(Player { id: 1, name: "1".to_string(), avatar: None }, 0);
+        state.reduce(&GameEvent::DealCards { client_id: 1, cards: cards.clone() });
+
+        // 观战者不在座位上，看不到任何手牌
+        let spectator = 9;
+        assert_eq!(
+            GameEvent::DealCards { client_id: 1, cards }.view_for(spectator),
+            GameEvent::DealHiddenCards { client_id: 1, count: 13 }
+        );
+        let GameEvent::SyncState(view) = GameEvent::SyncState(state).view_for(spectator) else {
+            panic!("SyncState expected");
+        };
+        let seat = &view.get_seats()[0];
+        assert!(seat.hands.is_empty());
+        assert_eq!(seat.hands_count(), 13);
+    }
+
+    #[test]
+    fn test_command_actor_from_connection() {
+        let mut state = GameState::default();
+        state.assign_seat(Player { id: 7, name: "7".to_string(), avatar: None }, 2);
+
+        assert_eq!(ClientCommand::Pass.to_game_event(7, &state), Ok(GameEvent::Pass(2)));
+        assert_eq!(
+            ClientCommand::Ready.to_game_event(7, &state),
+            Ok(GameEvent::Ready { client_id: 7 })
+        );
+        assert_eq!(ClientCommand::Blocking.to_game_event(8, &state), Err(GameError::NotSeated));
+        assert_eq!(
+            ClientCommand::ServerReset.to_game_event(7, &state),
+            Err(GameError::InvalidEvent)
+        );
+    }
 }
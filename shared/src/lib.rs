@@ -1,12 +1,20 @@
 use serde::{Deserialize, Serialize};
+pub mod auth;
+pub mod chat;
 pub mod event;
 pub mod cards;
 pub mod the_hidden_card;
 pub mod error;
+pub mod lobby;
+pub mod profile;
+pub mod replay;
 
 pub(crate) type ClientId = u64;
 pub(crate) type RoomId = u64;
 
+/// 客户端与服务器的协议版本，事件结构不兼容时需要修改
+pub const PROTOCOL_ID: u64 = 20;
+
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
@@ -21,6 +29,6 @@ pub trait Reducer<E, Er> {
 
     fn dispatch(&mut self, event: &E) -> Result<(), Er>;
 
-    fn validate(&self, event: &E) -> bool;
+    fn validate(&self, event: &E) -> Result<(), Er>;
 }
 
@@ -0,0 +1,236 @@
+//! ### 大厅
+//! 创建房间时的设置、房间号和房间列表。
+//!
+//! 每个房间有一个 6 位房间号，用于分享和加入，房间号去掉了容易看错的 `0 O 1 I`。
+//! 公开房间会出现在房间列表中，私密房间只能通过房间号加入，设置了密码的房间加入时需要密码。
+//! 观战同样按房间号进入，需要同样的密码。
+//! 正在浏览大厅的客户端拿到房间列表后，服务器用 [`LobbyEvent`] 推送之后的变化。
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::RoomId;
+use crate::error::RoomServiceError;
+use crate::the_hidden_card::prelude::RuleSet;
+
+/// 房间号长度
+pub const ROOM_CODE_LEN: usize = 6;
+/// 底分上限
+pub const MAX_BASE_STAKE: i32 = 100;
+/// 密码最大字符数
+pub const MAX_PASSWORD_CHARS: usize = 16;
+/// 观战延迟上限
+pub const MAX_SPECTATOR_DELAY_SECS: u64 = 300;
+
+const ROOM_CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// 随机生成一个房间号，调用方负责检查是否与已有房间重复
+pub fn random_room_code() -> String {
+    room_code_with(&mut rand::rng())
+}
+
+fn room_code_with(rng: &mut impl Rng) -> String {
+    (0..ROOM_CODE_LEN)
+        .map(|_| ROOM_CODE_CHARS[rng.random_range(0..ROOM_CODE_CHARS.len())] as char)
+        .collect()
+}
+
+/// 玩家输入的房间号，忽略大小写和首尾空白
+pub fn normalize_room_code(code: &str) -> String {
+    code.trim().to_ascii_uppercase()
+}
+
+/// 创建房间时的设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomSettings {
+    /// 私密房间不出现在房间列表中
+    pub private: bool,
+    pub password: Option<String>,
+    /// 底分，见 [`GameState::base`](crate::the_hidden_card::state::GameState::base)
+    pub base_stake: i32,
+    /// `None` 使用服务器的默认规则
+    pub rules: Option<RuleSet>,
+    /// 观战者看到的牌局比实际晚的秒数，0 表示实时观战
+    pub spectator_delay_secs: u64,
+}
+
+impl Default for RoomSettings {
+    fn default() -> Self {
+        Self {
+            private: false,
+            password: None,
+            base_stake: 1,
+            rules: None,
+            spectator_delay_secs: 0,
+        }
+    }
+}
+
+impl RoomSettings {
+    pub fn validate(&self) -> Result<(), RoomServiceError> {
+        if !(1..=MAX_BASE_STAKE).contains(&self.base_stake) {
+            return Err(RoomServiceError::InvalidSettings);
+        }
+        let invalid_password = |password: &String| {
+            password.is_empty() || password.chars().count() > MAX_PASSWORD_CHARS
+        };
+        if self.password.as_ref().is_some_and(invalid_password)
+            || self.spectator_delay_secs > MAX_SPECTATOR_DELAY_SECS
+        {
+            return Err(RoomServiceError::InvalidSettings);
+        }
+        if let Some(rules) = &self.rules {
+            rules.validate().map_err(|_| RoomServiceError::InvalidSettings)?;
+        }
+        Ok(())
+    }
+
+    /// 密码为空时任何人都可以加入
+    pub fn check_password(&self, password: Option<&str>) -> Result<(), RoomServiceError> {
+        match &self.password {
+            Some(expected) if Some(expected.as_str()) != password => Err(RoomServiceError::WrongPassword),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 房间列表中的一项，只包含公开房间
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomSummary {
+    pub room_id: RoomId,
+    pub code: String,
+    /// 已入座玩家的名字，按座位顺序
+    pub players: Vec<String>,
+    pub has_password: bool,
+    pub base_stake: i32,
+    pub rules: RuleSet,
+    /// 牌局是否已经开始
+    pub in_game: bool,
+    pub spectators: usize,
+}
+
+impl RoomSummary {
+    pub fn is_full(&self) -> bool {
+        self.players.len() >= 4
+    }
+}
+
+/// 大厅中房间的变化
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LobbyEvent {
+    Opened(RoomSummary),
+    Updated(RoomSummary),
+    Closed(RoomId),
+}
+
+impl LobbyEvent {
+    /// 比较前后两份房间列表，得到需要推送的变化
+    pub fn diff(old: &[RoomSummary], new: &[RoomSummary]) -> Vec<LobbyEvent> {
+        let closed = old
+            .iter()
+            .filter(|room| !new.iter().any(|other| other.room_id == room.room_id))
+            .map(|room| LobbyEvent::Closed(room.room_id));
+        let changed = new.iter().filter_map(|room| {
+            match old.iter().find(|other| other.room_id == room.room_id) {
+                None => Some(LobbyEvent::Opened(room.clone())),
+                Some(other) if other != room => Some(LobbyEvent::Updated(room.clone())),
+                Some(_) => None,
+            }
+        });
+        closed.chain(changed).collect()
+    }
+
+    /// 把变化应用到客户端的房间列表，列表按 `room_id` 排列
+    pub fn apply(&self, rooms: &mut Vec<RoomSummary>) {
+        match self {
+            LobbyEvent::Opened(room) | LobbyEvent::Updated(room) => {
+                match rooms.iter_mut().find(|other| other.room_id == room.room_id) {
+                    Some(other) => *other = room.clone(),
+                    None => {
+                        rooms.push(room.clone());
+                        rooms.sort_by_key(|room| room.room_id);
+                    },
+                }
+            },
+            LobbyEvent::Closed(room_id) => rooms.retain(|room| room.room_id != *room_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_room_code() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let code = room_code_with(&mut rng);
+        assert_eq!(code.len(), ROOM_CODE_LEN);
+        assert!(code.bytes().all(|c| ROOM_CODE_CHARS.contains(&c)));
+        assert_eq!(normalize_room_code(&format!(" {} ", code.to_lowercase())), code);
+    }
+
+    fn summary(room_id: RoomId, players: usize) -> RoomSummary {
+        RoomSummary {
+            room_id,
+            code: format!("ROOM{:02}", room_id),
+            players: (0..players).map(|index| index.to_string()).collect(),
+            has_password: false,
+            base_stake: 1,
+            rules: RuleSet::default(),
+            in_game: false,
+            spectators: 0,
+        }
+    }
+
+    #[test]
+    fn test_lobby_event_diff() {
+        let old = vec![summary(0, 1), summary(1, 2)];
+        let new = vec![summary(1, 3), summary(2, 1)];
+        let events = LobbyEvent::diff(&old, &new);
+        assert_eq!(
+            events,
+            vec![
+                LobbyEvent::Closed(0),
+                LobbyEvent::Updated(summary(1, 3)),
+                LobbyEvent::Opened(summary(2, 1)),
+            ]
+        );
+
+        // 客户端依次应用后与服务器的列表一致
+        let mut rooms = old.clone();
+        events.iter().for_each(|event| event.apply(&mut rooms));
+        assert_eq!(rooms, new);
+        assert!(LobbyEvent::diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_room_settings() {
+        assert_eq!(RoomSettings::default().validate(), Ok(()));
+        assert_eq!(RoomSettings::default().check_password(Some("any")), Ok(()));
+
+        let settings = RoomSettings { base_stake: 0, ..Default::default() };
+        assert_eq!(settings.validate(), Err(RoomServiceError::InvalidSettings));
+
+        let settings = RoomSettings {
+            rules: Some(RuleSet { bomb_multiplier: 0, ..Default::default() }),
+            ..Default::default()
+        };
+        assert_eq!(settings.validate(), Err(RoomServiceError::InvalidSettings));
+
+        let mut rules = RuleSet::default();
+        rules.abandon.compensation = -1;
+        let settings = RoomSettings { rules: Some(rules), ..Default::default() };
+        assert_eq!(settings.validate(), Err(RoomServiceError::InvalidSettings));
+
+        let settings = RoomSettings { spectator_delay_secs: 600, ..Default::default() };
+        assert_eq!(settings.validate(), Err(RoomServiceError::InvalidSettings));
+
+        let settings = RoomSettings { password: Some("1234".to_string()), ..Default::default() };
+        assert_eq!(settings.validate(), Ok(()));
+        assert_eq!(settings.check_password(Some("1234")), Ok(()));
+        assert_eq!(settings.check_password(None), Err(RoomServiceError::WrongPassword));
+    }
+}
@@ -0,0 +1,138 @@
+//! ### 玩家资料
+//! 服务器为每个玩家保存名字、头像、金币和累计统计，重启后保留。
+//!
+//! 玩家入座时金币通过 [`GameEvent::SetCoins`] 同步到座位，每手牌结算后按 [`GameResult`]
+//! 写入得分，并由 [`PlayerStats::record_hand`] 更新统计。
+
+use serde::{Deserialize, Serialize};
+
+use crate::event::GameEvent;
+use crate::the_hidden_card::prelude::{EndGameReason, GameResult, Outcome};
+use crate::the_hidden_card::state::{GameMode, GameState};
+use crate::{ClientId, Player};
+
+/// 累计统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerStats {
+    pub hands_played: u32,
+    /// 叫牌模式下赢的手数
+    pub hidden_allies_wins: u32,
+    /// 包牌模式下赢的手数，包括作为另一方赢
+    pub one_vs_three_wins: u32,
+    /// 包牌次数
+    pub block_attempts: u32,
+    /// 包牌成功次数
+    pub block_successes: u32,
+    /// 打出的炸弹数，见 [`Combination::is_boom`](crate::the_hidden_card::prelude::Combination::is_boom)
+    pub bombs_played: u32,
+}
+
+impl PlayerStats {
+    /// 按刚结算的这一手牌更新座位 `seat_index` 的统计，炸弹数从 `state` 的事件记录中统计。
+    /// 有玩家中途离开时只记手数，得到的补偿不算赢
+    pub fn record_hand(&mut self, seat_index: usize, state: &GameState, result: &GameResult) {
+        self.hands_played += 1;
+        let won = matches!(result.reason, EndGameReason::PlayerWon { .. }) && result.score_of(seat_index) > 0;
+        match result.mode {
+            GameMode::HiddenAllies { .. } => {
+                self.hidden_allies_wins += won as u32;
+            },
+            GameMode::OneVsThree(block_index) => {
+                self.one_vs_three_wins += won as u32;
+                if block_index == seat_index {
+                    self.block_attempts += 1;
+                    self.block_successes += (result.outcome == Outcome::BlockWin) as u32;
+                }
+            },
+        }
+        let bombs = state.get_history().iter().filter(|event| match event {
+            GameEvent::PlayCards(index, cards) => {
+                *index == seat_index && state.rules.analyze(cards.clone()).is_boom()
+            },
+            _ => false,
+        });
+        self.bombs_played += bombs.count() as u32;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    pub id: ClientId,
+    pub name: String,
+    pub avatar: Option<String>,
+    pub coins: i32,
+    #[serde(default)]
+    pub stats: PlayerStats,
+}
+
+impl PlayerProfile {
+    pub fn new(player: &Player) -> Self {
+        Self {
+            id: player.id,
+            name: player.name.clone(),
+            avatar: player.avatar.clone(),
+            coins: 0,
+            stats: PlayerStats::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::{Card, CardValue, Suit};
+
+    #[test]
+    fn test_record_hand() {
+        let mut state = GameState::default();
+        let bomb = [Suit::Spades, Suit::Hearts, Suit::Clubs]
+            .map(|suit| Card::new(suit, CardValue::Nine))
+            .to_vec();
+        state.add_history(GameEvent::PlayCards(0, bomb.clone()));
+        state.add_history(GameEvent::PlayCards(1, bomb[..1].to_vec()));
+        state.add_history(GameEvent::PlayCards(0, bomb));
+
+        let result = GameResult {
+            reason: EndGameReason::PlayerWon { winner: 0 },
+            mode: GameMode::OneVsThree(0),
+            teams: [vec![0], vec![1, 2, 3]],
+            finish_order: vec![0, 1, 2, 3],
+            team_points: [0, 0],
+            outcome: Outcome::BlockWin,
+            base: 1,
+            multiplier: 3,
+            spring: false,
+            scores: vec![(0, 9), (1, -3), (2, -3), (3, -3)],
+        };
+        let mut blocker = PlayerStats::default();
+        blocker.record_hand(0, &state, &result);
+        assert_eq!(
+            blocker,
+            PlayerStats {
+                hands_played: 1,
+                one_vs_three_wins: 1,
+                block_attempts: 1,
+                block_successes: 1,
+                bombs_played: 2,
+                ..Default::default()
+            }
+        );
+
+        let mut other = PlayerStats::default();
+        other.record_hand(1, &state, &result);
+        assert_eq!(other, PlayerStats { hands_played: 1, ..Default::default() });
+
+        // 中途离开得到的补偿不算赢
+        let result = GameResult {
+            reason: EndGameReason::PlayerLeft { player_id: 9 },
+            mode: GameMode::HiddenAllies { caller: 0, callee: Some(1), card: Card::new(Suit::Hearts, CardValue::Two) },
+            outcome: Outcome::Abandoned,
+            ..result
+        };
+        let mut stats = PlayerStats::default();
+        stats.record_hand(0, &state, &result);
+        assert_eq!(stats.hidden_allies_wins, 0);
+        assert_eq!(stats.hands_played, 1);
+    }
+}
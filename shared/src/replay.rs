@@ -0,0 +1,178 @@
+//! ### 牌局回放
+//! 每手牌结束后服务器把完整的事件记录（包含所有玩家的手牌）保存为回放文件，
+//! 客户端带上游客凭证从 HTTP 服务器的 `/replays` 获取自己参与过的回放列表，下载后逐步查看。
+//!
+//! 文件格式：
+//! 1. 4 字节魔数 `HCRP`
+//! 2. 2 字节格式版本（小端）
+//! 3. bincode 编码的 [`Replay`]
+//!
+//! 格式版本放在 bincode 数据之前，读取时先检查版本，不兼容的文件不会尝试解码。
+
+use bincode::config;
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+use crate::event::GameEvent;
+use crate::the_hidden_card::rules::RuleSet;
+use crate::the_hidden_card::state::GameState;
+use crate::{ClientId, PROTOCOL_ID, Player};
+
+const MAGIC: &[u8; 4] = b"HCRP";
+
+/// 回放文件格式版本，[`Replay`] 结构不兼容时需要修改
+pub const REPLAY_FORMAT_VERSION: u16 = 8;
+
+/// 回放文件扩展名
+pub const REPLAY_FILE_EXTENSION: &str = "replay";
+
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum ReplayError {
+    InvalidFormat,
+    UnsupportedVersion(u16),
+    Encode(String),
+    Decode(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    /// 录制时的协议版本，见 [`PROTOCOL_ID`]
+    pub protocol_id: u64,
+    /// 发牌种子
+    pub seed: Option<u64>,
+    /// 按座位排列的玩家
+    pub players: [Option<Player>; 4],
+    /// 底分
+    pub base: i32,
+    /// 这一手牌使用的规则
+    pub rules: RuleSet,
+}
+
+impl ReplayHeader {
+    /// `player_id` 是否坐在这一手牌的座位上
+    pub fn has_player(&self, player_id: ClientId) -> bool {
+        self.players.iter().flatten().any(|player| player.id == player_id)
+    }
+}
+
+/// HTTP 服务器 `/replays` 列表中的一项，带上游客凭证从 `/replays/{name}` 下载
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    /// 不含扩展名的文件名
+    pub name: String,
+    pub header: ReplayHeader,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    /// 这一手牌的事件记录，以状态快照开头，见 [`GameState::add_history`]
+    pub events: Vec<GameEvent>,
+}
+
+impl Replay {
+    /// 用 `state` 当前记录的这一手牌生成回放
+    pub fn new(state: &GameState, seed: Option<u64>) -> Self {
+        let players = state.get_seats().clone().map(|seat| seat.player);
+        Self {
+            header: ReplayHeader {
+                protocol_id: PROTOCOL_ID,
+                seed,
+                players,
+                base: state.base,
+                rules: state.rules.clone(),
+            },
+            events: state.get_history().to_vec(),
+        }
+    }
+
+    /// 回放的步数，第 0 步为初始状态，第 `len` 步为所有事件应用后的状态
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// 应用前 `step` 个事件后的状态
+    pub fn state_at(&self, step: usize) -> GameState {
+        GameState::replay(&self.events[..step.min(self.len())])
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, ReplayError> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&REPLAY_FORMAT_VERSION.to_le_bytes());
+        let payload = bincode::serde::encode_to_vec(self, config::standard())
+            .map_err(|err| ReplayError::Encode(err.to_string()))?;
+        bytes.extend(payload);
+        Ok(bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let (magic, rest) = bytes.split_at_checked(MAGIC.len()).ok_or(ReplayError::InvalidFormat)?;
+        if magic != MAGIC {
+            return Err(ReplayError::InvalidFormat);
+        }
+
+        let (version, payload) = rest.split_at_checked(2).ok_or(ReplayError::InvalidFormat)?;
+        let version = u16::from_le_bytes([version[0], version[1]]);
+        if version != REPLAY_FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let (replay, _) = bincode::serde::decode_from_slice(payload, config::standard())
+            .map_err(|err| ReplayError::Decode(err.to_string()))?;
+        Ok(replay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reducer;
+    use crate::cards::deal;
+    use crate::the_hidden_card::state::Stage;
+
+    fn recorded_state() -> GameState {
+        let mut state = GameState::default();
+        let mut events = Vec::new();
+        for index in 0..4 {
+            let player = Player { id: index, name: index.to_string(), avatar: None };
+            events.push(GameEvent::AssignSeats { player, seat_index: index as usize });
+        }
+        events.push(GameEvent::ToDealCardStage);
+        for (index, cards) in deal(9, 0).into_iter().enumerate() {
+            events.push(GameEvent::DealCards { client_id: index as u64, cards });
+        }
+        for event in events {
+            state.add_history(event.clone());
+            state.reduce(&event);
+        }
+        state
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let replay = Replay::new(&recorded_state(), Some(9));
+        let bytes = replay.encode().unwrap();
+        assert_eq!(Replay::decode(&bytes), Ok(replay.clone()));
+
+        assert_eq!(replay.header.seed, Some(9));
+        assert_eq!(replay.header.rules, RuleSet::default());
+        assert!(replay.header.players.iter().all(|player| player.is_some()));
+        assert_eq!(replay.state_at(0), GameState::default());
+        assert_eq!(replay.state_at(2).stage, Stage::DealCards);
+        assert!(replay.state_at(replay.len()).get_seats().iter().all(|seat| seat.hands.len() == 13));
+    }
+
+    #[test]
+    fn test_decode_rejects_other_files() {
+        let mut bytes = Replay::new(&recorded_state(), None).encode().unwrap();
+        assert_eq!(Replay::decode(b"HC"), Err(ReplayError::InvalidFormat));
+        assert_eq!(Replay::decode(b"not a replay"), Err(ReplayError::InvalidFormat));
+
+        bytes[MAGIC.len()] = 9;
+        assert_eq!(Replay::decode(&bytes), Err(ReplayError::UnsupportedVersion(9)));
+    }
+}
@@ -1,5 +1,27 @@
-#[derive(Debug, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+/// 事件校验失败的原因，服务器通过 [`ServerEvent::ActionRejected`](crate::event::ServerEvent::ActionRejected) 发回给客户端
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Display)]
 pub enum GameError {
+    #[strum(to_string = "无效操作")]
     InvalidEvent,
+    #[strum(to_string = "当前阶段不能这样做")]
+    WrongStage,
+    #[strum(to_string = "还没轮到你")]
+    NotYourTurn,
+    #[strum(to_string = "玩家不在座位上")]
+    NotSeated,
+    #[strum(to_string = "座位已经有人")]
+    SeatTaken,
+    #[strum(to_string = "已经准备")]
+    AlreadyReady,
+    #[strum(to_string = "不能叫这张牌")]
+    NotCallable,
+    #[strum(to_string = "无效牌型")]
     InvalidCombination,
-}
\ No newline at end of file
+    #[strum(to_string = "牌型太弱")]
+    TooWeak,
+    #[strum(to_string = "手牌中没有这些牌")]
+    CardsNotInHand,
+}
@@ -0,0 +1,129 @@
+//! ### 规则引擎不变量测试
+//! 用随机的合法动作把整局游戏打到结束，每个事件之后检查状态的不变量：
+//! 1. 52 张牌在手牌和已出的牌之间守恒
+//! 2. `current_player_seat` 不会指向已经出完的座位
+//! 3. 出牌阶段在有限步内结束（`next_player` 不会卡死）
+//! 4. 三名玩家出完后 `game_end_check` 一定给出结果
+//! 5. 每局得分之和为 0，结算结果覆盖所有座位
+
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::cards::{Deck, deal};
+use crate::event::GameEvent;
+use crate::the_hidden_card::result::GameResult;
+use crate::the_hidden_card::state::{GameState, Stage};
+use crate::{Player, Reducer};
+
+/// 随机对局的数量
+const GAMES: u64 = 300;
+
+/// 出牌阶段的最大步数，每步至少出一张牌或者不要，正常对局远小于这个数
+const MAX_PLAY_STEPS: usize = 2000;
+
+/// 包牌的概率，覆盖两种游戏模式
+const BLOCK_PROBABILITY: f64 = 0.2;
+
+fn apply(state: &mut GameState, event: GameEvent) {
+    assert_eq!(state.validate(&event), Ok(()), "rejected {event}");
+    state.reduce(&event);
+    check_invariants(state);
+}
+
+fn check_invariants(state: &GameState) {
+    // 1. 发牌完成后牌数守恒，且没有重复的牌
+    if matches!(state.stage, Stage::CallCard(_) | Stage::PlayCards | Stage::Ended(_)) {
+        let mut cards: Vec<_> = state.get_seats().iter().flat_map(|seat| seat.hands.iter()).collect();
+        cards.extend(state.played_cards.iter());
+        assert_eq!(cards.len(), 52);
+        let unique: HashSet<_> = cards.iter().collect();
+        let deck = Deck::new();
+        assert_eq!(unique.len(), 52);
+        assert!(unique.into_iter().all(|card| deck.get().contains(card)));
+    }
+
+    // 2. 当前出牌的座位还有手牌
+    if state.stage == Stage::PlayCards {
+        let current = state.current_player_seat.expect("no current player");
+        assert!(!state.finished_order().contains(&current), "seat {current} already finished");
+        assert!(state.get_seats()[current].hands_count() > 0);
+    }
+}
+
+/// 四个座位坐满并发好牌，进入叫牌阶段
+fn dealt_state(seed: u64) -> GameState {
+    let mut state = GameState::default();
+    for seat_index in 0..4 {
+        let player = Player { id: seat_index as u64, name: seat_index.to_string(), avatar: None };
+        apply(&mut state, GameEvent::AssignSeats { player, seat_index });
+        apply(&mut state, GameEvent::Ready { client_id: seat_index as u64 });
+    }
+    apply(&mut state, GameEvent::ToDealCardStage);
+    for (index, cards) in deal(seed, 0).into_iter().enumerate() {
+        apply(&mut state, GameEvent::DealCards { client_id: index as u64, cards });
+        apply(&mut state, GameEvent::DealCardsDone(index as u64));
+    }
+    let caller = state.get_caller_index().unwrap();
+    apply(&mut state, GameEvent::ToCallCardStage(caller));
+    state
+}
+
+/// 随机包牌或叫牌，然后随机出牌直到游戏结束，返回结算结果
+fn play_random_game(seed: u64) -> GameResult {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut state = dealt_state(seed);
+    let Stage::CallCard(caller) = state.stage else {
+        panic!("not in call card stage");
+    };
+
+    if rng.random_bool(BLOCK_PROBABILITY) {
+        apply(&mut state, GameEvent::Blocking(rng.random_range(0..4)));
+    } else {
+        let callable = state.callable_cards(caller).unwrap();
+        let card = callable.choose(&mut rng).unwrap().clone();
+        apply(&mut state, GameEvent::CallCard { seat_index: caller, card });
+    }
+
+    for _ in 0..MAX_PLAY_STEPS {
+        let current = state.current_player_seat.unwrap();
+        let mut moves: Vec<_> = state
+            .legal_moves(current)
+            .into_iter()
+            .map(|combo| GameEvent::PlayCards(current, combo.to_vec_cards()))
+            .collect();
+        if state.last_played_cards.is_some() {
+            moves.push(GameEvent::Pass(current));
+        }
+        apply(&mut state, moves.choose(&mut rng).expect("no legal move").clone());
+
+        // 4. 三名玩家出完后一定有结果
+        let three_out = state.finished_order().len() >= 3;
+        match state.game_end_check() {
+            Some(Stage::Ended(Some(result))) => {
+                apply(&mut state, GameEvent::GameEnd(result.clone()));
+                return result;
+            },
+            Some(stage) => panic!("unexpected end stage {stage:?}"),
+            None => assert!(!three_out, "three players out without a result"),
+        }
+    }
+    panic!("game did not end within {MAX_PLAY_STEPS} steps");
+}
+
+#[test]
+fn test_random_games_keep_invariants() {
+    for seed in 0..GAMES {
+        let result = play_random_game(seed);
+        let scores = &result.scores;
+
+        // 5. 得分之和为 0，每个座位恰好结算一次
+        assert_eq!(scores.iter().map(|(_, score)| score).sum::<i32>(), 0, "seed {seed}");
+        let seats: HashSet<_> = scores.iter().map(|(index, _)| *index).collect();
+        assert_eq!(seats.len(), 4, "seed {seed}");
+        assert_eq!(scores.iter().map(|(index, _)| *index).collect::<Vec<_>>(), result.finish_order);
+        assert_eq!(result.teams.iter().map(Vec::len).sum::<usize>(), 4, "seed {seed}");
+    }
+}
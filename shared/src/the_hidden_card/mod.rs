@@ -1,10 +1,22 @@
 pub mod state;
 pub mod reducer;
+pub mod rules;
+pub mod result;
+pub mod strategy;
+pub mod simulation;
 mod combination;
 mod error;
+mod moves;
+#[cfg(test)]
+mod invariants;
 
 pub mod prelude {
     pub use crate::the_hidden_card::combination::{Combination, HandAnalyzer};
+    pub use crate::the_hidden_card::error::GameError;
+    pub use crate::the_hidden_card::moves::legal_moves;
+    pub use crate::the_hidden_card::result::{EndGameReason, GameResult, Outcome};
+    pub use crate::the_hidden_card::rules::{AbandonPolicy, RuleSet};
     pub use crate::the_hidden_card::state::{GameState, Stage};
+    pub use crate::the_hidden_card::strategy::{BotDifficulty, PlayerView, Strategy};
     pub use crate::the_hidden_card::reducer;
 }
\ No newline at end of file
@@ -0,0 +1,210 @@
+//! ### 出牌候选
+//! 根据手牌和桌面上一手牌，列出所有可以打出的牌型，供提示、机器人和自动出牌使用。
+
+use crate::cards::{Card, CardNumericValue, CardValue};
+use crate::the_hidden_card::combination::Combination;
+
+/// 顺子最短长度
+const MIN_STRAIGHT_LEN: u8 = 3;
+
+/// 按点数分组的手牌，下标为 [`CardNumericValue::int`] 的值（1 ~ 13）
+struct ValueGroups([Vec<Card>; 14]);
+
+impl ValueGroups {
+    fn from_hands(hands: &[Card]) -> Self {
+        let mut groups: [Vec<Card>; 14] = Default::default();
+        for card in hands {
+            groups[card.value.int() as usize].push(card.clone());
+        }
+        Self(groups)
+    }
+
+    fn count(&self, value: u8) -> usize {
+        self.0[value as usize].len()
+    }
+
+    /// 取某个点数的前 `n` 张牌
+    fn take(&self, value: u8, n: usize) -> Option<&[Card]> {
+        let group = &self.0[value as usize];
+        if group.len() >= n {
+            Some(&group[..n])
+        } else {
+            None
+        }
+    }
+
+    /// 从 `start` 开始连续 `len` 个点数，每个点数取 `n` 张牌，最大牌不能大过 A
+    fn take_run(&self, start: u8, len: u8, n: usize) -> Option<Vec<Card>> {
+        let end = start + len - 1;
+        if end > CardValue::Ace.int() {
+            return None;
+        }
+        let mut cards = Vec::with_capacity(len as usize * n);
+        for value in start..=end {
+            cards.extend_from_slice(self.take(value, n)?);
+        }
+        Some(cards)
+    }
+}
+
+/// 列出手牌中所有可以出的牌型，`last` 为桌面上需要压过的牌型，`None` 表示自由出牌。
+///
+/// 牌型大小与花色无关，所以同样点数的组合只给出一个（取手牌中靠前的牌）。
+///
+/// 返回顺序：先是普通牌型（单张、对子、顺子按长度），每类从小到大；最后是炸弹，从小到大。
+/// 返回的每个牌型都能通过 [`Combination::gt`] 的检查。
+pub fn legal_moves(hands: &[Card], last: Option<&Combination>) -> Vec<Combination> {
+    let groups = ValueGroups::from_hands(hands);
+    let values = CardValue::Three.int()..=CardValue::Two.int();
+    let mut moves = Vec::new();
+
+    // 单张
+    for value in values.clone() {
+        if let Some(cards) = groups.take(value, 1) {
+            moves.push(cards.to_vec());
+        }
+    }
+
+    // 对子
+    for value in values.clone() {
+        if let Some(cards) = groups.take(value, 2) {
+            moves.push(cards.to_vec());
+        }
+    }
+
+    // 顺子，按长度从短到长
+    for len in MIN_STRAIGHT_LEN..=CardValue::Ace.int() {
+        for start in CardValue::Three.int()..=CardValue::Ace.int() {
+            if let Some(cards) = groups.take_run(start, len, 1) {
+                moves.push(cards);
+            }
+        }
+    }
+
+    // 三张炸弹
+    for value in values.clone() {
+        if let Some(cards) = groups.take(value, 3) {
+            moves.push(cards.to_vec());
+        }
+    }
+
+    // 板板炮
+    for start in CardValue::Three.int()..=CardValue::Ace.int() {
+        if let Some(cards) = groups.take_run(start, 3, 2) {
+            moves.push(cards);
+        }
+    }
+
+    // 四张炸弹
+    for value in values {
+        if groups.count(value) == 4 {
+            moves.push(groups.take(value, 4).unwrap().to_vec());
+        }
+    }
+
+    moves
+        .into_iter()
+        .map(Combination::analyze)
+        .filter(|combo| *combo != Combination::Invalid)
+        .filter(|combo| match last {
+            Some(last) => combo.gt(last),
+            None => true,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::CardValue::*;
+    use crate::cards::Suit::*;
+    use crate::cards::{Card, Deck};
+
+    fn hands() -> Vec<Card> {
+        vec![
+            Card::new(Spades, Three),
+            Card::new(Hearts, Three),
+            Card::new(Spades, Four),
+            Card::new(Hearts, Four),
+            Card::new(Spades, Five),
+            Card::new(Hearts, Five),
+            Card::new(Clubs, Six),
+            Card::new(Spades, King),
+            Card::new(Spades, Two),
+            Card::new(Hearts, Two),
+            Card::new(Clubs, Two),
+            Card::new(Diamonds, Two),
+        ]
+    }
+
+    // 自由出牌时，列出所有牌型
+    #[test]
+    fn test_leading_moves() {
+        let moves = legal_moves(&hands(), None);
+
+        let singles = moves.iter().filter(|c| matches!(c, Combination::Single(_))).count();
+        let pairs = moves.iter().filter(|c| matches!(c, Combination::Pair(_))).count();
+        let straights: Vec<_> = moves
+            .iter()
+            .filter_map(|c| match c {
+                Combination::Straight(cards) => Some(cards.len()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(singles, 6); // 3 4 5 6 K 2
+        assert_eq!(pairs, 4); // 33 44 55 22
+        assert_eq!(straights, vec![3, 3, 4]); // 345 456 3456
+        assert!(moves.iter().any(|c| matches!(c, Combination::ThreeStraitPair(_))));
+        assert!(moves.iter().any(|c| matches!(c, Combination::ThreeOfAKind(_))));
+        assert!(moves.iter().any(|c| matches!(c, Combination::FourOfAKind(_))));
+
+        // 炸弹排在最后
+        let first_boom = moves.iter().position(|c| c.is_boom()).unwrap();
+        assert!(moves[first_boom..].iter().all(|c| c.is_boom()));
+    }
+
+    // 跟牌时只能出更大的同类牌型或者炸弹
+    #[test]
+    fn test_following_moves() {
+        let last = Combination::Pair([Card::new(Clubs, Four), Card::new(Diamonds, Four)]);
+        let moves = legal_moves(&hands(), Some(&last));
+
+        assert!(moves.iter().all(|c| c.gt(&last)));
+        assert!(matches!(&moves[0], Combination::Pair(cards) if cards[0].value == Five));
+        assert!(!moves.iter().any(|c| matches!(c, Combination::Single(_))));
+
+        let last = Combination::Straight(vec![
+            Card::new(Clubs, Three),
+            Card::new(Clubs, Four),
+            Card::new(Clubs, Five),
+            Card::new(Clubs, Six),
+        ]);
+        let moves = legal_moves(&hands(), Some(&last));
+        // 没有更大的四张顺子，只剩炸弹
+        assert!(moves.iter().all(|c| c.is_boom()));
+    }
+
+    // 没有牌可以压过时返回空
+    #[test]
+    fn test_no_moves() {
+        let hands = vec![Card::new(Spades, Three), Card::new(Hearts, Four)];
+        let last = Combination::Single(Card::new(Spades, Two));
+        assert!(legal_moves(&hands, Some(&last)).is_empty());
+    }
+
+    // 所有候选牌型都能通过出牌校验
+    #[test]
+    fn test_moves_are_valid() {
+        for seed in 0..8 {
+            let mut deck = Deck::new();
+            deck.shuffle_with_seed(seed);
+            let hands = deck.get()[..13].to_vec();
+            for combo in legal_moves(&hands, None) {
+                let cards = combo.to_vec_cards();
+                assert!(cards.iter().all(|card| hands.contains(card)), "seed {}", seed);
+                assert_eq!(Combination::analyze(cards), combo, "seed {}", seed);
+            }
+        }
+    }
+}
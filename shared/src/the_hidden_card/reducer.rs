@@ -1,9 +1,7 @@
-use log::{error, info};
 use tiny_bail::prelude::*;
 
 use crate::Reducer;
 use crate::event::GameEvent;
-use crate::the_hidden_card::error::GameError;
 use crate::the_hidden_card::prelude::*;
 use crate::the_hidden_card::state::Stage;
 
@@ -14,6 +12,9 @@ impl Reducer<GameEvent, GameError> for GameState {
             AssignSeats { player, seat_index } => {
                 self.assign_seat(player.clone(), seat_index.clone());
             },
+            SetCoins { seat_index, coins } => {
+                self.set_coins(*seat_index, *coins);
+            },
             Ready { client_id } => {
                 let Some(seat_index) = self.get_player_seat_index_by_id(client_id.clone()) else {
                     return;
@@ -27,6 +28,9 @@ impl Reducer<GameEvent, GameError> for GameState {
             DealCards { client_id, cards } => {
                 self.set_hands(client_id.clone(), cards.clone());
             },
+            DealHiddenCards { client_id, count } => {
+                self.set_hidden_hands(*client_id, *count);
+            },
             DealCardsDone(client_id) => {
                 let seat = r!(self.get_seat_mut_by_id(client_id.clone()));
                 seat.hands.sort_by(|a, b| b.cmp(a));
@@ -52,6 +56,10 @@ impl Reducer<GameEvent, GameError> for GameState {
                 seat.player_connected = false;
             },
             GameEnd(result) => {
+                // 春天的倍数在结算时才确定
+                self.multiplayer = result.multiplier;
+                // 游戏结束后公开队伍
+                self.mode = Some(result.mode.clone());
                 self.stage = Stage::Ended(Some(result.clone()));
                 self.apply_score_result(&result);
                 self.reset_ready_state();
@@ -59,77 +67,89 @@ impl Reducer<GameEvent, GameError> for GameState {
             PlayerConnected(client_id) => {
                 let seat = r!(self.get_seat_mut_by_id(client_id.clone()));
                 seat.player_connected = true;
+                // 重新连接后玩家收回控制
+                seat.auto_play = false;
+            },
+            AutoPlay { seat_index, enabled } => {
+                self.set_auto_play(*seat_index, *enabled);
+            },
+            PlayerLeave(client_id) => {
+                self.leave_seat(*client_id);
             },
             SyncState(state) => {
                 self.set_state_by_state(state);
             },
-            _ => {},
         }
     }
 
     fn dispatch(&mut self, event: &GameEvent) -> Result<(), GameError> {
-        if !self.validate(&event) {
-            return Err(GameError::InvalidEvent);
-        }
-        Ok(())
+        self.validate(event)
     }
 
-    fn validate(&self, event: &GameEvent) -> bool {
+    fn validate(&self, event: &GameEvent) -> Result<(), GameError> {
         use GameEvent::*;
         match event {
-            AssignSeats { player, seat_index } => self.seat_is_empty(seat_index.clone()),
+            AssignSeats { player: _, seat_index } => {
+                ensure(self.seat_is_empty(seat_index.clone()), GameError::SeatTaken)
+            },
+            SetCoins { seat_index, .. } => {
+                let seat = self.get_seats().get(*seat_index).ok_or(GameError::InvalidEvent)?;
+                ensure(seat.get_player().is_some(), GameError::NotSeated)
+            },
             Ready { client_id } => {
-                let Some(seat) = self.get_seat_by_id(client_id.clone()) else {
-                    return false;
-                };
-                if seat.ready {
-                    // 已经就绪，无须二次确认
-                    return false;
-                }
-                true
-            },
-            ToDealCardStage => matches!(self.stage, Stage::PreGame) || matches!(self.stage, Stage::Ended(_)),
-            DealCards { client_id, cards } => cards.len() == 13,
+                let seat = self.get_seat_by_id(client_id.clone()).ok_or(GameError::NotSeated)?;
+                // 已经就绪，无须二次确认
+                ensure(!seat.ready, GameError::AlreadyReady)
+            },
+            ToDealCardStage => ensure(
+                matches!(self.stage, Stage::PreGame) || matches!(self.stage, Stage::Ended(_)),
+                GameError::WrongStage,
+            ),
+            DealCards { client_id: _, cards } => ensure(cards.len() == 13, GameError::InvalidEvent),
+            DealHiddenCards { count, .. } => ensure(*count == 13, GameError::InvalidEvent),
             DealCardsDone(client_id) => {
-                let Some(seat) = self.get_seat_by_id(client_id.clone()) else {
-                    return false;
-                };
-                if seat.hands_ready {
-                    return false;
-                }
-                true
+                let seat = self.get_seat_by_id(client_id.clone()).ok_or(GameError::NotSeated)?;
+                ensure(!seat.hands_ready, GameError::InvalidEvent)
             },
             ToCallCardStage(index) => {
-                self.stage == Stage::DealCards && self.seat_hands_has_special_card(index.clone())
+                ensure(self.stage == Stage::DealCards, GameError::WrongStage)?;
+                ensure(self.seat_hands_has_special_card(index.clone()), GameError::InvalidEvent)
             },
-            Blocking(index) => {
-                matches!(self.stage, Stage::CallCard(_))
+            Blocking(_) => ensure(matches!(self.stage, Stage::CallCard(_)), GameError::WrongStage),
+            CallCard { seat_index, card } => {
+                ensure(matches!(self.stage, Stage::CallCard(_)), GameError::WrongStage)?;
+                ensure(self.stage == Stage::CallCard(seat_index.clone()), GameError::NotYourTurn)?;
+                ensure(self.is_callable_card(seat_index.clone(), card), GameError::NotCallable)
             },
-            CallCard { seat_index, card } => self.stage == Stage::CallCard(seat_index.clone()),
             PlayCards(seat_index, cards) => {
-                let pre_condition = matches!(self.stage, Stage::PlayCards)
-                    && Some(seat_index.clone()) == self.current_player_seat;
-                if !pre_condition {
-                    return false;
-                }
-                let Ok(_) = self.can_play_cards(cards) else {
-                    return false;
-                };
-                true
-            },
-            Pass(seat_index) => {
-                matches!(self.stage, Stage::PlayCards)
-                    && Some(seat_index.clone()) == self.current_player_seat
-            },
-            GameEnd(_) => {
-                true
-            }
-            PlayerDisconnected(_) => true,
-            SyncState(_) => true,
-            _ => {
-                error!(target: "Game state", "Not implement {}", event);
-                false
-            },
+                check_turn(self, *seat_index)?;
+                ensure(self.get_seats()[*seat_index].has_cards(cards), GameError::CardsNotInHand)?;
+                self.can_play_cards(cards).map(|_| ())
+            },
+            Pass(seat_index) => check_turn(self, *seat_index),
+            GameEnd(_) => Ok(()),
+            PlayerDisconnected(_) | PlayerConnected(_) => Ok(()),
+            AutoPlay { seat_index, enabled } => {
+                let seat = self.get_seats().get(*seat_index).ok_or(GameError::InvalidEvent)?;
+                ensure(seat.get_player().is_some(), GameError::NotSeated)?;
+                ensure(seat.auto_play != *enabled, GameError::InvalidEvent)
+            },
+            PlayerLeave(client_id) => self
+                .get_seat_by_id(*client_id)
+                .map(|_| ())
+                .ok_or(GameError::NotSeated),
+            SyncState(_) => Ok(()),
         }
     }
 }
+
+/// 条件不满足时返回 `err`
+fn ensure(condition: bool, err: GameError) -> Result<(), GameError> {
+    if condition { Ok(()) } else { Err(err) }
+}
+
+/// 出牌阶段轮到 `seat_index` 出牌
+fn check_turn(state: &GameState, seat_index: usize) -> Result<(), GameError> {
+    ensure(state.stage == Stage::PlayCards, GameError::WrongStage)?;
+    ensure(state.current_player_seat == Some(seat_index), GameError::NotYourTurn)
+}
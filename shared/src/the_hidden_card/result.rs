@@ -0,0 +1,79 @@
+//! ### 结算结果
+//! 一手牌结束时由 [`GameState::game_end_check`] 生成，随 [`GameEvent::GameEnd`] 发给客户端，
+//! 除了每个座位的得分，还记录了队伍、完成顺序、记牌数和倍数，客户端据此展示输赢的原因。
+//!
+//! [`GameState::game_end_check`]: crate::the_hidden_card::state::GameState::game_end_check
+//! [`GameEvent::GameEnd`]: crate::event::GameEvent::GameEnd
+
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
+
+use crate::ClientId;
+use crate::the_hidden_card::state::GameMode;
+
+/// 游戏结束的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EndGameReason {
+    PlayerLeft { player_id: ClientId },
+    /// 正常结束，`winner` 为上游的座位索引
+    PlayerWon { winner: usize },
+}
+
+/// 结果类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumIter, Serialize, Deserialize)]
+pub enum Outcome {
+    #[strum(to_string = "双赢")]
+    DoubleWin,
+    #[strum(to_string = "单赢")]
+    SingleWin,
+    #[strum(to_string = "平局")]
+    Draw,
+    #[strum(to_string = "包牌成功")]
+    BlockWin,
+    #[strum(to_string = "包牌失败")]
+    BlockLoss,
+    /// 有玩家中途离开，提前结算
+    #[strum(to_string = "中途离开")]
+    Abandoned,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameResult {
+    pub reason: EndGameReason,
+    /// 游戏模式，叫牌模式下 `callee` 已经确定
+    pub mode: GameMode,
+    /// `teams[0]` 为叫牌方或包牌方，`teams[1]` 为另一方
+    pub teams: [Vec<usize>; 2],
+    /// 完成顺序，没有出完的座位按结算时补在后面
+    pub finish_order: Vec<usize>,
+    /// 两队的记牌数，与 `teams` 对应，单赢时包含最后一名的手牌和桌面上未结算的分数
+    pub team_points: [i32; 2],
+    pub outcome: Outcome,
+    pub base: i32,
+    /// 结算倍数，包含春天
+    pub multiplier: i32,
+    /// 春天：输的一方一张牌都没出
+    pub spring: bool,
+    /// 按完成顺序排列的 (座位索引, 得分)
+    pub scores: Vec<(usize, i32)>,
+}
+
+impl GameResult {
+    pub fn score_of(&self, seat_index: usize) -> i32 {
+        self.scores
+            .iter()
+            .find(|(index, _)| *index == seat_index)
+            .map_or(0, |(_, score)| *score)
+    }
+
+    /// `seat_index` 所在的队伍，0 为叫牌方或包牌方
+    pub fn team_of(&self, seat_index: usize) -> Option<usize> {
+        self.teams.iter().position(|team| team.contains(&seat_index))
+    }
+
+    /// 获胜的队伍，平局时为 `None`
+    pub fn winning_team(&self) -> Option<usize> {
+        let (index, _) = self.scores.iter().find(|(_, score)| *score > 0)?;
+        self.team_of(*index)
+    }
+}
@@ -0,0 +1,298 @@
+//! ### 规则设置
+//! 各地玩法不同，特殊牌、叫牌顺序、桌面记分、倍数和允许的牌型都放在 [`RuleSet`] 中，创建房间时选择。
+//!
+//! 默认值为原来的标准玩法：黑桃 7 叫牌，按 2 → A → K → Q 的顺序叫牌，双赢 ×2，包牌 ×3。
+//! 另外每个炸弹和春天都翻倍，倍数最高 16 倍。
+//! 牌局中有玩家离开时直接结算，离开的玩家扣 3 倍底分，其他每位玩家得 1 倍，见 [`AbandonRules`]。
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+use crate::cards::{Card, CardValue, Suit};
+use crate::the_hidden_card::combination::Combination;
+use crate::the_hidden_card::moves::legal_moves;
+
+/// 各项倍数和倍数上限允许的最大值
+pub const MAX_RULE_MULTIPLIER: i32 = 64;
+/// 叫牌顺序至少包含的点数个数
+pub const MIN_CALL_PRIORITY_VALUES: usize = 4;
+/// 离开时扣分和补偿允许的最大底分倍数
+pub const MAX_ABANDON_UNITS: i32 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSet {
+    /// 持有这张牌的玩家叫牌
+    pub special_card: Card,
+    /// 叫牌顺序，叫牌者叫第一个没有凑齐四张的点数
+    pub call_priority: Vec<CardValue>,
+    /// 桌面记分
+    pub table_points: TablePoints,
+    /// 双赢的倍数，单赢为 1 倍
+    pub double_win_multiplier: i32,
+    /// 包牌的倍数，包牌后计入当前倍数，包牌方与其他每位玩家之间按当前倍数结算
+    pub block_multiplier: i32,
+    /// 每打出一个炸弹（见 [`Combination::is_boom`]）当前倍数乘以这个值
+    pub bomb_multiplier: i32,
+    /// 春天的倍数：输的一方一张牌都没出时结算倍数乘以这个值
+    pub spring_multiplier: i32,
+    /// 倍数上限，不能超过 [`MAX_RULE_MULTIPLIER`]
+    pub max_multiplier: i32,
+    /// 允许的牌型
+    pub combinations: AllowedCombinations,
+    /// 牌局中有玩家离开时的处理
+    pub abandon: AbandonRules,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            special_card: Card::new(Suit::Spades, CardValue::Seven),
+            call_priority: vec![CardValue::Two, CardValue::Ace, CardValue::King, CardValue::Queen],
+            table_points: TablePoints::default(),
+            double_win_multiplier: 2,
+            block_multiplier: 3,
+            bomb_multiplier: 2,
+            spring_multiplier: 2,
+            max_multiplier: 16,
+            combinations: AllowedCombinations::default(),
+            abandon: AbandonRules::default(),
+        }
+    }
+}
+
+/// 每种牌型打出后计入桌面的分数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TablePoints {
+    pub single: i32,
+    pub pair: i32,
+    /// 顺子每张牌的分数
+    pub straight_per_card: i32,
+    pub three_of_a_kind: i32,
+    pub three_strait_pair: i32,
+    pub four_of_a_kind: i32,
+}
+
+impl Default for TablePoints {
+    fn default() -> Self {
+        Self {
+            single: 1,
+            pair: 2,
+            straight_per_card: 1,
+            three_of_a_kind: 3,
+            three_strait_pair: 6,
+            four_of_a_kind: 4,
+        }
+    }
+}
+
+/// 单张和对子总是允许的，其他牌型可以关闭
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AllowedCombinations {
+    pub straight: bool,
+    /// 顺子最短长度，不能小于 3
+    pub min_straight_len: usize,
+    pub three_of_a_kind: bool,
+    pub three_strait_pair: bool,
+    pub four_of_a_kind: bool,
+}
+
+impl Default for AllowedCombinations {
+    fn default() -> Self {
+        Self {
+            straight: true,
+            min_straight_len: 3,
+            three_of_a_kind: true,
+            three_strait_pair: true,
+            four_of_a_kind: true,
+        }
+    }
+}
+
+/// 牌局中离开的玩家超时没有回来时的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AbandonPolicy {
+    /// 机器人代打到这一手牌结束，按正常结果结算
+    BotTakeover,
+    /// 直接结束这一手牌，按 [`AbandonRules`] 的扣分和补偿结算
+    EndHand,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AbandonRules {
+    pub policy: AbandonPolicy,
+    /// 直接结束时离开的玩家扣除的底分倍数，再乘以当前倍数
+    pub penalty: i32,
+    /// 直接结束时其他每位玩家获得的底分倍数，再乘以当前倍数
+    pub compensation: i32,
+}
+
+impl Default for AbandonRules {
+    fn default() -> Self {
+        Self {
+            policy: AbandonPolicy::EndHand,
+            penalty: 3,
+            compensation: 1,
+        }
+    }
+}
+
+/// [`RuleSet::validate`] 拒绝规则的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum RuleError {
+    #[strum(to_string = "倍数必须在 1 到 64 之间")]
+    InvalidMultiplier,
+    #[strum(to_string = "叫牌顺序不能为空")]
+    EmptyCallPriority,
+    #[strum(to_string = "叫牌顺序至少要有 4 个不重复的点数")]
+    InvalidCallPriority,
+    #[strum(to_string = "顺子最短长度不能小于 3")]
+    InvalidStraightLen,
+    #[strum(to_string = "离开的扣分和补偿必须在 0 到 10 之间")]
+    InvalidAbandon,
+}
+
+impl RuleSet {
+    /// 检查规则是否可用，房间设置中的规则和服务器的规则文件都要通过检查
+    pub fn validate(&self) -> Result<(), RuleError> {
+        let multipliers = [
+            self.double_win_multiplier,
+            self.block_multiplier,
+            self.bomb_multiplier,
+            self.spring_multiplier,
+        ];
+        let valid_multiplier = |multiplier: &i32| (1..=MAX_RULE_MULTIPLIER).contains(multiplier);
+        if !multipliers.iter().all(valid_multiplier) || !valid_multiplier(&self.max_multiplier) {
+            return Err(RuleError::InvalidMultiplier);
+        }
+        if self.call_priority.is_empty() {
+            return Err(RuleError::EmptyCallPriority);
+        }
+        // 4 个点数共 16 张牌，13 张手牌不可能全部凑齐，叫牌者总有牌可叫
+        let distinct = self.call_priority.iter().collect::<HashSet<_>>().len();
+        if distinct != self.call_priority.len() || distinct < MIN_CALL_PRIORITY_VALUES {
+            return Err(RuleError::InvalidCallPriority);
+        }
+        if self.combinations.min_straight_len < 3 {
+            return Err(RuleError::InvalidStraightLen);
+        }
+        let valid_units = |units: i32| (0..=MAX_ABANDON_UNITS).contains(&units);
+        if !valid_units(self.abandon.penalty) || !valid_units(self.abandon.compensation) {
+            return Err(RuleError::InvalidAbandon);
+        }
+        Ok(())
+    }
+
+    /// 按规则分析牌型，不允许的牌型视为 [`Combination::Invalid`]
+    pub fn analyze(&self, cards: Vec<Card>) -> Combination {
+        let combo = Combination::analyze(cards);
+        if self.allows(&combo) { combo } else { Combination::Invalid }
+    }
+
+    pub fn allows(&self, combo: &Combination) -> bool {
+        let allowed = &self.combinations;
+        match combo {
+            Combination::Single(_) | Combination::Pair(_) => true,
+            Combination::Straight(cards) => allowed.straight && cards.len() >= allowed.min_straight_len,
+            Combination::ThreeOfAKind(_) => allowed.three_of_a_kind,
+            Combination::ThreeStraitPair(_) => allowed.three_strait_pair,
+            Combination::FourOfAKind(_) => allowed.four_of_a_kind,
+            Combination::Invalid => false,
+        }
+    }
+
+    /// 规则允许的所有可出牌型，见 [`legal_moves`]
+    pub fn legal_moves(&self, hands: &[Card], last: Option<&Combination>) -> Vec<Combination> {
+        legal_moves(hands, last)
+            .into_iter()
+            .filter(|combo| self.allows(combo))
+            .collect()
+    }
+
+    /// 当前倍数乘以 `factor`，不超过上限
+    pub fn multiply(&self, multiplier: i32, factor: i32) -> i32 {
+        multiplier.saturating_mul(factor).min(self.max_multiplier)
+    }
+
+    pub fn table_points(&self, combo: &Combination) -> i32 {
+        let points = &self.table_points;
+        match combo {
+            Combination::Single(_) => points.single,
+            Combination::Pair(_) => points.pair,
+            Combination::Straight(cards) => points.straight_per_card * cards.len() as i32,
+            Combination::ThreeOfAKind(_) => points.three_of_a_kind,
+            Combination::ThreeStraitPair(_) => points.three_strait_pair,
+            Combination::FourOfAKind(_) => points.four_of_a_kind,
+            Combination::Invalid => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::CardValue::*;
+    use crate::cards::Suit::*;
+
+    #[test]
+    fn test_disabled_combinations() {
+        let mut rules = RuleSet::default();
+        rules.combinations.three_of_a_kind = false;
+        rules.combinations.min_straight_len = 4;
+
+        let three = vec![Card::new(Hearts, Five), Card::new(Spades, Five), Card::new(Clubs, Five)];
+        let straight = vec![Card::new(Hearts, Five), Card::new(Hearts, Six), Card::new(Hearts, Seven)];
+        assert_eq!(rules.analyze(three.clone()), Combination::Invalid);
+        assert_eq!(rules.analyze(straight.clone()), Combination::Invalid);
+        assert_ne!(RuleSet::default().analyze(three), Combination::Invalid);
+
+        let mut hands = straight;
+        hands.extend([Card::new(Spades, Five), Card::new(Clubs, Five)]);
+        assert!(rules.legal_moves(&hands, None).iter().all(|combo| rules.allows(combo)));
+        assert!(!rules.legal_moves(&hands, None).iter().any(Combination::is_boom));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(RuleSet::default().validate(), Ok(()));
+
+        let invalid = [
+            (RuleSet { bomb_multiplier: 0, ..RuleSet::default() }, RuleError::InvalidMultiplier),
+            (RuleSet { max_multiplier: 0, ..RuleSet::default() }, RuleError::InvalidMultiplier),
+            (RuleSet { max_multiplier: 1000, ..RuleSet::default() }, RuleError::InvalidMultiplier),
+            (RuleSet { spring_multiplier: i32::MAX, ..RuleSet::default() }, RuleError::InvalidMultiplier),
+            (RuleSet { call_priority: vec![], ..RuleSet::default() }, RuleError::EmptyCallPriority),
+            (RuleSet { call_priority: vec![Two], ..RuleSet::default() }, RuleError::InvalidCallPriority),
+            (
+                RuleSet { call_priority: vec![Two, Ace, King, King], ..RuleSet::default() },
+                RuleError::InvalidCallPriority,
+            ),
+        ];
+        for (rules, error) in invalid {
+            assert_eq!(rules.validate(), Err(error));
+        }
+
+        let mut rules = RuleSet::default();
+        rules.combinations.min_straight_len = 2;
+        assert_eq!(rules.validate(), Err(RuleError::InvalidStraightLen));
+        let mut rules = RuleSet::default();
+        rules.abandon.penalty = -1;
+        assert_eq!(rules.validate(), Err(RuleError::InvalidAbandon));
+        let mut rules = RuleSet::default();
+        rules.abandon.compensation = MAX_ABANDON_UNITS + 1;
+        assert_eq!(rules.validate(), Err(RuleError::InvalidAbandon));
+    }
+
+    #[test]
+    fn test_multiply_is_capped() {
+        let rules = RuleSet::default();
+        assert_eq!(rules.multiply(4, 2), 8);
+        assert_eq!(rules.multiply(16, 2), 16);
+        assert_eq!(rules.multiply(i32::MAX, 2), 16);
+    }
+}
@@ -0,0 +1,329 @@
+//! ### 无界面对局模拟
+//! 不经过网络和 Bevy，直接用 [`Reducer`] 驱动 [`GameState`] 完成整局游戏，四个座位都由 [`Strategy`] 决策。
+//!
+//! 用于大量对局的统计：检查计分是否平衡，以及规则引擎是否会卡死。
+//! 卡死的对局会记录发牌种子，可以用服务器的 `DEAL_SEED` 重现。
+
+use std::collections::HashMap;
+use std::fmt;
+
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+use crate::cards::deal;
+use crate::event::GameEvent;
+use crate::the_hidden_card::result::Outcome;
+use crate::the_hidden_card::rules::RuleSet;
+use crate::the_hidden_card::state::{GameMode, GameState, Stage};
+use crate::the_hidden_card::strategy::{BotDifficulty, PlayerView, Strategy};
+use crate::{Player, Reducer};
+
+/// 出牌阶段的最大步数，超过视为卡死
+const MAX_PLAY_STEPS: usize = 1000;
+
+/// 对局卡死的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumIter)]
+pub enum DeadEnd {
+    /// 发牌或叫牌阶段的事件没有通过校验
+    #[strum(to_string = "开局失败")]
+    Setup,
+    /// 出牌阶段没有当前玩家
+    #[strum(to_string = "无人出牌")]
+    Stuck,
+    /// 策略给出的出牌或不要没有通过校验
+    #[strum(to_string = "不合法的出牌")]
+    IllegalMove,
+    /// 结算检查结束了对局但没有给出结果，即 `Stage::Ended(None)`
+    #[strum(to_string = "没有结算结果")]
+    NoResult,
+    /// 出牌超过 [`MAX_PLAY_STEPS`] 步仍未结束
+    #[strum(to_string = "超过步数上限")]
+    StepLimit,
+}
+
+/// 一局模拟的记录
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    /// 发牌种子
+    pub seed: u64,
+    pub mode: Option<GameMode>,
+    /// `None` 表示对局卡死，没有得到结果，原因见 `dead_end`
+    pub outcome: Option<Outcome>,
+    pub dead_end: Option<DeadEnd>,
+    /// 按完成顺序排列的 (座位索引, 得分)
+    pub result: Vec<(usize, i32)>,
+}
+
+impl GameRecord {
+    fn dead_end(seed: u64, state: &GameState, reason: DeadEnd) -> Self {
+        Self {
+            seed,
+            mode: state.mode.clone(),
+            outcome: None,
+            dead_end: Some(reason),
+            result: vec![],
+        }
+    }
+
+    /// 叫牌方（叫牌模式）或包牌方（包牌模式）是否获胜
+    pub fn is_declarer_win(&self) -> bool {
+        let declarer = match &self.mode {
+            Some(GameMode::HiddenAllies { caller, .. }) => *caller,
+            Some(GameMode::OneVsThree(blocker)) => *blocker,
+            None => return false,
+        };
+        self.result
+            .iter()
+            .any(|(index, score)| *index == declarer && *score > 0)
+    }
+
+    /// 本局赢家得分之和，即输家付出的总分
+    pub fn swing(&self) -> i32 {
+        self.result.iter().map(|(_, score)| *score).filter(|score| *score > 0).sum()
+    }
+}
+
+pub struct Simulator {
+    strategies: [Box<dyn Strategy>; 4],
+    rules: RuleSet,
+}
+
+impl Simulator {
+    pub fn new(strategies: [Box<dyn Strategy>; 4]) -> Self {
+        Self {
+            strategies,
+            rules: RuleSet::default(),
+        }
+    }
+
+    /// 使用指定规则模拟，默认为 [`RuleSet::default`]
+    pub fn with_rules(mut self, rules: RuleSet) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// 四个座位使用同样难度的机器人
+    pub fn with_difficulty(difficulty: BotDifficulty, seed: u64) -> Self {
+        Self::new(std::array::from_fn(|index| difficulty.strategy(seed + index as u64)))
+    }
+
+    /// 用 `seed` 发牌完成一局游戏
+    pub fn run(&mut self, seed: u64) -> GameRecord {
+        let mut state = GameState::with_rules(self.rules.clone());
+
+        for seat_index in 0..4 {
+            let player = Player {
+                id: seat_index as u64,
+                name: format!("{seat_index}"),
+                avatar: None,
+            };
+            apply(&mut state, &GameEvent::AssignSeats { player, seat_index });
+            apply(&mut state, &GameEvent::Ready { client_id: seat_index as u64 });
+        }
+        if !apply(&mut state, &GameEvent::ToDealCardStage) {
+            return GameRecord::dead_end(seed, &state, DeadEnd::Setup);
+        }
+        for (index, cards) in deal(seed, 0).into_iter().enumerate() {
+            apply(&mut state, &GameEvent::DealCards { client_id: index as u64, cards });
+            apply(&mut state, &GameEvent::DealCardsDone(index as u64));
+        }
+
+        let Some(caller) = state.get_caller_index() else {
+            return GameRecord::dead_end(seed, &state, DeadEnd::Setup);
+        };
+        apply(&mut state, &GameEvent::ToCallCardStage(caller));
+        let event = match self.call_or_block(&state, caller) {
+            Some(event) => event,
+            None => return GameRecord::dead_end(seed, &state, DeadEnd::Setup),
+        };
+        if !apply(&mut state, &event) {
+            return GameRecord::dead_end(seed, &state, DeadEnd::Setup);
+        }
+
+        for _ in 0..MAX_PLAY_STEPS {
+            let Some(current) = state.current_player_seat else {
+                return GameRecord::dead_end(seed, &state, DeadEnd::Stuck);
+            };
+            let view = PlayerView::new(&state, current);
+            let event = match self.strategies[current].play(&view) {
+                Some(cards) => GameEvent::PlayCards(current, cards),
+                None => GameEvent::Pass(current),
+            };
+            if !apply(&mut state, &event) {
+                return GameRecord::dead_end(seed, &state, DeadEnd::IllegalMove);
+            }
+
+            match state.game_end_check() {
+                Some(Stage::Ended(Some(result))) => {
+                    apply(&mut state, &GameEvent::GameEnd(result.clone()));
+                    return GameRecord {
+                        seed,
+                        mode: Some(result.mode),
+                        outcome: Some(result.outcome),
+                        dead_end: None,
+                        result: result.scores,
+                    };
+                },
+                Some(_) => return GameRecord::dead_end(seed, &state, DeadEnd::NoResult),
+                None => {},
+            }
+        }
+        GameRecord::dead_end(seed, &state, DeadEnd::StepLimit)
+    }
+
+    /// 从叫牌者开始依次询问是否包牌，都不包牌时由叫牌者叫牌
+    fn call_or_block(&mut self, state: &GameState, caller: usize) -> Option<GameEvent> {
+        for offset in 0..4 {
+            let seat_index = (caller + offset) % 4;
+            let view = PlayerView::new(state, seat_index);
+            if self.strategies[seat_index].should_block(&view) {
+                return Some(GameEvent::Blocking(seat_index));
+            }
+        }
+        let callable = state.callable_cards(caller)?;
+        let view = PlayerView::new(state, caller);
+        let card = self.strategies[caller].call_card(&view, &callable);
+        Some(GameEvent::CallCard { seat_index: caller, card })
+    }
+}
+
+/// 校验通过后应用事件，与服务器处理客户端事件的方式相同
+fn apply(state: &mut GameState, event: &GameEvent) -> bool {
+    if state.dispatch(event).is_err() {
+        return false;
+    }
+    state.reduce(event);
+    true
+}
+
+/// 按模式统计的局数与叫牌方/包牌方胜局数
+#[derive(Debug, Clone, Default)]
+pub struct ModeStats {
+    pub games: usize,
+    pub declarer_wins: usize,
+}
+
+impl ModeStats {
+    pub fn win_rate(&self) -> f64 {
+        percent(self.declarer_wins, self.games)
+    }
+}
+
+/// 多局模拟的汇总统计
+#[derive(Debug, Clone, Default)]
+pub struct SimulationStats {
+    pub games: usize,
+    pub hidden_allies: ModeStats,
+    pub one_vs_three: ModeStats,
+    pub outcomes: HashMap<Outcome, usize>,
+    /// 所有完成对局的 [`GameRecord::swing`] 之和
+    pub total_swing: i64,
+    /// 卡死对局的发牌种子和原因
+    pub dead_ends: Vec<(u64, DeadEnd)>,
+}
+
+impl SimulationStats {
+    pub fn add(&mut self, record: &GameRecord) {
+        self.games += 1;
+        let Some(outcome) = record.outcome else {
+            let reason = record.dead_end.unwrap_or(DeadEnd::Stuck);
+            self.dead_ends.push((record.seed, reason));
+            return;
+        };
+
+        let mode_stats = match record.mode {
+            Some(GameMode::OneVsThree(_)) => &mut self.one_vs_three,
+            _ => &mut self.hidden_allies,
+        };
+        mode_stats.games += 1;
+        if record.is_declarer_win() {
+            mode_stats.declarer_wins += 1;
+        }
+
+        *self.outcomes.entry(outcome).or_default() += 1;
+        self.total_swing += record.swing() as i64;
+    }
+
+    /// 因为 `reason` 卡死的对局的发牌种子
+    pub fn dead_end_seeds(&self, reason: DeadEnd) -> Vec<u64> {
+        self.dead_ends
+            .iter()
+            .filter(|(_, dead_end)| *dead_end == reason)
+            .map(|(seed, _)| *seed)
+            .collect()
+    }
+
+    /// 完成对局的平均分数变动
+    pub fn average_swing(&self) -> f64 {
+        let finished = self.games - self.dead_ends.len();
+        if finished == 0 {
+            return 0.0;
+        }
+        self.total_swing as f64 / finished as f64
+    }
+}
+
+impl fmt::Display for SimulationStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "对局数: {}", self.games)?;
+        writeln!(
+            f,
+            "叫牌: {} 局，叫牌方胜率 {:.1}%",
+            self.hidden_allies.games,
+            self.hidden_allies.win_rate()
+        )?;
+        writeln!(
+            f,
+            "包牌: {} 局，包牌方胜率 {:.1}%",
+            self.one_vs_three.games,
+            self.one_vs_three.win_rate()
+        )?;
+        for outcome in Outcome::iter() {
+            let count = self.outcomes.get(&outcome).copied().unwrap_or_default();
+            writeln!(f, "{outcome}: {count} 局 ({:.1}%)", percent(count, self.games))?;
+        }
+        writeln!(f, "平均分数变动: {:.2}", self.average_swing())?;
+        write!(f, "卡死: {} 局", self.dead_ends.len())?;
+        for reason in DeadEnd::iter() {
+            let seeds = self.dead_end_seeds(reason);
+            if !seeds.is_empty() {
+                write!(f, "\n  {reason}: {} 局，种子: {:?}", seeds.len(), seeds)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    count as f64 * 100.0 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulated_games_are_zero_sum() {
+        let games = 20;
+        let mut simulator = Simulator::with_difficulty(BotDifficulty::Normal, 0);
+        let mut stats = SimulationStats::default();
+        for seed in 0..games {
+            let record = simulator.run(seed);
+            assert_eq!(record.outcome.is_some(), record.dead_end.is_none());
+            if record.outcome.is_some() {
+                assert_eq!(record.result.len(), 4);
+                assert_eq!(record.result.iter().map(|(_, score)| score).sum::<i32>(), 0);
+            }
+            stats.add(&record);
+        }
+
+        assert_eq!(stats.games, games as usize);
+        let finished = stats.outcomes.values().sum::<usize>();
+        assert_eq!(finished + stats.dead_ends.len(), games as usize);
+        assert_eq!(stats.hidden_allies.games + stats.one_vs_three.games, finished);
+        assert!(stats.dead_ends.is_empty(), "{stats}");
+    }
+}
@@ -7,6 +7,8 @@
 //! 1. 单赢，队伍一名玩家为上游，一名玩家为下游，记牌数高的队伍判赢，赢的队伍每位玩家均得1分。
 //! 2. 双赢，队伍一名玩家为上游，一名玩家为中游，不记牌获得胜利，一人获得2分。
 //! 3. 包牌，任一玩家出完游戏结束，包牌玩家（A）出完，获得（3 x 3）= 9分，若其他任一玩家出完，则玩家（A）负9分。
+//!
+//! 以上为默认规则，特殊牌、叫牌顺序、记分和倍数可以通过 [`RuleSet`] 修改。
 
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -14,8 +16,9 @@ use std::collections::{HashMap, HashSet, VecDeque};
 use strum::IntoEnumIterator;
 
 use crate::cards::{Card, CardValue, Suit};
+use crate::event::GameEvent;
 pub use crate::the_hidden_card::prelude::*;
-use crate::{ClientId, Player};
+use crate::{ClientId, Player, Reducer};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PlayerSeat {
@@ -26,6 +29,10 @@ pub struct PlayerSeat {
     pub ready: bool, // 准备状态
     pub hands_ready: bool,
     pub player_connected: bool,
+    /// 托管中，由服务器代替玩家出牌，玩家重新连接或者取消托管后恢复
+    pub auto_play: bool,
+    /// 其他玩家的手牌对本地不可见，只同步数量，见 [`GameState::view_for`]
+    pub hidden_hands: Option<usize>,
 }
 
 impl Default for PlayerSeat {
@@ -40,6 +47,8 @@ impl Default for PlayerSeat {
             score: 0,
 
             player_connected: false,
+            auto_play: false,
+            hidden_hands: None,
         }
     }
 }
@@ -48,11 +57,16 @@ impl PlayerSeat {
     pub fn get_player(&self) -> Option<&Player> {
         self.player.as_ref()
     }
+
+    /// 手牌数量，手牌不可见时为同步过来的数量
+    pub fn hands_count(&self) -> usize {
+        self.hidden_hands.unwrap_or(self.hands.len())
+    }
 }
 
 impl PlayerSeat {
     fn add_coins(&mut self, coins: i32) {
-        self.coins += coins;
+        self.coins = self.coins.saturating_add(coins);
     }
 
     fn add_score(&mut self, score: i32) {
@@ -71,8 +85,31 @@ impl PlayerSeat {
         count == 4
     }
 
-    /// 检查并移除手牌
+    /// 隐藏手牌，只保留数量
+    fn hide_hands(&mut self) {
+        self.hidden_hands = Some(self.hands_count());
+        self.hands.clear();
+    }
+
+    /// 手牌中是否有这些牌（不能重复），手牌不可见时只检查数量
+    pub fn has_cards(&self, cards: &[Card]) -> bool {
+        if let Some(count) = self.hidden_hands {
+            return count >= cards.len();
+        }
+        let unique: HashSet<_> = cards.iter().collect();
+        unique.len() == cards.len() && cards.iter().all(|card| self.hands.contains(card))
+    }
+
+    /// 检查并移除手牌，手牌不可见时只减少数量
     fn remove_cards(&mut self, cards: &[Card]) -> Result<(), String> {
+        if let Some(count) = self.hidden_hands {
+            if count < cards.len() {
+                return Err(format!("玩家手牌不足: {}", count));
+            }
+            self.hidden_hands = Some(count - cards.len());
+            return Ok(());
+        }
+
         let hands: HashSet<_> = self.hands.iter().collect();
         for card in cards {
             if !hands.contains(card) {
@@ -103,22 +140,18 @@ impl PlayerSeat {
         suits
     }
 
-    pub fn get_callable_cards(&self) -> Option<Vec<Card>> {
-        if !self.has_full_of(CardValue::Two) {
-            return Some(self.get_complement_suit(CardValue::Two));
-        } else if !self.has_full_of(CardValue::Ace) {
-            return Some(self.get_complement_suit(CardValue::Ace));
-        } else if !self.has_full_of(CardValue::King) {
-            return Some(self.get_complement_suit(CardValue::King));
-        } else if !self.has_full_of(CardValue::Queen) {
-            return Some(self.get_complement_suit(CardValue::Queen));
-        }
-        None
+    /// 按叫牌顺序找到第一个没有凑齐四张的点数，可叫的牌为这个点数中自己没有的花色
+    pub fn get_callable_cards(&self, call_priority: &[CardValue]) -> Option<Vec<Card>> {
+        call_priority
+            .iter()
+            .find(|value| !self.has_full_of((*value).clone()))
+            .map(|value| self.get_complement_suit(value.clone()))
     }
 
     fn reset(&mut self) {
         self.score = 0;
         self.hands.clear();
+        self.hidden_hands = None;
         self.hands_ready = false;
     }
 
@@ -127,20 +160,29 @@ impl PlayerSeat {
     }
 }
 
+/// 等待玩家完成的操作，超时后由服务器按 [`GameState::timeout_events`] 代替执行
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PendingTurn {
+    /// 座位坐满后等待所有玩家准备
+    Ready,
+    CallCard(usize),
+    PlayCards(usize),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Stage {
     PreGame,                          // 等带玩家入座
     DealCards,                        // 发牌
     CallCard(usize),                  // 叫牌
     PlayCards,                        // 出牌
-    Ended(Option<Vec<(usize, i32)>>), // 游戏结束
+    Ended(Option<GameResult>),        // 游戏结束
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub enum GameMode {
     HiddenAllies {
         caller: usize,
-        callee: usize,
+        callee: Option<usize>, // 叫牌亮出之前，只有服务器和队友自己知道
         card: Card,
     }, // 暗叫组队
     OneVsThree(usize), // 包牌
@@ -150,7 +192,7 @@ pub enum GameMode {
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
 pub struct GameState {
     seats: [PlayerSeat; 4],
-    special_card: Card,
+    pub rules: RuleSet,
 
     pub mode: Option<GameMode>,
     pub stage: Stage,
@@ -160,13 +202,18 @@ pub struct GameState {
 
     pub last_played_set_index: Option<usize>,
     pub last_played_cards: Option<Combination>,
+    pub played_cards: Vec<Card>, // 本局已经打出的牌
     pub table_score_counter: i32,
 
     pub base: i32,
+    /// 当前倍数，包牌和炸弹会翻倍，见 [`RuleSet`]
     pub multiplayer: i32,
 
     finished_order: VecDeque<usize>,
-    // history: Vec<GameEvent>,
+
+    // 当前这一手牌的事件记录，只在服务器上记录，不参与同步
+    #[serde(skip)]
+    history: Vec<GameEvent>,
 }
 
 impl Default for GameState {
@@ -175,7 +222,7 @@ impl Default for GameState {
 
         Self {
             seats: sets,
-            special_card: Card::new(Suit::Spades, CardValue::Seven), //  特殊牌黑桃7喊牌
+            rules: RuleSet::default(),
 
             mode: None,
             stage: Stage::PreGame,
@@ -183,6 +230,7 @@ impl Default for GameState {
             current_player_seat: None,
             last_played_cards: None,
             last_played_set_index: None,
+            played_cards: Vec::new(),
             is_hidden_card_shown: false,
             table_score_counter: 0,
 
@@ -190,11 +238,20 @@ impl Default for GameState {
             multiplayer: 1,
 
             finished_order: VecDeque::new(),
+            history: Vec::new(),
         }
     }
 }
 
 impl GameState {
+    /// 使用指定规则的新牌局，创建房间时选择规则
+    pub fn with_rules(rules: RuleSet) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
     pub fn get_seats(&self) -> &[PlayerSeat; 4] {
         &self.seats
     }
@@ -203,6 +260,33 @@ impl GameState {
         *self = state.clone();
     }
 
+    /// 座位 `seat_index` 的玩家能看到的状态：其他座位的手牌只保留数量，暗叫的队友在叫牌亮出前隐藏
+    pub fn view_for(&self, seat_index: usize) -> GameState {
+        self.redacted(Some(seat_index))
+    }
+
+    /// 不在座位上的玩家能看到的状态，所有手牌都只保留数量
+    pub fn public_view(&self) -> GameState {
+        self.redacted(None)
+    }
+
+    fn redacted(&self, visible_seat: Option<usize>) -> GameState {
+        let mut state = self.clone();
+        state.history.clear();
+        for (index, seat) in state.seats.iter_mut().enumerate() {
+            if Some(index) != visible_seat {
+                seat.hide_hands();
+            }
+        }
+        if let Some(GameMode::HiddenAllies { callee, .. }) = &mut state.mode
+            && !state.is_hidden_card_shown
+            && *callee != visible_seat
+        {
+            *callee = None;
+        }
+        state
+    }
+
     pub fn get_seat_mut_by_id(&mut self, player_id: ClientId) -> Option<&mut PlayerSeat> {
         self.seats
             .iter_mut()
@@ -210,13 +294,33 @@ impl GameState {
             .next()
     }
 
-    // pub fn add_history(&mut self, event: GameEvent) {
-    //     self.history.push(event);
-    // }
-    //
-    // pub fn get_history(&self) -> &[GameEvent] {
-    //     &self.history
-    // }
+    /// 记录一个已通过校验的事件，需要在 [`Reducer::reduce`] 之前调用。
+    ///
+    /// 每手牌从 [`GameEvent::ToDealCardStage`] 开始一份新的记录，记录的第一个事件是开始前状态的快照
+    /// [`GameEvent::SyncState`]，所以任意一份记录都可以用 [`Self::replay`] 从默认状态重建。
+    /// 一手牌结束后记录会保留到下一手牌开始。
+    pub fn add_history(&mut self, event: GameEvent) {
+        if self.history.is_empty() || event == GameEvent::ToDealCardStage {
+            let mut snapshot = self.clone();
+            snapshot.history.clear();
+            self.history.clear();
+            self.history.push(GameEvent::SyncState(snapshot));
+        }
+        self.history.push(event);
+    }
+
+    pub fn get_history(&self) -> &[GameEvent] {
+        &self.history
+    }
+
+    /// 从默认状态依次应用事件，传入记录的前缀即可得到这一手牌任意时刻的状态
+    pub fn replay(events: &[GameEvent]) -> GameState {
+        let mut state = GameState::default();
+        for event in events {
+            state.reduce(event);
+        }
+        state
+    }
 
     fn get_active_set(&self) -> Option<PlayerSeat> {
         if let Some(index) = self.current_player_seat {
@@ -284,7 +388,7 @@ impl GameState {
 
     pub fn is_all_hands_ready(&self) -> bool {
         for seat in self.seats.iter() {
-            if !seat.ready {
+            if !seat.hands_ready {
                 return false;
             }
         }
@@ -294,32 +398,65 @@ impl GameState {
     pub fn assign_seat(&mut self, player: Player, seat_index: usize) {
         self.seats[seat_index] = PlayerSeat {
             player: Some(player),
+            player_connected: true,
             ..Default::default()
         };
     }
 
+    /// 玩家离开房间：牌局没有开始或已经结束时让出座位，牌局进行中保留座位并标记为离线
+    pub fn leave_seat(&mut self, player_id: ClientId) {
+        let Some(seat_index) = self.get_player_seat_index_by_id(player_id) else {
+            return;
+        };
+        if matches!(self.stage, Stage::PreGame | Stage::Ended(_)) {
+            self.seats[seat_index] = PlayerSeat::default();
+        } else {
+            self.seats[seat_index].player_connected = false;
+        }
+    }
+
+    pub fn set_coins(&mut self, seat_index: usize, coins: i32) {
+        if let Some(seat) = self.seats.get_mut(seat_index) {
+            seat.coins = coins;
+        }
+    }
+
+    pub fn set_auto_play(&mut self, seat_index: usize, enabled: bool) {
+        if let Some(seat) = self.seats.get_mut(seat_index) {
+            seat.auto_play = enabled;
+        }
+    }
+
+    /// 直接设置座位手牌，用于推演（见 [`crate::the_hidden_card::strategy`]）和测试
+    pub(crate) fn set_seat_hands(&mut self, seat_index: usize, hands: Vec<Card>) {
+        self.seats[seat_index].hands = hands;
+        self.seats[seat_index].hidden_hands = None;
+    }
+
+    /// 已经出完牌的座位，按出完的先后排列
+    pub fn finished_order(&self) -> &VecDeque<usize> {
+        &self.finished_order
+    }
+
     pub fn set_hands(&mut self, client_id: ClientId, hands: Vec<Card>) {
         if let Some(mut seat) = self.get_seat_mut_by_id(client_id) {
             seat.hands.clear();
             seat.hands.extend(hands);
+            seat.hidden_hands = None;
         }
     }
 
-    /// 获取可叫的牌，叫牌阶段
-    fn get_callable_cards(&self) -> Option<Vec<Card>> {
-        if let Stage::CallCard(caller_index) = self.stage {
-            let set = &self.seats[caller_index];
-            if !set.has_full_of(CardValue::Two) {
-                return Some(set.get_complement_suit(CardValue::Two));
-            } else if !set.has_full_of(CardValue::Ace) {
-                return Some(set.get_complement_suit(CardValue::Ace));
-            } else if !set.has_full_of(CardValue::King) {
-                return Some(set.get_complement_suit(CardValue::King));
-            } else if !set.has_full_of(CardValue::Queen) {
-                return Some(set.get_complement_suit(CardValue::Queen));
-            }
+    /// 其他玩家的发牌，只记录数量
+    pub fn set_hidden_hands(&mut self, client_id: ClientId, count: usize) {
+        if let Some(seat) = self.get_seat_mut_by_id(client_id) {
+            seat.hands.clear();
+            seat.hidden_hands = Some(count);
         }
-        None
+    }
+
+    /// 座位按当前规则可以叫的牌
+    pub fn callable_cards(&self, seat_index: usize) -> Option<Vec<Card>> {
+        self.seats[seat_index].get_callable_cards(&self.rules.call_priority)
     }
 
     /// 重置玩家席状态和游戏状态
@@ -331,6 +468,7 @@ impl GameState {
         self.is_hidden_card_shown = false;
         self.last_played_set_index = None;
         self.last_played_cards = None;
+        self.played_cards.clear();
         self.table_score_counter = 0;
         self.finished_order.clear();
         self.multiplayer = 1;
@@ -376,7 +514,7 @@ impl GameState {
         let caller_index = self
             .seats
             .iter()
-            .position(|set| set.hands.iter().any(|card| *card == self.special_card));
+            .position(|set| set.hands.iter().any(|card| *card == self.rules.special_card));
 
         let Some(caller_index) = caller_index else {
             return None;
@@ -393,13 +531,13 @@ impl GameState {
         let caller_index = self
             .seats
             .iter()
-            .position(|set| set.hands.iter().any(|card| *card == self.special_card));
+            .position(|set| set.hands.iter().any(|card| *card == self.rules.special_card));
 
         caller_index
     }
 
     pub fn seat_hands_has_special_card(&self, index: usize) -> bool {
-        self.seats[index].hands.contains(&self.special_card)
+        self.seats[index].hands.contains(&self.rules.special_card)
     }
 
     /// 第三步：当前状态：Stage::CallCard(caller_index) 执行后进入下一个状态：Stage::InGame
@@ -411,6 +549,7 @@ impl GameState {
         }
         self.mode = Some(GameMode::OneVsThree(player_set_index));
         self.current_player_seat = Some(player_set_index);
+        self.multiplayer = self.rules.multiply(self.multiplayer, self.rules.block_multiplier);
 
         // self.first = Some(player_set_index);
         self.stage = Stage::PlayCards;
@@ -419,6 +558,8 @@ impl GameState {
     /// 第三步：当前状态：Stage::CallCard(caller_index) 执行后进入下一个状态：Stage::InGame
     /// 设置游戏模式为 GameMode::HiddenAllies((caller_index, callee_index, call_card))
     /// 玩家叫牌后开始
+    ///
+    /// 客户端只能看到自己的手牌，看不到叫牌时 `callee` 为 `None`，等叫牌打出后再确定
     pub fn call_card_start(&mut self, caller_index: usize, call_card: Card) {
         if !matches!(self.stage, Stage::CallCard(_)) {
             return;
@@ -429,28 +570,34 @@ impl GameState {
             .iter()
             .position(|set| set.hands.iter().any(|card| *card == call_card));
 
-        if let Some(callee_index) = callee_index {
-            self.mode = Some(GameMode::HiddenAllies {
-                caller: caller_index,
-                callee: callee_index,
-                card: call_card,
-            });
-            self.stage = Stage::PlayCards;
-            self.current_player_seat = Some(caller_index);
-        } else {
-            self.mode = None;
-            self.stage = Stage::Ended(None);
-        }
+        self.mode = Some(GameMode::HiddenAllies {
+            caller: caller_index,
+            callee: callee_index,
+            card: call_card,
+        });
+        self.stage = Stage::PlayCards;
+        self.current_player_seat = Some(caller_index);
     }
 
-    pub fn can_play_cards(&self, cards: &Vec<Card>) -> Result<Combination, String> {
-        let combo = Combination::analyze(cards.clone());
+    /// 叫牌阶段叫牌者可以叫的牌
+    pub fn is_callable_card(&self, caller_index: usize, card: &Card) -> bool {
+        self.callable_cards(caller_index)
+            .is_some_and(|cards| cards.contains(card))
+    }
+
+    /// 列出座位当前按规则可以出的所有牌型，见 [`RuleSet::legal_moves`]
+    pub fn legal_moves(&self, seat_index: usize) -> Vec<Combination> {
+        self.rules.legal_moves(&self.seats[seat_index].hands, self.last_played_cards.as_ref())
+    }
+
+    pub fn can_play_cards(&self, cards: &Vec<Card>) -> Result<Combination, GameError> {
+        let combo = self.rules.analyze(cards.clone());
         if combo == Combination::Invalid {
-            return Err("无效牌型".to_string());
+            return Err(GameError::InvalidCombination);
         }
         if let Some(ref last_combo) = self.last_played_cards {
             if !combo.gt(last_combo) {
-                return Err("牌型太弱".to_string());
+                return Err(GameError::TooWeak);
             }
         }
         Ok(combo)
@@ -460,7 +607,7 @@ impl GameState {
     /// ⚠️做出改动后务必测试出牌逻辑，确保逻辑正确
     ///
     /// 这里如果最后一名玩家出牌完成后，不将其加入已完成列表。 因为在[`Self::next_player()`]中，下面的代码会导致无限循环：
-    /// ```
+    /// ```ignore
     /// fn next_player(&mut self) {
     ///     if self.finished_order.contains(&self.current_player_seat.unwrap())
     ///     {
@@ -471,12 +618,12 @@ impl GameState {
     /// * `player_set_index` - 玩家座位索引
     /// * `cards` - 玩家出牌
     pub fn play_cards(&mut self, player_set_index: usize, cards: Vec<Card>) -> Result<(), String> {
-        let combo = Combination::analyze(cards.clone());
+        let combo = self.rules.analyze(cards.clone());
 
         // 获取玩家手牌的可变引用
         let player_set = &mut self.seats[player_set_index];
 
-        if player_set.hands.is_empty() {
+        if player_set.hands_count() == 0 {
             return Err("玩家手牌为空".to_string());
         }
 
@@ -486,10 +633,11 @@ impl GameState {
                             caller,
                             callee,
                             card,
-                        }) = &self.mode
+                        }) = &mut self.mode
             {
                 if cards.contains(card) {
                     self.is_hidden_card_shown = true;
+                    *callee = Some(player_set_index);
                 }
             }
         }
@@ -502,13 +650,17 @@ impl GameState {
         }
 
 
-        if player_set.hands.is_empty() && self.finished_order.len() < 3 {
+        if player_set.hands_count() == 0 && self.finished_order.len() < 3 {
             self.finished_order.push_back(player_set_index);
         }
 
         // === 更新游戏状态 ===
         self.last_played_set_index = Some(player_set_index);
+        self.played_cards.extend(cards);
         self.add_table_score(&combo);
+        if combo.is_boom() {
+            self.multiplayer = self.rules.multiply(self.multiplayer, self.rules.bomb_multiplier);
+        }
         self.last_played_cards = Some(combo);
         self.next_player();
         Ok(())
@@ -554,27 +706,7 @@ impl GameState {
     }
 
     fn add_table_score(&mut self, combo: &Combination) {
-        match combo {
-            Combination::Single(_) => {
-                self.table_score_counter += 1;
-            },
-            Combination::Pair(_) => {
-                self.table_score_counter += 2;
-            },
-            Combination::Straight(cards) => {
-                self.table_score_counter += cards.len() as i32;
-            },
-            Combination::ThreeOfAKind(_) => {
-                self.table_score_counter += 3;
-            },
-            Combination::ThreeStraitPair(_) => {
-                self.table_score_counter += 6;
-            },
-            Combination::FourOfAKind(_) => {
-                self.table_score_counter += 4;
-            },
-            _ => (),
-        }
+        self.table_score_counter += self.rules.table_points(combo);
     }
 
     pub fn game_end_check(&mut self) -> Option<Stage> {
@@ -583,8 +715,8 @@ impl GameState {
         }
         if let Some(GameMode::HiddenAllies {
             caller,
-            callee,
-            card,
+            callee: Some(callee),
+            card: _,
         }) = &self.mode
         {
             if self.finished_order.len() >= 2 {
@@ -595,6 +727,8 @@ impl GameState {
                 let second = self.finished_order[1];
 
                 let mut score_map: HashMap<usize, i32> = HashMap::new();
+                let mut team_points = [self.collected_points(&team_one), self.collected_points(&team_two)];
+                let mut outcome = Outcome::DoubleWin;
 
                 //双赢
                 if (first == team_one[0] && second == team_one[1])
@@ -603,7 +737,7 @@ impl GameState {
                     self.finished_order.push_back(team_two[0]);
                     self.finished_order.push_back(team_two[1]);
 
-                    let score = self.base * 2 * self.multiplayer;
+                    let score = self.base.saturating_mul(self.rules.double_win_multiplier);
                     score_map.insert(first, score);
                     score_map.insert(second, score);
                     score_map.insert(team_two[0], -score);
@@ -614,12 +748,12 @@ impl GameState {
                     self.finished_order.push_back(team_one[0]);
                     self.finished_order.push_back(team_one[1]);
 
-                    let score = self.base * 2 * self.multiplayer;
+                    let score = self.base.saturating_mul(self.rules.double_win_multiplier);
                     score_map.insert(first, score);
                     score_map.insert(second, score);
                     score_map.insert(team_one[0], -score);
                     score_map.insert(team_one[1], -score);
-                } else if self.finished_order.len() == 3 && self.last_played_cards.is_none() {
+                } else if self.finished_order.len() == 3 {
                     // 单赢
                     let last = [0, 1, 2, 3]
                         .iter()
@@ -631,17 +765,22 @@ impl GameState {
                     };
                     self.finished_order.push_back(*last);
 
-                    let mut team_one_cards_num =
-                        self.seats[team_one[0]].score + self.seats[team_one[1]].score;
-                    let mut team_two_cards_num =
-                        self.seats[team_two[0]].score + self.seats[team_two[1]].score;
-
+                    let [team_one_cards_num, team_two_cards_num] = &mut team_points;
                     if team_one.contains(&last) {
-                        team_two_cards_num += self.seats[*last].hands.len() as i32;
+                        *team_two_cards_num += self.seats[*last].hands_count() as i32;
                     } else if team_two.contains(&last) {
-                        team_one_cards_num += self.seats[*last].hands.len() as i32;
+                        *team_one_cards_num += self.seats[*last].hands_count() as i32;
+                    }
+                    // 第三名玩家出完时桌面上的牌还没有结算，归最后出牌的玩家
+                    if let Some(last_played) = self.last_played_set_index {
+                        if team_one.contains(&last_played) {
+                            *team_one_cards_num += self.table_score_counter;
+                        } else {
+                            *team_two_cards_num += self.table_score_counter;
+                        }
                     }
-                    let score = self.base * self.multiplayer;
+                    let score = self.base;
+                    outcome = Outcome::SingleWin;
                     if team_one_cards_num > team_two_cards_num {
                         score_map.insert(team_one[0], score);
                         score_map.insert(team_one[1], score);
@@ -653,6 +792,7 @@ impl GameState {
                         score_map.insert(team_two[0], score);
                         score_map.insert(team_two[1], score);
                     } else {
+                        outcome = Outcome::Draw;
                         score_map.insert(team_one[0], 0);
                         score_map.insert(team_one[1], 0);
                         score_map.insert(team_two[0], 0);
@@ -661,40 +801,186 @@ impl GameState {
                 }
 
                 if !score_map.is_empty() {
-                    let result: Vec<(usize, i32)> = self
+                    let scores: Vec<(usize, i32)> = self
                         .finished_order
                         .iter()
                         .map(|index| (*index, *score_map.get(index).unwrap()))
                         .collect();
+                    let result = self.result(outcome, [team_one, team_two], team_points, scores);
                     return Some(Stage::Ended(Some(result)));
                 }
             }
         } else if let Some(GameMode::OneVsThree(block_index)) = &self.mode {
             if !self.finished_order.is_empty() {
+                let block_index = *block_index;
+                let team_one = vec![block_index];
                 let mut team_two: Vec<usize> = Vec::from(&[0, 1, 2, 3]);
-                team_two.retain(|x| x != block_index);
-
-                let mut result = Vec::new();
-                if *block_index == self.finished_order[0] {
-                    result.push((*block_index, self.base * 3 * 3 * self.multiplayer));
-                    result.push((team_two[0], -self.base * 3 * self.multiplayer));
-                    result.push((team_two[1], -self.base * 3 * self.multiplayer));
-                    result.push((team_two[2], -self.base * 3 * self.multiplayer));
+                team_two.retain(|x| *x != block_index);
+                let team_points = [self.collected_points(&team_one), self.collected_points(&team_two)];
+
+                let score = self.base;
+                let mut scores = Vec::new();
+                let outcome = if block_index == self.finished_order[0] {
+                    scores.push((block_index, score.saturating_mul(3)));
+                    scores.push((team_two[0], -score));
+                    scores.push((team_two[1], -score));
+                    scores.push((team_two[2], -score));
+                    Outcome::BlockWin
                 } else {
-                    result.push((team_two[0], self.base * 3 * self.multiplayer));
-                    result.push((team_two[1], self.base * 3 * self.multiplayer));
-                    result.push((team_two[2], self.base * 3 * self.multiplayer));
-                    result.push((*block_index, -self.base * 3 * 3 * self.multiplayer));
+                    scores.push((team_two[0], score));
+                    scores.push((team_two[1], score));
+                    scores.push((team_two[2], score));
+                    scores.push((block_index, -score.saturating_mul(3)));
+                    Outcome::BlockLoss
+                };
+                for (index, _) in &scores {
+                    if !self.finished_order.contains(index) {
+                        self.finished_order.push_back(*index);
+                    }
                 }
+                scores.sort_by_key(|(index, _)| self.finished_order.iter().position(|x| x == index));
+                let result = self.result(outcome, [team_one, team_two], team_points, scores);
                 return Some(Stage::Ended(Some(result)));
             }
         }
         None
     }
 
-    pub fn apply_score_result(&mut self, result: &Vec<(usize, i32)>) {
-        for (index, score) in result {
-            self.seats[*index].coins += score;
+    /// 玩家 `player_id` 中途离开时提前结算：离开的玩家扣分，其他每位玩家（包括队友）得到补偿，
+    /// 都按 [`RuleSet::abandon`] 的底分倍数再乘以当前倍数。只在出牌阶段结算，之前模式还没有确定
+    pub fn abandon_result(&self, player_id: ClientId) -> Option<GameResult> {
+        if self.stage != Stage::PlayCards {
+            return None;
+        }
+        let leaver = self.get_player_seat_index_by_id(player_id)?;
+        let mode = self.mode.clone()?;
+        let team_one = match &mode {
+            GameMode::HiddenAllies { caller, callee, .. } => {
+                [Some(*caller), *callee].into_iter().flatten().collect::<Vec<_>>()
+            },
+            GameMode::OneVsThree(block_index) => vec![*block_index],
+        };
+        let team_two = (0..4).filter(|index| !team_one.contains(index)).collect::<Vec<_>>();
+        let team_points = [self.collected_points(&team_one), self.collected_points(&team_two)];
+
+        // 没有出完的座位补在后面，离开的玩家排在最后
+        let finished = self.finished_order.iter().copied().filter(|index| *index != leaver);
+        let unfinished = (0..4).filter(|index| *index != leaver && !self.finished_order.contains(index));
+        let finish_order = finished.chain(unfinished).chain([leaver]).collect::<Vec<_>>();
+
+        let abandon = &self.rules.abandon;
+        let unit = self.base.saturating_mul(self.multiplayer);
+        let scores = finish_order
+            .iter()
+            .map(|index| {
+                let score = if *index == leaver { -abandon.penalty } else { abandon.compensation };
+                (*index, score.saturating_mul(unit))
+            })
+            .collect();
+        Some(GameResult {
+            reason: EndGameReason::PlayerLeft { player_id },
+            mode,
+            teams: [team_one, team_two],
+            finish_order,
+            team_points,
+            outcome: Outcome::Abandoned,
+            base: self.base,
+            multiplier: self.multiplayer,
+            spring: false,
+            scores,
+        })
+    }
+
+    /// 队伍已经收取的记牌数
+    fn collected_points(&self, team: &[usize]) -> i32 {
+        team.iter().map(|index| self.seats[*index].score).sum()
+    }
+
+    /// 生成结算结果，`scores` 为 1 倍时的得分，这里乘以结算倍数
+    fn result(
+        &self,
+        outcome: Outcome,
+        teams: [Vec<usize>; 2],
+        team_points: [i32; 2],
+        scores: Vec<(usize, i32)>,
+    ) -> GameResult {
+        let spring = self.is_spring(&scores);
+        let multiplier = if spring {
+            self.rules.multiply(self.multiplayer, self.rules.spring_multiplier)
+        } else {
+            self.multiplayer
+        };
+        let finish_order: Vec<usize> = self.finished_order.iter().copied().collect();
+        GameResult {
+            reason: EndGameReason::PlayerWon { winner: finish_order[0] },
+            mode: self.mode.clone().expect("game ended without mode"),
+            teams,
+            finish_order,
+            team_points,
+            outcome,
+            base: self.base,
+            multiplier,
+            spring,
+            scores: scores
+                .into_iter()
+                .map(|(index, score)| (index, score.saturating_mul(multiplier)))
+                .collect(),
+        }
+    }
+
+    /// 春天：输的一方一张牌都没出
+    fn is_spring(&self, scores: &[(usize, i32)]) -> bool {
+        let mut losers = scores.iter().filter(|(_, score)| *score < 0).peekable();
+        losers.peek().is_some() && losers.all(|(index, _)| self.seats[*index].hands_count() == 13)
+    }
+
+    pub fn apply_score_result(&mut self, result: &GameResult) {
+        for (index, score) in &result.scores {
+            self.seats[*index].add_coins(*score);
+        }
+    }
+
+    /// 当前在等待的操作，发牌阶段和座位没坐满时不等待
+    pub fn pending_turn(&self) -> Option<PendingTurn> {
+        match self.stage {
+            Stage::PreGame | Stage::Ended(_) => {
+                (!self.has_empty_seat() && !self.is_all_ready()).then_some(PendingTurn::Ready)
+            },
+            Stage::DealCards => None,
+            Stage::CallCard(index) => Some(PendingTurn::CallCard(index)),
+            Stage::PlayCards => self.current_player_seat.map(PendingTurn::PlayCards),
+        }
+    }
+
+    /// 等待超时后代替玩家执行的事件：
+    /// 没有准备的玩家自动准备，叫牌时叫第一张可叫的牌，
+    /// 出牌时跟牌自动不要，自由出牌时出最小的单张
+    pub fn timeout_events(&self, turn: PendingTurn) -> Vec<GameEvent> {
+        match turn {
+            PendingTurn::Ready => self
+                .seats
+                .iter()
+                .filter(|seat| !seat.ready)
+                .filter_map(|seat| seat.get_player())
+                .map(|player| GameEvent::Ready { client_id: player.id })
+                .collect(),
+            PendingTurn::CallCard(seat_index) => self
+                .callable_cards(seat_index)
+                .and_then(|cards| cards.first().cloned())
+                .map(|card| GameEvent::CallCard { seat_index, card })
+                .into_iter()
+                .collect(),
+            PendingTurn::PlayCards(seat_index) => {
+                if self.last_played_cards.is_some() {
+                    return vec![GameEvent::Pass(seat_index)];
+                }
+                // 自由出牌时第一个牌型就是最小的单张
+                self.legal_moves(seat_index)
+                    .first()
+                    .map(|combo| GameEvent::PlayCards(seat_index, combo.to_vec_cards()))
+                    .into_iter()
+                    .collect()
+            },
         }
     }
 }
@@ -702,7 +988,7 @@ impl GameState {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cards::{Card, CardValue::*, Suit::*};
+    use crate::cards::{Card, CardNumericValue, CardValue::*, Suit::*};
     use rand::rng;
     use rand::seq::IndexedRandom;
 
@@ -714,12 +1000,26 @@ mod tests {
         assert!(state.current_player_seat.is_none());
     }
 
+    /// 准备游戏并发牌，发牌由服务器按种子完成
+    fn dealt_state() -> GameState {
+        let mut state = GameState::default();
+        state.prepare_game();
+        state.to_deal_cards_stage();
+        for (index, hands) in crate::cards::deal(0, 0).into_iter().enumerate() {
+            state.seats[index].hands = hands;
+        }
+        state
+    }
+
     #[test]
     fn test_pre_start_flow() {
         let mut state = GameState::default();
 
         // 准备阶段
         state.prepare_game();
+        assert_eq!(state.stage, Stage::PreGame);
+
+        let state = dealt_state();
         assert_eq!(state.stage, Stage::DealCards);
 
         // 检查发牌情况
@@ -731,14 +1031,13 @@ mod tests {
         let has_special = state
             .seats
             .iter()
-            .any(|set| set.hands.contains(&state.special_card));
+            .any(|set| set.hands.contains(&state.rules.special_card));
         assert!(has_special);
     }
 
     #[test]
     fn test_call_card_stage_transition() {
-        let mut state = GameState::default();
-        state.prepare_game();
+        let mut state = dealt_state();
         let index = state.get_caller_index();
         state.to_call_card_stage(index.unwrap().clone());
         match state.stage {
@@ -747,7 +1046,7 @@ mod tests {
                 assert!(
                     state.seats[caller_index]
                         .hands
-                        .contains(&state.special_card)
+                        .contains(&state.rules.special_card)
                 );
             },
             _ => panic!("Should be in CallCard stage"),
@@ -756,8 +1055,7 @@ mod tests {
 
     #[test]
     fn test_blocking_start() {
-        let mut state = GameState::default();
-        state.prepare_game();
+        let mut state = dealt_state();
         let index = state.get_caller_index();
         state.to_call_card_stage(index.unwrap().clone());
 
@@ -776,14 +1074,42 @@ mod tests {
         assert_eq!(state.current_player_seat, Some(blocker_index));
     }
 
+    #[test]
+    fn test_bomb_and_spring_multiplier() {
+        let mut state = dealt_state();
+        state.to_call_card_stage(0);
+        state.blocking_start(0);
+        assert_eq!(state.multiplayer, 3);
+
+        let bomb = |value: CardValue| {
+            [Hearts, Spades, Clubs, Diamonds].map(|suit| Card::new(suit, value.clone())).to_vec()
+        };
+        state.seats[0].hands = [bomb(Five), bomb(Six)].concat();
+        assert!(state.play_cards(0, bomb(Five)).is_ok());
+        assert_eq!(state.multiplayer, 6);
+        (1..4).for_each(|_| state.pass());
+        assert!(state.play_cards(0, bomb(Six)).is_ok());
+        assert_eq!(state.multiplayer, 12);
+
+        // 其他玩家一张牌都没出，春天翻倍后达到 16 倍上限
+        let Some(Stage::Ended(Some(result))) = state.game_end_check() else {
+            panic!("game should end")
+        };
+        assert_eq!(result.outcome, Outcome::BlockWin);
+        assert!(result.spring);
+        assert_eq!(result.multiplier, 16);
+        assert_eq!(result.scores[0], (0, 48));
+        assert!(result.scores[1..].iter().all(|(_, score)| *score == -16));
+        assert_eq!(result.winning_team(), Some(0));
+    }
+
     #[test]
     fn test_call_card_start() {
-        let mut state = GameState::default();
-        state.prepare_game();
+        let mut state = dealt_state();
         let index = state.get_caller_index();
         state.to_call_card_stage(index.unwrap().clone());
 
-        let callable = state.get_callable_cards().unwrap();
+        let callable = state.callable_cards(index.unwrap()).unwrap();
         let call_card = callable.choose(&mut rng()).unwrap(); // 任意测试牌
 
         let Stage::CallCard(caller_index) = state.stage else {
@@ -811,11 +1137,12 @@ mod tests {
     fn test_play_card_validation() {
         let mut state = GameState::default();
         state.stage = Stage::PlayCards; // 设置为游戏中
+        state.current_player_seat = Some(0);
 
         // 构造合法牌型
         let valid_cards = vec![
             Card::new(Suit::Hearts, CardValue::Ten),
-            Card::new(Suit::Hearts, CardValue::Ten),
+            Card::new(Suit::Spades, CardValue::Ten),
         ];
 
         // 构造非法牌型
@@ -824,9 +1151,77 @@ mod tests {
             Card::new(Suit::Spades, CardValue::Jack),
         ];
 
+        state.seats[0].hands = vec![
+            Card::new(Suit::Hearts, CardValue::Ten),
+            Card::new(Suit::Spades, CardValue::Ten),
+            Card::new(Suit::Spades, CardValue::Jack),
+        ];
+
         // 验证出牌
+        assert!(state.can_play_cards(&valid_cards).is_ok());
+        assert!(state.can_play_cards(&invalid_cards).is_err());
+        assert_eq!(
+            state.validate(&GameEvent::PlayCards(1, valid_cards.clone())),
+            Err(GameError::NotYourTurn)
+        );
+        assert_eq!(
+            state.validate(&GameEvent::PlayCards(0, invalid_cards)),
+            Err(GameError::InvalidCombination)
+        );
+        let not_in_hand = vec![Card::new(Suit::Hearts, CardValue::Ace)];
+        assert_eq!(
+            state.validate(&GameEvent::PlayCards(0, not_in_hand)),
+            Err(GameError::CardsNotInHand)
+        );
+        assert_eq!(state.validate(&GameEvent::PlayCards(0, valid_cards.clone())), Ok(()));
         assert!(state.play_cards(0, valid_cards).is_ok());
-        assert!(state.play_cards(0, invalid_cards).is_err());
+        assert_eq!(state.seats[0].hands, vec![Card::new(Suit::Spades, CardValue::Jack)]);
+    }
+
+    #[test]
+    fn test_timeout_events() {
+        let mut state = GameState::default();
+        for index in 0..3 {
+            state.assign_seat(Player { id: index as u64, name: index.to_string(), avatar: None }, index);
+        }
+        // 座位没坐满时不等待准备
+        assert_eq!(state.pending_turn(), None);
+        state.assign_seat(Player { id: 3, name: "3".to_string(), avatar: None }, 3);
+        state.seats[1].ready = true;
+        assert_eq!(state.pending_turn(), Some(PendingTurn::Ready));
+        assert_eq!(state.timeout_events(PendingTurn::Ready).len(), 3);
+
+        let mut state = dealt_state();
+        let caller = state.get_caller_index().unwrap();
+        state.to_call_card_stage(caller);
+        let turn = state.pending_turn().unwrap();
+        assert_eq!(turn, PendingTurn::CallCard(caller));
+        let events = state.timeout_events(turn);
+        assert_eq!(events.len(), 1);
+        assert_eq!(state.validate(&events[0]), Ok(()));
+        state.reduce(&events[0]);
+
+        // 自由出牌时出最小的单张
+        let turn = state.pending_turn().unwrap();
+        let PendingTurn::PlayCards(leader) = turn else {
+            panic!("PlayCards expected");
+        };
+        let events = state.timeout_events(turn);
+        let GameEvent::PlayCards(_, cards) = &events[0] else {
+            panic!("PlayCards expected");
+        };
+        assert_eq!(cards.len(), 1);
+        let lowest = state.seats[leader].hands.iter().map(|card| card.value.int()).min();
+        assert_eq!(Some(cards[0].value.int()), lowest);
+        assert_eq!(state.validate(&events[0]), Ok(()));
+        state.reduce(&events[0]);
+
+        // 跟牌时自动不要
+        let turn = state.pending_turn().unwrap();
+        let PendingTurn::PlayCards(next) = turn else {
+            panic!("PlayCards expected");
+        };
+        assert_eq!(state.timeout_events(turn), vec![GameEvent::Pass(next)]);
     }
 
     #[test]
@@ -848,4 +1243,191 @@ mod tests {
         // 注意：金币和手牌不会被重置
         assert_eq!(set.coins, 5);
     }
+
+    #[test]
+    fn test_leave_seat() {
+        let mut state = GameState::default();
+        state.assign_seat(Player { id: 1, name: "1".to_string(), avatar: None }, 0);
+        state.assign_seat(Player { id: 2, name: "2".to_string(), avatar: None }, 1);
+        assert!(state.seats[0].player_connected);
+
+        // 没有开始时让出座位
+        state.leave_seat(1);
+        assert!(state.seat_is_empty(0));
+
+        // 牌局进行中保留座位
+        state.to_deal_cards_stage();
+        state.leave_seat(2);
+        assert!(!state.seat_is_empty(1));
+        assert!(!state.seats[1].player_connected);
+    }
+
+    #[test]
+    fn test_auto_play() {
+        let mut state = GameState::default();
+        state.assign_seat(Player { id: 1, name: "1".to_string(), avatar: None }, 0);
+
+        let enable = GameEvent::AutoPlay { seat_index: 0, enabled: true };
+        assert_eq!(state.dispatch(&enable), Ok(()));
+        state.reduce(&enable);
+        assert!(state.seats[0].auto_play);
+        // 已经在托管，空座位不能托管
+        assert_eq!(state.dispatch(&enable), Err(GameError::InvalidEvent));
+        assert_eq!(
+            state.dispatch(&GameEvent::AutoPlay { seat_index: 1, enabled: true }),
+            Err(GameError::NotSeated)
+        );
+
+        // 断线重连后收回控制
+        state.reduce(&GameEvent::PlayerDisconnected(1));
+        state.reduce(&GameEvent::PlayerConnected(1));
+        assert!(!state.seats[0].auto_play);
+    }
+
+    /// 四个座位发好牌并叫牌，叫牌者为 0 号座位
+    fn hidden_allies_state() -> GameState {
+        let mut state = GameState::default();
+        for (index, hands) in crate::cards::deal(0, 0).into_iter().enumerate() {
+            state.seats[index].hands = hands;
+        }
+        state.to_call_card_stage(0);
+        let card = state.callable_cards(0).unwrap()[0].clone();
+        state.call_card_start(0, card);
+        state
+    }
+
+    #[test]
+    fn test_abandon_result() {
+        let mut state = hidden_allies_state();
+        for index in 0..4 {
+            let player = Player { id: index as ClientId + 1, name: index.to_string(), avatar: None };
+            state.seats[index].player = Some(player);
+        }
+        let Some(GameMode::HiddenAllies { callee: Some(callee), .. }) = state.mode else {
+            panic!("Wrong game mode")
+        };
+
+        // 叫牌方离开，队友也得到补偿
+        let result = state.abandon_result(1).unwrap();
+        assert_eq!(result.reason, EndGameReason::PlayerLeft { player_id: 1 });
+        assert_eq!(result.outcome, Outcome::Abandoned);
+        assert_eq!(result.teams[0], vec![0, callee]);
+        assert_eq!(result.finish_order, vec![1, 2, 3, 0]);
+        assert_eq!(result.score_of(0), -3);
+        assert_eq!(result.score_of(callee), 1);
+        assert_eq!(result.scores.iter().map(|(_, score)| score).sum::<i32>(), 0);
+
+        // 包牌时按当前倍数结算
+        let mut state = GameState::default();
+        for index in 0..4 {
+            state.assign_seat(Player { id: index as ClientId + 1, name: index.to_string(), avatar: None }, index);
+        }
+        state.to_call_card_stage(0);
+        assert_eq!(state.abandon_result(4), None);
+        state.blocking_start(2);
+        let result = state.abandon_result(4).unwrap();
+        assert_eq!(result.teams, [vec![2], vec![0, 1, 3]]);
+        assert_eq!(result.score_of(2), 3);
+        assert_eq!(result.score_of(3), -9);
+        assert_eq!(state.abandon_result(9), None);
+    }
+
+    #[test]
+    fn test_view_for_hides_other_hands() {
+        let state = hidden_allies_state();
+        let Some(GameMode::HiddenAllies { callee: Some(callee), .. }) = state.mode else {
+            panic!("Wrong game mode")
+        };
+        let other = (1..4).find(|index| *index != callee).unwrap();
+
+        let view = state.view_for(other);
+        for (index, seat) in view.seats.iter().enumerate() {
+            assert_eq!(seat.hands_count(), 13);
+            assert_eq!(seat.hands.is_empty(), index != other);
+        }
+        // 叫牌亮出之前，其他玩家不知道队友是谁
+        assert!(matches!(view.mode, Some(GameMode::HiddenAllies { callee: None, .. })));
+        assert!(matches!(
+            state.view_for(callee).mode,
+            Some(GameMode::HiddenAllies { callee: Some(_), .. })
+        ));
+        assert!(state.public_view().seats.iter().all(|seat| seat.hands.is_empty()));
+    }
+
+    // 记录的事件可以重建每一步的状态
+    #[test]
+    fn test_replay_history() {
+        let mut state = GameState::default();
+        let apply = |state: &mut GameState, event: GameEvent| {
+            assert_eq!(state.validate(&event), Ok(()), "{event}");
+            state.add_history(event.clone());
+            state.reduce(&event);
+        };
+
+        for index in 0..4 {
+            let player = Player { id: index as u64, name: index.to_string(), avatar: None };
+            apply(&mut state, GameEvent::AssignSeats { player, seat_index: index });
+        }
+        apply(&mut state, GameEvent::ToDealCardStage);
+        for (index, cards) in crate::cards::deal(1, 0).into_iter().enumerate() {
+            apply(&mut state, GameEvent::DealCards { client_id: index as u64, cards });
+        }
+        let caller = state.get_caller_index().unwrap();
+        apply(&mut state, GameEvent::ToCallCardStage(caller));
+        apply(&mut state, GameEvent::Blocking(caller));
+        let cards = state.legal_moves(caller)[0].to_vec_cards();
+        apply(&mut state, GameEvent::PlayCards(caller, cards));
+
+        let history = state.get_history().to_vec();
+        // 新的一手牌从发牌前的快照开始
+        assert!(matches!(history[0], GameEvent::SyncState(_)));
+        assert_eq!(history[1], GameEvent::ToDealCardStage);
+
+        let mut expected = state.clone();
+        expected.history.clear();
+        assert_eq!(GameState::replay(&history), expected);
+
+        let before_play = GameState::replay(&history[..history.len() - 1]);
+        assert_eq!(before_play.stage, Stage::PlayCards);
+        assert_eq!(before_play.seats[caller].hands.len(), 13);
+    }
+
+    #[test]
+    fn test_play_cards_on_hidden_seat() {
+        let state = hidden_allies_state();
+        let mut view = state.view_for(1);
+        let cards = vec![state.seats[0].hands[0].clone()];
+
+        assert!(view.play_cards(0, cards.clone()).is_ok());
+        assert_eq!(view.seats[0].hands_count(), 12);
+        assert_eq!(view.played_cards, cards);
+        assert_eq!(view.current_player_seat, Some(1));
+    }
+
+    #[test]
+    fn test_single_win_counts_unsettled_table_points() {
+        // 第三名玩家出完时桌面上还有 15 分，归最后出牌的 1 号玩家
+        let mut state = GameState {
+            stage: Stage::PlayCards,
+            mode: Some(GameMode::HiddenAllies {
+                caller: 0,
+                callee: Some(2),
+                card: Card::new(Spades, Seven),
+            }),
+            finished_order: VecDeque::from([0, 1, 2]),
+            table_score_counter: 15,
+            last_played_set_index: Some(1),
+            last_played_cards: Some(Combination::Single(Card::new(Hearts, Five))),
+            ..Default::default()
+        };
+        state.seats[0].score = 20;
+        state.seats[1].score = 10;
+
+        let Some(Stage::Ended(Some(result))) = state.game_end_check() else {
+            panic!("game should end after the third player finishes");
+        };
+        assert_eq!(result.outcome, Outcome::SingleWin);
+        assert_eq!(result.team_points, [20, 25]);
+        assert_eq!(result.scores, vec![(0, -1), (1, 1), (2, -1), (3, 1)]);
+    }
 }
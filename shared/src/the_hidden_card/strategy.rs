@@ -0,0 +1,517 @@
+//! ### 机器人策略
+//! 策略只能看到玩家视角的信息 [`PlayerView`]：自己的手牌、其他玩家的手牌数量和已经打出的牌。
+//!
+//! 提供三种难度：
+//! 1. [`RandomStrategy`] 简单，在所有可出的牌型中随机选择。
+//! 2. [`GreedyStrategy`] 普通，出能压过桌面的最小牌型，保留炸弹。
+//! 3. [`MonteCarloStrategy`] 困难，按已出的牌随机还原其他玩家的手牌，用普通策略推演到游戏结束，选择得分最高的出牌。
+//!
+//! 机器人在服务器的主循环中同步决策，困难机器人每次决策的推演步数有上限，避免卡住其他房间。
+
+use std::array;
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
+
+use crate::cards::{Card, CardValue, Deck};
+use crate::the_hidden_card::state::{GameMode, GameState, Stage};
+
+/// 推演的最大步数，防止异常状态下死循环
+const MAX_ROLLOUT_STEPS: usize = 400;
+
+/// 困难机器人每次决策的采样次数
+const MONTE_CARLO_SAMPLES: usize = 32;
+
+/// 困难机器人每次决策最多推演的总步数，约为几十毫秒，达到后不再开始新的采样
+const MONTE_CARLO_STEP_BUDGET: usize = 4_000;
+
+/// 玩家视角的游戏状态，不包含其他玩家的手牌
+#[derive(Debug, Clone)]
+pub struct PlayerView {
+    pub seat_index: usize,
+    pub hands: Vec<Card>,
+    pub hands_count: [usize; 4],
+    /// 本局已经打出的牌
+    pub played_cards: Vec<Card>,
+    /// 去掉其他玩家手牌后的游戏状态
+    pub state: GameState,
+}
+
+impl PlayerView {
+    /// `state` 可以是完整的状态，也可以是该座位视角的状态（见 [`GameState::view_for`]）
+    pub fn new(state: &GameState, seat_index: usize) -> Self {
+        let state = state.view_for(seat_index);
+        let seats = state.get_seats();
+
+        Self {
+            seat_index,
+            hands: seats[seat_index].hands.clone(),
+            hands_count: array::from_fn(|index| seats[index].hands_count()),
+            played_cards: state.played_cards.clone(),
+            state,
+        }
+    }
+
+    /// 判断某个座位是否为已知的队友，暗叫模式下叫牌亮出之前只有持有叫牌的玩家知道队友是谁
+    pub fn is_ally(&self, seat_index: usize) -> bool {
+        if seat_index == self.seat_index {
+            return false;
+        }
+        match &self.state.mode {
+            Some(GameMode::OneVsThree(blocker)) => {
+                self.seat_index != *blocker && seat_index != *blocker
+            },
+            Some(GameMode::HiddenAllies { caller, callee: Some(callee), .. }) => {
+                let team = [*caller, *callee];
+                team.contains(&self.seat_index) == team.contains(&seat_index)
+            },
+            _ => false,
+        }
+    }
+
+    /// 随机还原其他玩家的手牌，还原的结果与各玩家手牌数量和已打出的牌一致
+    pub fn determinize(&self, rng: &mut StdRng) -> GameState {
+        let mut unknown: Vec<Card> = Deck::new()
+            .get()
+            .iter()
+            .filter(|card| !self.hands.contains(card) && !self.played_cards.contains(card))
+            .cloned()
+            .collect();
+        unknown.shuffle(rng);
+
+        // 叫牌还没亮出时，叫牌只能在叫牌者以外的玩家手里，先把它随机发给其中一个玩家
+        let hidden_card = match &self.state.mode {
+            Some(GameMode::HiddenAllies { caller, card, .. }) => {
+                let holders: Vec<usize> = (0..4)
+                    .filter(|index| *index != *caller && *index != self.seat_index)
+                    .filter(|index| self.hands_count[*index] > 0)
+                    .collect();
+                match (unknown.iter().position(|c| c == card), holders.choose(rng)) {
+                    (Some(position), Some(holder)) => Some((*holder, unknown.remove(position))),
+                    _ => None,
+                }
+            },
+            _ => None,
+        };
+
+        let mut state = self.state.clone();
+        for index in 0..4 {
+            if index == self.seat_index {
+                continue;
+            }
+            let mut count = self.hands_count[index];
+            let mut hands = Vec::with_capacity(count);
+            if let Some((_, card)) = hidden_card.as_ref().filter(|(holder, _)| *holder == index) {
+                hands.push(card.clone());
+                count -= 1;
+            }
+            let count = count.min(unknown.len());
+            hands.extend(unknown.split_off(unknown.len() - count));
+            state.set_seat_hands(index, hands);
+        }
+
+        if let (Some(GameMode::HiddenAllies { callee, .. }), Some((holder, _))) =
+            (&mut state.mode, hidden_card)
+        {
+            *callee = Some(holder);
+        }
+
+        state
+    }
+}
+
+/// 机器人策略
+pub trait Strategy: Send + Sync {
+    /// 叫牌阶段选择一张叫牌，`callable` 不为空
+    fn call_card(&mut self, view: &PlayerView, callable: &[Card]) -> Card;
+
+    /// 叫牌阶段是否包牌
+    fn should_block(&mut self, view: &PlayerView) -> bool;
+
+    /// 轮到自己出牌，返回 `None` 表示不要
+    fn play(&mut self, view: &PlayerView) -> Option<Vec<Card>>;
+}
+
+/// 机器人难度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Display, EnumIter)]
+pub enum BotDifficulty {
+    #[strum(to_string = "简单")]
+    Easy,
+    #[default]
+    #[strum(to_string = "普通")]
+    Normal,
+    #[strum(to_string = "困难")]
+    Hard,
+}
+
+impl BotDifficulty {
+    pub fn strategy(&self, seed: u64) -> Box<dyn Strategy> {
+        match self {
+            BotDifficulty::Easy => Box::new(RandomStrategy::new(seed)),
+            BotDifficulty::Normal => Box::new(GreedyStrategy),
+            BotDifficulty::Hard => Box::new(MonteCarloStrategy::new(seed, MONTE_CARLO_SAMPLES)),
+        }
+    }
+}
+
+/// 在所有可出的牌型（包括不要）中随机选择，不包牌
+pub struct RandomStrategy {
+    rng: StdRng,
+}
+
+impl RandomStrategy {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for RandomStrategy {
+    fn call_card(&mut self, _view: &PlayerView, callable: &[Card]) -> Card {
+        callable.choose(&mut self.rng).unwrap().clone()
+    }
+
+    fn should_block(&mut self, _view: &PlayerView) -> bool {
+        false
+    }
+
+    fn play(&mut self, view: &PlayerView) -> Option<Vec<Card>> {
+        candidates(view).choose(&mut self.rng).cloned().flatten()
+    }
+}
+
+/// 出能压过桌面的最小牌型，保留炸弹；不压队友的牌
+pub struct GreedyStrategy;
+
+impl GreedyStrategy {
+    /// 炸弹数量（三张及以上的点数）达到 3 个，并且至少有两张 2 时包牌
+    const BLOCK_BOOMS: usize = 3;
+    const BLOCK_TWOS: usize = 2;
+}
+
+impl Strategy for GreedyStrategy {
+    fn call_card(&mut self, _view: &PlayerView, callable: &[Card]) -> Card {
+        callable[0].clone()
+    }
+
+    fn should_block(&mut self, view: &PlayerView) -> bool {
+        let count = |value: &CardValue| view.hands.iter().filter(|card| card.value == *value).count();
+        let booms = view
+            .hands
+            .iter()
+            .map(|card| &card.value)
+            .filter(|value| count(value) >= 3)
+            .collect::<HashSet<_>>()
+            .len();
+        booms >= Self::BLOCK_BOOMS && count(&CardValue::Two) >= Self::BLOCK_TWOS
+    }
+
+    fn play(&mut self, view: &PlayerView) -> Option<Vec<Card>> {
+        let last = view.state.last_played_cards.as_ref();
+        let moves = view.state.rules.legal_moves(&view.hands, last);
+
+        let Some(last_index) = view.state.last_played_set_index.filter(|_| last.is_some()) else {
+            // 自由出牌，出最小的非炸弹牌型，只剩炸弹时出最小的炸弹
+            let combo = moves.iter().find(|combo| !combo.is_boom()).or(moves.first())?;
+            return Some(combo.to_vec_cards());
+        };
+
+        if view.is_ally(last_index) {
+            return None;
+        }
+
+        if let Some(combo) = moves.iter().find(|combo| !combo.is_boom()) {
+            return Some(combo.to_vec_cards());
+        }
+
+        // 对手快出完时才用炸弹
+        if view.hands_count[last_index] <= 3 {
+            return moves.first().map(|combo| combo.to_vec_cards());
+        }
+        None
+    }
+}
+
+/// 随机还原其他玩家手牌后，用 [`GreedyStrategy`] 推演到游戏结束，选择平均得分最高的出牌
+///
+/// 每次决策最多采样 `samples` 次，推演的总步数达到 `step_budget` 后不再开始新的采样，至少采样一次
+pub struct MonteCarloStrategy {
+    rng: StdRng,
+    samples: usize,
+    step_budget: usize,
+}
+
+impl MonteCarloStrategy {
+    pub fn new(seed: u64, samples: usize) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            samples: samples.max(1),
+            step_budget: MONTE_CARLO_STEP_BUDGET,
+        }
+    }
+
+    pub fn with_step_budget(mut self, step_budget: usize) -> Self {
+        self.step_budget = step_budget;
+        self
+    }
+
+    /// 对每个候选出牌推演，返回各候选的总得分和用掉的推演步数
+    fn evaluate(&mut self, view: &PlayerView, candidates: &[Option<Vec<Card>>]) -> (Vec<i32>, usize) {
+        let mut totals = vec![0; candidates.len()];
+        let mut steps = 0;
+        for _ in 0..self.samples {
+            let world = view.determinize(&mut self.rng);
+            for (total, candidate) in totals.iter_mut().zip(candidates.iter()) {
+                let mut state = world.clone();
+                match candidate {
+                    Some(cards) => {
+                        let _ = state.play_cards(view.seat_index, cards.clone());
+                    },
+                    None => state.pass(),
+                }
+                let (score, used) = rollout(state, view.seat_index);
+                *total += score;
+                steps += used;
+            }
+            if steps >= self.step_budget {
+                break;
+            }
+        }
+        (totals, steps)
+    }
+}
+
+impl Strategy for MonteCarloStrategy {
+    fn call_card(&mut self, view: &PlayerView, callable: &[Card]) -> Card {
+        GreedyStrategy.call_card(view, callable)
+    }
+
+    /// 包牌胜负分数相同，推演中包牌获胜超过六成时包牌
+    fn should_block(&mut self, view: &PlayerView) -> bool {
+        if !matches!(view.state.stage, Stage::CallCard(_)) {
+            return false;
+        }
+        let (mut wins, mut samples, mut steps) = (0, 0, 0);
+        while samples < self.samples && (samples == 0 || steps < self.step_budget) {
+            let mut state = view.determinize(&mut self.rng);
+            state.blocking_start(view.seat_index);
+            let (score, used) = rollout(state, view.seat_index);
+            wins += (score > 0) as usize;
+            samples += 1;
+            steps += used;
+        }
+        wins * 10 > samples * 6
+    }
+
+    fn play(&mut self, view: &PlayerView) -> Option<Vec<Card>> {
+        let mut candidates = candidates(view);
+        if candidates.len() <= 1 {
+            return candidates.pop().flatten();
+        }
+
+        let (totals, _) = self.evaluate(view, &candidates);
+
+        // 得分相同时选靠前的，即更小的牌型
+        let (best, _) = totals
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, total)| **total)
+            .unwrap();
+        candidates.swap_remove(best)
+    }
+}
+
+/// 所有可出的牌型，跟牌时最后加上不要（`None`）
+fn candidates(view: &PlayerView) -> Vec<Option<Vec<Card>>> {
+    let last = view.state.last_played_cards.as_ref();
+    let mut candidates: Vec<_> = view.state.rules.legal_moves(&view.hands, last)
+        .into_iter()
+        .map(|combo| Some(combo.to_vec_cards()))
+        .collect();
+    if last.is_some() || candidates.is_empty() {
+        candidates.push(None);
+    }
+    candidates
+}
+
+/// 所有玩家使用 [`GreedyStrategy`] 推演到游戏结束，返回 `seat_index` 的得分和推演的步数
+fn rollout(mut state: GameState, seat_index: usize) -> (i32, usize) {
+    for step in 0..MAX_ROLLOUT_STEPS {
+        if let Some(Stage::Ended(Some(result))) = state.game_end_check() {
+            return (result.score_of(seat_index), step);
+        }
+        let Some(current) = state.current_player_seat else {
+            return (0, step);
+        };
+        let view = PlayerView::new(&state, current);
+        match GreedyStrategy.play(&view) {
+            Some(cards) => {
+                let _ = state.play_cards(current, cards);
+            },
+            None => state.pass(),
+        }
+    }
+    (0, MAX_ROLLOUT_STEPS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Player;
+    use crate::cards::CardValue::*;
+    use crate::cards::Suit::*;
+    use crate::the_hidden_card::combination::Combination;
+
+    /// 四个座位都有玩家并发好牌，黑桃 7 的持有者叫了第一张可叫的牌
+    fn playing_state(seed: u64) -> GameState {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut cards = Deck::new().get().clone();
+        cards.shuffle(&mut rng);
+
+        let mut state = GameState::default();
+        for (index, hands) in cards.chunks(13).enumerate() {
+            let player = Player {
+                id: index as u64,
+                name: format!("{index}"),
+                avatar: None,
+            };
+            state.assign_seat(player, index);
+            state.set_seat_hands(index, hands.to_vec());
+        }
+        let caller = state.get_caller_index().unwrap();
+        state.to_call_card_stage(caller);
+        let card = state.callable_cards(caller).unwrap()[0].clone();
+        state.call_card_start(caller, card);
+        state
+    }
+
+    fn assert_legal(view: &PlayerView, play: &Option<Vec<Card>>) {
+        match play {
+            Some(cards) => {
+                assert!(cards.iter().all(|card| view.hands.contains(card)));
+                let combo = Combination::analyze(cards.clone());
+                assert_ne!(combo, Combination::Invalid);
+                if let Some(last) = &view.state.last_played_cards {
+                    assert!(combo.gt(last));
+                }
+            },
+            None => assert!(view.state.last_played_cards.is_some()),
+        }
+    }
+
+    // 视角中只有自己的手牌
+    #[test]
+    fn test_player_view_redacts_hands() {
+        let state = playing_state(1);
+        let view = PlayerView::new(&state, 2);
+
+        assert_eq!(view.hands, state.get_seats()[2].hands);
+        assert_eq!(view.hands_count, [13; 4]);
+        assert!(view.played_cards.is_empty());
+        for (index, seat) in view.state.get_seats().iter().enumerate() {
+            assert_eq!(seat.hands.is_empty(), index != 2);
+        }
+    }
+
+    // 还原的手牌数量一致，且与自己的手牌、已出的牌不重复
+    #[test]
+    fn test_determinize_is_consistent() {
+        let mut state = playing_state(2);
+        let current = state.current_player_seat.unwrap();
+        let cards = GreedyStrategy.play(&PlayerView::new(&state, current)).unwrap();
+        state.play_cards(current, cards).unwrap();
+
+        let view = PlayerView::new(&state, 3);
+        let world = view.determinize(&mut StdRng::seed_from_u64(0));
+
+        let mut all: HashSet<Card> = view.played_cards.iter().cloned().collect();
+        for (index, seat) in world.get_seats().iter().enumerate() {
+            assert_eq!(seat.hands.len(), view.hands_count[index]);
+            all.extend(seat.hands.iter().cloned());
+        }
+        assert_eq!(world.get_seats()[3].hands, view.hands);
+        assert_eq!(all.len(), 52);
+    }
+
+    // 普通机器人跟牌时不拆炸弹
+    #[test]
+    fn test_greedy_saves_booms() {
+        let mut state = playing_state(3);
+        let hands = vec![
+            Card::new(Spades, Five),
+            Card::new(Hearts, Five),
+            Card::new(Clubs, Five),
+            Card::new(Spades, Nine),
+        ];
+        let current = state.current_player_seat.unwrap();
+        state.set_seat_hands(current, hands);
+        state.last_played_set_index = Some((current + 3) % 4);
+        state.last_played_cards = Some(Combination::Single(Card::new(Spades, King)));
+        state.mode = Some(GameMode::OneVsThree(current));
+
+        assert_eq!(GreedyStrategy.play(&PlayerView::new(&state, current)), None);
+
+        state.last_played_cards = Some(Combination::Single(Card::new(Spades, Eight)));
+        assert_eq!(
+            GreedyStrategy.play(&PlayerView::new(&state, current)),
+            Some(vec![Card::new(Spades, Nine)])
+        );
+    }
+
+    // 每种难度都能只用合法的出牌打完一局
+    #[test]
+    fn test_strategies_finish_game() {
+        // 困难机器人减少采样次数，加快测试
+        let factories: [fn(u64) -> Box<dyn Strategy>; 3] = [
+            |seed| BotDifficulty::Easy.strategy(seed),
+            |seed| BotDifficulty::Normal.strategy(seed),
+            |seed| Box::new(MonteCarloStrategy::new(seed, 4)),
+        ];
+        for (round, factory) in factories.iter().enumerate() {
+            let mut state = playing_state(4);
+            let mut strategies: Vec<_> = (0..4).map(factory).collect();
+
+            let mut ended = false;
+            for _ in 0..MAX_ROLLOUT_STEPS {
+                if state.game_end_check().is_some() {
+                    ended = true;
+                    break;
+                }
+                let current = state.current_player_seat.unwrap();
+                let view = PlayerView::new(&state, current);
+                let play = strategies[current].play(&view);
+                assert_legal(&view, &play);
+                match play {
+                    Some(cards) => state.play_cards(current, cards).unwrap(),
+                    None => state.pass(),
+                }
+            }
+            assert!(ended, "strategy {round} did not finish the game");
+        }
+    }
+
+    // 推演步数达到上限后不再开始新的采样，但至少完成一次采样
+    #[test]
+    fn test_monte_carlo_step_budget() {
+        let state = playing_state(5);
+        let view = PlayerView::new(&state, state.current_player_seat.unwrap());
+        let candidates = candidates(&view);
+
+        let mut strategy = MonteCarloStrategy::new(5, MONTE_CARLO_SAMPLES).with_step_budget(0);
+        let (_, one_sample) = strategy.evaluate(&view, &candidates);
+        assert!(one_sample > 0);
+
+        let budget = one_sample * 3;
+        let mut strategy = MonteCarloStrategy::new(5, MONTE_CARLO_SAMPLES).with_step_budget(budget);
+        let (_, steps) = strategy.evaluate(&view, &candidates);
+        assert!(steps >= budget);
+        assert!(steps < budget + candidates.len() * MAX_ROLLOUT_STEPS);
+        assert!(steps < one_sample * MONTE_CARLO_SAMPLES / 2);
+    }
+}